@@ -0,0 +1,69 @@
+use std::{fs, path::Path};
+
+use boxercrab::binlog::{BinlogFileReader, Event, EventPayload, TableMapEvent};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+/// content of every binlog file in `tests/events`
+fn fixtures() -> Vec<(String, Vec<u8>)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests/events");
+    let mut fixtures: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            let data = fs::read(path.join("log.bin")).unwrap();
+            (name, data)
+        })
+        .collect();
+    fixtures.sort_by(|a, b| a.0.cmp(&b.0));
+    fixtures
+}
+
+fn decode_all(data: &[u8]) -> Vec<Event<EventPayload>> {
+    BinlogFileReader::new(data)
+        .unwrap()
+        .map(|event| event.unwrap().1)
+        .collect()
+}
+
+fn bench_decode(c: &mut Criterion) {
+    let fixtures = fixtures();
+    let mut group = c.benchmark_group("decode");
+    for (name, data) in &fixtures {
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_function(name, |b| b.iter(|| decode_all(data)));
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("rows");
+    for (name, data) in &fixtures {
+        let events = decode_all(data);
+        let mut table: Option<&TableMapEvent> = None;
+        let mut rows = vec![];
+        for event in &events {
+            match &event.payload {
+                EventPayload::TableMap(event) => table = Some(event),
+                EventPayload::Rows(event) => rows.push((table.unwrap(), event)),
+                _ => {}
+            }
+        }
+        if rows.is_empty() {
+            continue;
+        }
+        let len = rows.iter().map(|(_, event)| event.rows.len() as u64).sum();
+        group.throughput(Throughput::Bytes(len));
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for (table, event) in &rows {
+                    for row in event.rows(table).unwrap() {
+                        criterion::black_box(row.unwrap());
+                    }
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_decode);
+criterion_main!(benches);
@@ -0,0 +1,350 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    thread,
+    time::Duration,
+};
+
+use thiserror::Error;
+
+use crate::{
+    binlog::{
+        binlog_files, BinlogFileReader, Event, EventPayload, ReaderError, TransactionTracker,
+        BINLOG_MAGIC, EVENT_HEADER_LEN,
+    },
+    client::{Client, ClientError, Transport},
+    codec::{Int2, Int4},
+    connector::{ClientOpts, ComBinLogDump, BINLOG_DUMP_NON_BLOCK},
+};
+
+#[derive(Debug, Error)]
+pub enum BackupError {
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Client(#[from] ClientError),
+    #[error("local binlog file is corrupted: {0}")]
+    Reader(#[from] ReaderError),
+    #[error("invalid event from upstream")]
+    InvalidEvent,
+    #[error("upstream has no binlog file")]
+    NoBinlog,
+    #[error("binlog file {0} is too large to continue, positions are 32 bits in dump")]
+    FileTooLarge(String),
+}
+
+impl BackupError {
+    /// connection lost or io error, which is retried in `stop_never` mode
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::Io(_) | Self::Client(ClientError::Io(_)))
+    }
+}
+
+/// options of [`Backup`]
+#[derive(Debug, Clone)]
+pub struct BackupOpts {
+    pub upstream: ClientOpts,
+    /// local directory binlog files are written into
+    pub dir: PathBuf,
+    /// server id to register on upstream, must be unique among replicas
+    pub server_id: u32,
+    /// first binlog file to fetch when local directory is empty, first file listed by
+    /// `SHOW BINARY LOGS` of upstream by default
+    pub start_file: Option<String>,
+    /// keep waiting for new events and reconnect when connection is lost, otherwise stop
+    /// at the end of last binlog file
+    pub stop_never: bool,
+    /// wait before reconnecting in `stop_never` mode
+    pub reconnect_interval: Duration,
+}
+
+impl BackupOpts {
+    pub fn new(upstream: ClientOpts, dir: impl Into<PathBuf>) -> Self {
+        let server_id = upstream.server_id.unwrap_or(65535);
+        Self {
+            upstream,
+            dir: dir.into(),
+            server_id,
+            start_file: None,
+            stop_never: false,
+            reconnect_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+/// archives binlog files of upstream byte by byte like
+/// `mysqlbinlog --read-from-remote-server --raw`
+///
+/// files are followed across rotate events and written as received, last local file
+/// is continued after restart, file is fsynced at the end of every transaction.
+#[derive(Debug)]
+pub struct Backup {
+    opts: BackupOpts,
+}
+
+impl Backup {
+    pub fn new(opts: BackupOpts) -> Self {
+        Self { opts }
+    }
+
+    pub fn opts(&self) -> &BackupOpts {
+        &self.opts
+    }
+
+    /// connect upstream and archive binlog files, see [`Backup::run_with_retry`]
+    pub fn run(&self) -> Result<(), BackupError> {
+        self.run_with_retry(|_| {})
+    }
+
+    /// connect upstream and archive binlog files, in `stop_never` mode transient errors
+    /// are passed to `on_retry` before reconnecting, other errors are returned
+    pub fn run_with_retry(
+        &self,
+        mut on_retry: impl FnMut(&BackupError),
+    ) -> Result<(), BackupError> {
+        loop {
+            let result = Client::connect(self.opts.upstream.clone())
+                .map_err(BackupError::from)
+                .and_then(|client| self.run_with(client));
+            match result {
+                Err(e) if self.opts.stop_never && e.is_transient() => {
+                    on_retry(&e);
+                    thread::sleep(self.opts.reconnect_interval);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// archive binlog files by connected client, return when upstream ends the stream
+    pub fn run_with<S: Transport>(&self, mut client: Client<S>) -> Result<(), BackupError> {
+        fs::create_dir_all(&self.opts.dir)?;
+        client.query_drop("SET @master_binlog_checksum = @@global.binlog_checksum")?;
+        let checksum = first_column(&mut client, "SELECT @@global.binlog_checksum")?
+            .is_some_and(|v| v.eq_ignore_ascii_case("CRC32"));
+        let mut log = match LocalBinlog::resume(self.opts.dir.clone())? {
+            Some(log) => log,
+            None => {
+                let name = match &self.opts.start_file {
+                    Some(name) => name.clone(),
+                    None => first_column(&mut client, "SHOW BINARY LOGS")?
+                        .ok_or(BackupError::NoBinlog)?,
+                };
+                LocalBinlog::open(self.opts.dir.clone(), name)?
+            }
+        };
+        let flags = if self.opts.stop_never {
+            0
+        } else {
+            BINLOG_DUMP_NON_BLOCK
+        };
+        let dump = ComBinLogDump {
+            pos: Int4::from(
+                u32::try_from(log.len).map_err(|_| BackupError::FileTooLarge(log.name.clone()))?,
+            ),
+            flags: Int2::from(flags),
+            server_id: Int4::from(self.opts.server_id),
+            filename: log.name.clone(),
+        };
+        client.binlog_dump(&dump)?;
+        let mut tracker = TransactionTracker::new();
+        while let Some(mut data) = client.recv_binlog()? {
+            if data.len() < EVENT_HEADER_LEN {
+                return Err(BackupError::InvalidEvent);
+            }
+            let log_pos = u32::from_le_bytes([data[13], data[14], data[15], data[16]]);
+            // artificial events and events already written have unmatched position
+            let new = log_pos as u64 == log.len + data.len() as u64;
+            if new {
+                log.append(&data)?;
+            }
+            let event =
+                Event::decode_with(&mut data, checksum).map_err(|_| BackupError::InvalidEvent)?;
+            if new && tracker.update(&event.payload) {
+                log.sync()?;
+            }
+            if let EventPayload::Rotate(rotate) = event.payload {
+                if rotate.log != log.name {
+                    log.sync()?;
+                    log = LocalBinlog::open(self.opts.dir.clone(), rotate.log)?;
+                }
+            }
+        }
+        log.sync()?;
+        Ok(())
+    }
+}
+
+/// first column of first row as string
+pub(crate) fn first_column<S: Transport>(
+    client: &mut Client<S>,
+    sql: &str,
+) -> Result<Option<String>, ClientError> {
+    let row = client.query(sql)?.next().transpose()?;
+    Ok(row.and_then(|row| row.get(0).and_then(|v| v.as_str()).map(str::to_string)))
+}
+
+/// binlog file being written as received from upstream
+#[derive(Debug)]
+struct LocalBinlog {
+    name: String,
+    len: u64,
+    file: File,
+}
+
+impl LocalBinlog {
+    /// continue last file in `dir`, incomplete event at the end is truncated while
+    /// corrupted file is an error and left untouched
+    fn resume(dir: PathBuf) -> Result<Option<Self>, BackupError> {
+        let Some((name, _)) = binlog_files(&dir)?.pop() else {
+            return Ok(None);
+        };
+        let path = dir.join(&name);
+        // magic is written again if the file was created but not written
+        let len = if fs::metadata(&path)?.len() < BINLOG_MAGIC.len() as u64 {
+            BINLOG_MAGIC.len() as u64
+        } else {
+            let mut reader = BinlogFileReader::open(&path)?;
+            loop {
+                match reader.next_raw() {
+                    Ok(Some(_)) => {}
+                    Ok(None) | Err(ReaderError::Incomplete(_)) => break reader.offset(),
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        };
+        let mut log = Self::open(dir, name)?;
+        if log.len > len {
+            log.file.set_len(len)?;
+            log.len = len;
+        }
+        Ok(Some(log))
+    }
+
+    /// open or create binlog file, magic is written for new file
+    fn open(dir: PathBuf, name: String) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(&name))?;
+        let mut len = file.metadata()?.len();
+        if len < BINLOG_MAGIC.len() as u64 {
+            file.set_len(0)?;
+            file.write_all(&BINLOG_MAGIC)?;
+            len = BINLOG_MAGIC.len() as u64;
+        }
+        Ok(Self { name, file, len })
+    }
+
+    fn append(&mut self, event: &[u8]) -> io::Result<()> {
+        self.file.write_all(event)?;
+        self.len += event.len() as u64;
+        Ok(())
+    }
+
+    fn sync(&self) -> io::Result<()> {
+        self.file.sync_data()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        server::{FakeSource, FakeSourceOpts},
+        test_util::temp_dir,
+    };
+
+    #[test]
+    fn test_backup() {
+        let upstream_dir = temp_dir("backup-upstream");
+        let backup_dir = temp_dir("backup-local");
+        let file1 = include_bytes!("../../../tests/events/02_query/log.bin");
+        let file2 = include_bytes!("../../../tests/events/04_rotate/log.bin");
+        fs::write(upstream_dir.join("mysql_bin.000001"), file1).unwrap();
+        let addr = FakeSource::new(FakeSourceOpts::new(&upstream_dir))
+            .spawn("127.0.0.1:0")
+            .unwrap();
+        let upstream = FakeSource::client_opts(addr);
+        let backup = Backup::new(BackupOpts::new(upstream, &backup_dir));
+        backup.run().unwrap();
+        assert_eq!(
+            fs::read(backup_dir.join("mysql_bin.000001")).unwrap(),
+            file1
+        );
+
+        // resume after an incomplete event and new upstream file
+        let local2 = backup_dir.join("mysql_bin.000002");
+        fs::write(&local2, &file2[..file2.len() - 10]).unwrap();
+        fs::write(upstream_dir.join("mysql_bin.000002"), file2).unwrap();
+        backup.run().unwrap();
+        assert_eq!(
+            fs::read(backup_dir.join("mysql_bin.000001")).unwrap(),
+            file1
+        );
+        assert_eq!(fs::read(local2).unwrap(), file2);
+
+        fs::remove_dir_all(upstream_dir).unwrap();
+        fs::remove_dir_all(backup_dir).unwrap();
+    }
+
+    #[test]
+    fn test_stop_never_error() {
+        let upstream_dir = temp_dir("backup-error-upstream");
+        let backup_dir = temp_dir("backup-error-local");
+        let file1 = include_bytes!("../../../tests/events/02_query/log.bin");
+        fs::write(upstream_dir.join("mysql_bin.000001"), file1).unwrap();
+        let addr = FakeSource::new(FakeSourceOpts::new(&upstream_dir))
+            .spawn("127.0.0.1:0")
+            .unwrap();
+        let upstream = FakeSource::client_opts(addr);
+        // missing start file is reported instead of retried forever
+        let mut opts = BackupOpts::new(upstream, &backup_dir);
+        opts.stop_never = true;
+        opts.start_file = Some("mysql_bin.000009".into());
+        let mut retries = 0;
+        let err = Backup::new(opts)
+            .run_with_retry(|_| retries += 1)
+            .unwrap_err();
+        assert!(!err.is_transient(), "{err}");
+        assert_eq!(retries, 0);
+
+        fs::remove_dir_all(upstream_dir).unwrap();
+        fs::remove_dir_all(backup_dir).unwrap();
+    }
+
+    #[test]
+    fn test_resume_corrupted() {
+        let dir = temp_dir("backup-corrupted");
+        let data = include_bytes!("../../../tests/events/02_query/log.bin");
+        let path = dir.join("mysql_bin.000001");
+
+        // event size smaller than header
+        let mut corrupted = data.to_vec();
+        corrupted[BINLOG_MAGIC.len() + 9..BINLOG_MAGIC.len() + 13].copy_from_slice(&[5, 0, 0, 0]);
+        fs::write(&path, &corrupted).unwrap();
+        assert!(matches!(
+            LocalBinlog::resume(dir.clone()),
+            Err(BackupError::Reader(ReaderError::Decode { offset: 4, .. }))
+        ));
+        assert_eq!(fs::read(&path).unwrap(), corrupted);
+
+        let mut corrupted = data.to_vec();
+        corrupted[0] = 0;
+        fs::write(&path, &corrupted).unwrap();
+        assert!(matches!(
+            LocalBinlog::resume(dir.clone()),
+            Err(BackupError::Reader(ReaderError::InvalidMagic))
+        ));
+        assert_eq!(fs::read(&path).unwrap(), corrupted);
+
+        // empty file is started again
+        fs::write(&path, b"").unwrap();
+        let log = LocalBinlog::resume(dir.clone()).unwrap().unwrap();
+        assert_eq!(log.len, BINLOG_MAGIC.len() as u64);
+        assert_eq!(fs::read(&path).unwrap(), BINLOG_MAGIC);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,40 @@
+use std::{net::TcpListener, process::ExitCode, sync::Arc};
+
+use boxercrab::proxy::{Proxy, ProxyEvent, ProxyOpts};
+use clap::Parser;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "proxy",
+    about = "MySQL proxy which logs every command of proxied sessions as JSON lines"
+)]
+struct Args {
+    /// Address to listen on
+    #[arg(default_value = "127.0.0.1:3307")]
+    listen: String,
+
+    /// Address of MySQL server
+    #[arg(default_value = "127.0.0.1:3306")]
+    upstream: String,
+
+    /// Don't offer TLS to clients, so commands of every session are logged
+    #[arg(long)]
+    no_tls: bool,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let listener = match TcpListener::bind(&args.listen) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("failed to listen on {}: {e}", args.listen);
+            return ExitCode::FAILURE;
+        }
+    };
+    eprintln!("proxy {} -> {}", args.listen, args.upstream);
+    let mut opts = ProxyOpts::new(args.upstream);
+    opts.tls_passthrough = !args.no_tls;
+    let logger = |event: ProxyEvent| println!("{}", event.to_json());
+    Arc::new(Proxy::new(opts, logger)).run(listener);
+    ExitCode::SUCCESS
+}
@@ -1,16 +1,208 @@
+use std::{fs, io, path::Path};
+
+use bytes::{BufMut, Bytes, BytesMut};
 use parse_tool::InputBuf;
 
-use crate::codec::{Decode, DecodeError, Int1, Int2, Int4, Int8};
+use crate::codec::{Decode, DecodeError, Encode, Int1, Int2, Int4};
+
+mod control;
+pub use control::*;
+mod gtid;
+pub use gtid::*;
+mod load;
+pub use load::*;
+mod rows;
+pub use rows::*;
+mod statement;
+pub use statement::*;
+mod filter;
+pub use filter::*;
+mod reader;
+pub use reader::*;
+mod writer;
+pub use writer::*;
+
+/// magic bytes at the beginning of every binlog file
+pub const BINLOG_MAGIC: [u8; 4] = [0xfe, b'b', b'i', b'n'];
+
+/// length of v4 event header
+pub const EVENT_HEADER_LEN: usize = 19;
+
+/// length of CRC32 checksum appended to event when `binlog_checksum=CRC32`
+pub const CHECKSUM_LEN: usize = 4;
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_replication_binlog_event.html)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum EventType {
+    Unknown = 0,
+    StartV3 = 1,
+    Query = 2,
+    Stop = 3,
+    Rotate = 4,
+    IntVar = 5,
+    Load = 6,
+    Slave = 7,
+    CreateFile = 8,
+    AppendBlock = 9,
+    ExecLoad = 10,
+    DeleteFile = 11,
+    NewLoad = 12,
+    Rand = 13,
+    UserVar = 14,
+    FormatDescription = 15,
+    Xid = 16,
+    BeginLoadQuery = 17,
+    ExecuteLoadQuery = 18,
+    TableMap = 19,
+    WriteRowsV1 = 23,
+    UpdateRowsV1 = 24,
+    DeleteRowsV1 = 25,
+    Incident = 26,
+    Heartbeat = 27,
+    Ignorable = 28,
+    RowsQuery = 29,
+    WriteRows = 30,
+    UpdateRows = 31,
+    DeleteRows = 32,
+    Gtid = 33,
+    AnonymousGtid = 34,
+    PreviousGtids = 35,
+    TransactionContext = 36,
+    ViewChange = 37,
+    XaPrepare = 38,
+    PartialUpdateRows = 39,
+    TransactionPayload = 40,
+    HeartbeatV2 = 41,
+    GtidTagged = 42,
+}
+
+impl TryFrom<u8> for EventType {
+    type Error = DecodeError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        let ty = match value {
+            0 => Self::Unknown,
+            1 => Self::StartV3,
+            2 => Self::Query,
+            3 => Self::Stop,
+            4 => Self::Rotate,
+            5 => Self::IntVar,
+            6 => Self::Load,
+            7 => Self::Slave,
+            8 => Self::CreateFile,
+            9 => Self::AppendBlock,
+            10 => Self::ExecLoad,
+            11 => Self::DeleteFile,
+            12 => Self::NewLoad,
+            13 => Self::Rand,
+            14 => Self::UserVar,
+            15 => Self::FormatDescription,
+            16 => Self::Xid,
+            17 => Self::BeginLoadQuery,
+            18 => Self::ExecuteLoadQuery,
+            19 => Self::TableMap,
+            23 => Self::WriteRowsV1,
+            24 => Self::UpdateRowsV1,
+            25 => Self::DeleteRowsV1,
+            26 => Self::Incident,
+            27 => Self::Heartbeat,
+            28 => Self::Ignorable,
+            29 => Self::RowsQuery,
+            30 => Self::WriteRows,
+            31 => Self::UpdateRows,
+            32 => Self::DeleteRows,
+            33 => Self::Gtid,
+            34 => Self::AnonymousGtid,
+            35 => Self::PreviousGtids,
+            36 => Self::TransactionContext,
+            37 => Self::ViewChange,
+            38 => Self::XaPrepare,
+            39 => Self::PartialUpdateRows,
+            40 => Self::TransactionPayload,
+            41 => Self::HeartbeatV2,
+            42 => Self::GtidTagged,
+            _ => return Err(DecodeError::InvalidData),
+        };
+        Ok(ty)
+    }
+}
+
+/// checksum algorithm declared by a format description event, 0 is off and 1 is CRC32
+///
+/// `event` is the whole event including header, return `None` if server is older than
+/// 5.6.1 which has no checksum support
+pub fn format_desc_checksum(event: &[u8]) -> Option<u8> {
+    // binlog version(2) + server version(50)
+    let version = event.get(EVENT_HEADER_LEN + 2..EVENT_HEADER_LEN + 52)?;
+    let version = version.split(|b| *b == 0).next()?;
+    let version = std::str::from_utf8(version).ok()?;
+    if !has_checksum_alg(version) {
+        return None;
+    }
+    event
+        .len()
+        .checked_sub(CHECKSUM_LEN + 1)
+        .map(|idx| event[idx])
+}
+
+/// whether format description event of server carries checksum algorithm, since 5.6.1
+fn has_checksum_alg(server_version: &str) -> bool {
+    let mut nums = server_version
+        .split(|c: char| !c.is_ascii_digit())
+        .take(3)
+        .map(|n| n.parse::<u32>().unwrap_or_default());
+    let version = (
+        nums.next().unwrap_or_default(),
+        nums.next().unwrap_or_default(),
+        nums.next().unwrap_or_default(),
+    );
+    version >= (5, 6, 1)
+}
+
+/// files with numeric extension in `dir` and their size, in name order
+pub fn binlog_files(dir: &Path) -> io::Result<Vec<(String, u64)>> {
+    let mut files = vec![];
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let numeric = name
+            .rsplit_once('.')
+            .is_some_and(|(_, ext)| !ext.is_empty() && ext.bytes().all(|b| b.is_ascii_digit()));
+        let meta = entry.metadata()?;
+        if numeric && meta.is_file() {
+            files.push((name, meta.len()));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// complete event starts at `offset`
+pub(crate) fn next_event(data: &[u8], offset: usize) -> Option<&[u8]> {
+    let header = data.get(offset..offset + EVENT_HEADER_LEN)?;
+    let size = u32::from_le_bytes(header[9..13].try_into().ok()?) as usize;
+    if size < EVENT_HEADER_LEN {
+        return None;
+    }
+    data.get(offset..offset + size)
+}
 
 bitflags::bitflags! {
     /// https://dev.mysql.com/doc/dev/mysql-server/latest/group__group__cs__binglog__event__header__flags.html
     #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-    #[cfg_attr(feature="serde", serde::Serialize, serde::DeSerialize)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct EventHeaderFlag: u16 {
+        /// binlog file is in use, only set on format description event
+        const LOG_EVENT_BINLOG_IN_USE_F = 0x1;
+        /// deprecated, rotate is forced
+        const LOG_EVENT_FORCED_ROTATE_F = 0x2;
         /// If the query depends on the thread (for example: TEMPORARY TABLE)
         const LOG_EVENT_THREAD_SPECIFIC_F=   0x4;
         /// Suppress the generation of 'USE' statements before the actual statement
         const LOG_EVENT_SUPPRESS_USE_F   =0x8;
+        /// deprecated, table map version is updated
+        const LOG_EVENT_UPDATE_TABLE_MAP_VERSION_F = 0x10;
         /// Artificial events are created arbitrarily and not written to binary log
         const LOG_EVENT_ARTIFICIAL_F =    0x20;
         /// Events with this flag set are created by slave IO thread and written to relay log
@@ -27,12 +219,12 @@ bitflags::bitflags! {
 impl<I: InputBuf> Decode<I> for EventHeaderFlag {
     fn decode(input: &mut I) -> Result<Self, crate::codec::DecodeError> {
         let flags = Int2::decode(input)?;
-        Self::from_bits(flags.int()).ok_or(DecodeError::InvalidData)
+        Ok(Self::from_bits_retain(flags.int()))
     }
 }
 
 #[derive(Debug, Clone)]
-#[cfg_attr(feature = "serde", serde::Serialize, serde::DeSerialize)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EventHeader {
     pub timestamp: Int4,
     pub event_type: Int1,
@@ -62,118 +254,450 @@ impl<I: InputBuf> Decode<I> for EventHeader {
     }
 }
 
+impl Encode for EventHeader {
+    fn encode(&self, buf: &mut BytesMut) {
+        self.timestamp.encode(buf);
+        self.event_type.encode(buf);
+        self.server_id.encode(buf);
+        self.event_size.encode(buf);
+        self.log_pos.encode(buf);
+        Int2::from(self.flags.bits()).encode(buf);
+    }
+}
+
 #[derive(Debug, Clone)]
-#[cfg_attr(feature = "serde", serde::Serialize, serde::DeSerialize)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EventRaw {
     pub header: EventHeader,
     pub payload: Vec<u8>,
 }
 
 #[derive(Debug, Clone)]
-#[cfg_attr(feature = "serde", serde::Serialize, serde::DeSerialize)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Event<P> {
     pub header: EventHeader,
     pub payload: P,
 }
 
-#[derive(Debug, Clone)]
-#[cfg_attr(feature = "serde", serde::Serialize, serde::DeSerialize)]
-pub struct QueryEvent {
-    /// thread id
-    pub slave_proxy_id: Int4,
-    pub exec_time: Int4,
-    pub schema_len: Int1,
-    pub error_code: Int2,
-    pub status_vars_length: Int2,
-}
-
-pub enum QueryStatusVar {
-    QFlags2Code(),
+/// decoded payload of an event, event type is kept in [`EventHeader`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventPayload {
+    Query(QueryEvent),
+    Stop(StopEvent),
+    Rotate(RotateEvent),
+    IntVar(IntVarEvent),
+    AppendBlock(AppendBlockEvent),
+    DeleteFile(DeleteFileEvent),
+    Rand(RandEvent),
+    UserVar(UserVarEvent),
+    FormatDescription(FormatDescriptionEvent),
+    Xid(XidEvent),
+    BeginLoadQuery(AppendBlockEvent),
+    ExecuteLoadQuery(ExecuteLoadQueryEvent),
+    TableMap(TableMapEvent),
+    /// write, update and delete rows events of v1 and v2, and partial update rows event
+    Rows(RowsEvent),
+    Incident(IncidentEvent),
+    Heartbeat(HeartbeatEvent),
+    RowsQuery(RowsQueryEvent),
+    /// GTID and anonymous GTID event
+    Gtid(GtidEvent),
+    PreviousGtids(GtidSet),
+    XaPrepare(XaPrepareEvent),
+    /// payload of obsolete or not yet supported event types
+    Raw(Bytes),
 }
 
-bitflags::bitflags! {
-    /// bit mask of flags that are usually set with the SET command
+impl EventPayload {
+    /// decode payload of event type `ty`, checksum should be stripped from input
     ///
-    /// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_replication_binlog_event.html#sect_protocol_replication_event_query_00)
-    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-    #[cfg_attr(feature="serde", serde::Serialize, serde::DeSerialize)]
-    pub struct QFlag2Code : u32 {
-        const OPTION_AUTO_IS_NULL =  0x00004000;
-        const OPTION_NOT_AUTOCOMMIT =  0x00080000;
-        const OPTION_NO_FOREIGN_KEY_CHECKS =  0x04000000;
-        const OPTION_RELAXED_UNIQUE_CHECKS =  0x08000000;
+    /// queries, rows and other large fields are sliced from `input` without copying.
+    pub fn decode_with(ty: u8, input: &mut BytesMut) -> Result<Self, DecodeError> {
+        let Ok(event_type) = EventType::try_from(ty) else {
+            return Ok(Self::Raw(input.split().freeze()));
+        };
+        let payload = match event_type {
+            EventType::Query => Self::Query(QueryEvent::decode(input)?),
+            EventType::Stop => Self::Stop(StopEvent::decode(input)?),
+            EventType::Rotate => Self::Rotate(RotateEvent::decode(input)?),
+            EventType::IntVar => Self::IntVar(IntVarEvent::decode(input)?),
+            EventType::AppendBlock => Self::AppendBlock(AppendBlockEvent::decode(input)?),
+            EventType::DeleteFile => Self::DeleteFile(DeleteFileEvent::decode(input)?),
+            EventType::Rand => Self::Rand(RandEvent::decode(input)?),
+            EventType::UserVar => Self::UserVar(UserVarEvent::decode(input)?),
+            EventType::FormatDescription => {
+                Self::FormatDescription(FormatDescriptionEvent::decode(input)?)
+            }
+            EventType::Xid => Self::Xid(XidEvent::decode(input)?),
+            EventType::BeginLoadQuery => Self::BeginLoadQuery(AppendBlockEvent::decode(input)?),
+            EventType::ExecuteLoadQuery => {
+                Self::ExecuteLoadQuery(ExecuteLoadQueryEvent::decode(input)?)
+            }
+            EventType::TableMap => Self::TableMap(TableMapEvent::decode(input)?),
+            EventType::WriteRowsV1
+            | EventType::UpdateRowsV1
+            | EventType::DeleteRowsV1
+            | EventType::WriteRows
+            | EventType::UpdateRows
+            | EventType::DeleteRows
+            | EventType::PartialUpdateRows => {
+                Self::Rows(RowsEvent::decode_with(event_type, input)?)
+            }
+            EventType::Incident => Self::Incident(IncidentEvent::decode(input)?),
+            EventType::Heartbeat => Self::Heartbeat(HeartbeatEvent::decode(input)?),
+            EventType::RowsQuery => Self::RowsQuery(RowsQueryEvent::decode(input)?),
+            EventType::Gtid | EventType::AnonymousGtid => Self::Gtid(GtidEvent::decode(input)?),
+            EventType::PreviousGtids => Self::PreviousGtids(GtidSet::decode(input)?),
+            EventType::XaPrepare => Self::XaPrepare(XaPrepareEvent::decode(input)?),
+            _ => Self::Raw(input.split().freeze()),
+        };
+        if input.left() > 0 {
+            return Err(DecodeError::InvalidData);
+        }
+        Ok(payload)
     }
 
-    /// bit mask of flags that are usually set with SET sql_mode
-    ///
-    /// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_replication_binlog_event.html#sect_protocol_replication_event_query_00)
-    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-    #[cfg_attr(feature="serde", serde::Serialize, serde::DeSerialize)]
-    pub struct QSqlModeCode : u64 {
-        const MODE_REAL_AS_FLOAT = 0x00000001;
-        const MODE_PIPES_AS_CONCAT = 0x00000002;
-        const MODE_ANSI_QUOTES = 0x00000004;
-        const MODE_IGNORE_SPACE = 0x00000008;
-        const MODE_NOT_USED = 0x00000010;
-        const MODE_ONLY_FULL_GROUP_BY = 0x00000020;
-        const MODE_NO_UNSIGNED_SUBTRACTION = 0x00000040;
-        const MODE_NO_DIR_IN_CREATE = 0x00000080;
-        const MODE_POSTGRESQL = 0x00000100;
-        const MODE_ORACLE = 0x00000200;
-        const MODE_MSSQL = 0x00000400;
-        const MODE_DB2 = 0x00000800;
-        const MODE_MAXDB = 0x00001000;
-        const MODE_NO_KEY_OPTIONS = 0x00002000;
-        const MODE_NO_TABLE_OPTIONS = 0x00004000;
-        const MODE_NO_FIELD_OPTIONS = 0x00008000;
-        const MODE_MYSQL323 = 0x00010000;
-        const MODE_MYSQL40 = 0x00020000;
-        const MODE_ANSI = 0x00040000;
-        const MODE_NO_AUTO_VALUE_ON_ZERO = 0x00080000;
-        const MODE_NO_BACKSLASH_ESCAPES = 0x00100000;
-        const MODE_STRICT_TRANS_TABLES = 0x00200000;
-        const MODE_STRICT_ALL_TABLES = 0x00400000;
-        const MODE_NO_ZERO_IN_DATE = 0x00800000;
-        const MODE_NO_ZERO_DATE = 0x01000000;
-        const MODE_INVALID_DATES = 0x02000000;
-        const MODE_ERROR_FOR_DIVISION_BY_ZERO = 0x04000000;
-        const MODE_TRADITIONAL = 0x08000000;
-        const MODE_NO_AUTO_CREATE_USER = 0x10000000;
-        const MODE_HIGH_NOT_PRECEDENCE = 0x20000000;
-        const MODE_NO_ENGINE_SUBSTITUTION = 0x40000000;
-        const MODE_PAD_CHAR_TO_FULL_LENGTH = 0x80000000;
+    /// GTID event or `BEGIN` and `XA START` query starts a transaction
+    pub fn starts_transaction(&self) -> bool {
+        match self {
+            Self::Gtid(_) => true,
+            Self::Query(query) => is_begin(&query.sql()),
+            _ => false,
+        }
     }
 }
 
-#[derive(Debug, Clone)]
-#[cfg_attr(feature = "serde", serde::Serialize, serde::DeSerialize)]
-pub struct QAutoIncrement {}
+pub(crate) fn sql_starts_with(sql: &str, prefix: &str) -> bool {
+    sql.trim_start()
+        .get(..prefix.len())
+        .is_some_and(|s| s.eq_ignore_ascii_case(prefix))
+}
 
-/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_replication_binlog_event.html#sect_protocol_replication_event_stop)
-#[derive(Debug, Clone)]
-#[cfg_attr(feature = "serde", serde::Serialize, serde::DeSerialize)]
+pub(crate) fn is_begin(sql: &str) -> bool {
+    sql.trim().eq_ignore_ascii_case("BEGIN") || sql_starts_with(sql, "XA START")
+}
 
-pub struct StopEvent;
+pub(crate) fn is_commit(sql: &str) -> bool {
+    let sql = sql.trim();
+    sql.eq_ignore_ascii_case("COMMIT")
+        || sql.eq_ignore_ascii_case("ROLLBACK")
+        || sql_starts_with(sql, "XA COMMIT")
+        || sql_starts_with(sql, "XA ROLLBACK")
+}
 
-/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_replication_binlog_event.html#sect_protocol_replication_event_rotate)
-#[derive(Debug, Clone)]
-#[cfg_attr(feature = "serde", serde::Serialize, serde::DeSerialize)]
-pub struct RotateEvent {
-    pub pos: Int8,
-    pub log: String,
+/// finds transaction boundaries of an event stream, must see every event in order
+///
+/// a transaction ends at XID event, XA prepare event, or `COMMIT` and `ROLLBACK` query
+/// after `BEGIN`. a query without `BEGIN`, like DDL, is a transaction by itself, while
+/// statements between `BEGIN` and `COMMIT` belong to one transaction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransactionTracker {
+    in_transaction: bool,
+    /// `BEGIN` or `XA START` query is open
+    begun: bool,
 }
 
-/// [source](https://dev.mysql.com/doc/dev/mysql-server/latest/classbinary__log_1_1Intvar__event.html#details)
-#[derive(Debug, Clone)]
-#[cfg_attr(feature = "serde", serde::Serialize, serde::DeSerialize)]
-pub struct IntVarEvent {
-    pub ty: u8,
-    pub value: u64,
+impl TransactionTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// after GTID event or `BEGIN` query, before the end of transaction
+    pub fn in_transaction(&self) -> bool {
+        self.in_transaction
+    }
+
+    /// feed next event, returns whether it ends a transaction
+    pub fn update(&mut self, payload: &EventPayload) -> bool {
+        let ends = match payload {
+            EventPayload::Gtid(_) => {
+                *self = Self {
+                    in_transaction: true,
+                    begun: false,
+                };
+                false
+            }
+            EventPayload::Xid(_) | EventPayload::XaPrepare(_) => true,
+            EventPayload::Query(query) => {
+                let sql = query.sql();
+                if self.begun {
+                    is_commit(&sql)
+                } else if is_begin(&sql) {
+                    *self = Self {
+                        in_transaction: true,
+                        begun: true,
+                    };
+                    false
+                } else {
+                    true
+                }
+            }
+            _ => false,
+        };
+        if ends {
+            *self = Self::default();
+        }
+        ends
+    }
 }
 
-#[repr(u8)]
-pub enum IntVarEventType {
-    InvalidIntEvent = 0,
-    LastInsertIdEvent = 1,
-    InsertIdEvent = 2,
+impl Encode for EventPayload {
+    fn encode(&self, buf: &mut BytesMut) {
+        match self {
+            Self::Query(e) => e.encode(buf),
+            Self::Stop(e) => e.encode(buf),
+            Self::Rotate(e) => e.encode(buf),
+            Self::IntVar(e) => e.encode(buf),
+            Self::AppendBlock(e) | Self::BeginLoadQuery(e) => e.encode(buf),
+            Self::DeleteFile(e) => e.encode(buf),
+            Self::Rand(e) => e.encode(buf),
+            Self::UserVar(e) => e.encode(buf),
+            Self::FormatDescription(e) => e.encode(buf),
+            Self::Xid(e) => e.encode(buf),
+            Self::ExecuteLoadQuery(e) => e.encode(buf),
+            Self::TableMap(e) => e.encode(buf),
+            Self::Rows(e) => e.encode(buf),
+            Self::Incident(e) => e.encode(buf),
+            Self::Heartbeat(e) => e.encode(buf),
+            Self::RowsQuery(e) => e.encode(buf),
+            Self::Gtid(e) => e.encode(buf),
+            Self::PreviousGtids(e) => e.encode(buf),
+            Self::XaPrepare(e) => e.encode(buf),
+            Self::Raw(data) => buf.extend_from_slice(data),
+        }
+    }
+}
+
+impl Event<EventPayload> {
+    /// decode a complete event, CRC32 at the end is verified and stripped when `checksum`
+    /// is set, format description event decides it by its own checksum algorithm
+    ///
+    /// event is split off `input` without copying, large fields of payload share its buffer.
+    pub fn decode_with(input: &mut BytesMut, checksum: bool) -> Result<Self, DecodeError> {
+        let size = input.get(9..13).ok_or(DecodeError::NoEnoughData)?;
+        let size = u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as usize;
+        if size < EVENT_HEADER_LEN {
+            return Err(DecodeError::InvalidData);
+        }
+        if input.len() < size {
+            return Err(DecodeError::NoEnoughData);
+        }
+        let mut data = input.split_to(size);
+        let checksum = if data[4] == EventType::FormatDescription as u8 {
+            format_desc_checksum(&data) == Some(1)
+        } else {
+            checksum
+        };
+        if checksum {
+            let end = size
+                .checked_sub(CHECKSUM_LEN)
+                .filter(|end| *end >= EVENT_HEADER_LEN)
+                .ok_or(DecodeError::InvalidData)?;
+            let expect =
+                u32::from_le_bytes([data[end], data[end + 1], data[end + 2], data[end + 3]]);
+            if crc32fast::hash(&data[..end]) != expect {
+                return Err(DecodeError::ChecksumMismatch);
+            }
+            data.truncate(end);
+        }
+        let header = EventHeader::decode(&mut data)?;
+        let payload = EventPayload::decode_with(header.event_type.int(), &mut data)?;
+        Ok(Self { header, payload })
+    }
+}
+
+impl<P: Encode> Event<P> {
+    /// encode event with `event_size` computed, `log_pos` of header is kept, CRC32 is
+    /// appended when `checksum` is set
+    pub fn encode_with(&self, checksum: bool, buf: &mut BytesMut) {
+        self.encode_event(None, checksum, buf)
+    }
+
+    /// encode event to be written at offset `pos` of binlog file, `log_pos` is set to
+    /// end of the event
+    pub fn encode_at(&self, pos: u32, checksum: bool, buf: &mut BytesMut) {
+        self.encode_event(Some(pos), checksum, buf)
+    }
+
+    fn encode_event(&self, pos: Option<u32>, checksum: bool, buf: &mut BytesMut) {
+        let start = buf.len();
+        self.header.encode(buf);
+        self.payload.encode(buf);
+        let checksum_len = if checksum { CHECKSUM_LEN } else { 0 };
+        let size = (buf.len() - start + checksum_len) as u32;
+        buf[start + 9..start + 13].copy_from_slice(&size.to_le_bytes());
+        if let Some(pos) = pos {
+            buf[start + 13..start + 17].copy_from_slice(&(pos + size).to_le_bytes());
+        }
+        if checksum {
+            let crc = crc32fast::hash(&buf[start..]);
+            buf.put_u32_le(crc);
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// statement based transactions of 05_intvar fixture, with several inserts in the
+    /// last transaction, events written by [`BinlogWriter`] itself are left out
+    pub(crate) fn statement_events() -> Vec<Event<EventPayload>> {
+        let data = include_bytes!("../../../tests/events/05_intvar/log.bin");
+        let mut events = vec![];
+        for event in BinlogFileReader::new(&data[..]).unwrap() {
+            let (_, event) = event.unwrap();
+            match event.payload {
+                EventPayload::FormatDescription(_)
+                | EventPayload::PreviousGtids(_)
+                | EventPayload::Rotate(_) => {}
+                EventPayload::Xid(_) => {
+                    // IntVar and INSERT
+                    let statement = events[events.len() - 2..].to_vec();
+                    events.extend_from_slice(&statement);
+                    events.extend_from_slice(&statement);
+                    events.push(event);
+                }
+                _ => events.push(event),
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn test_transaction_tracker() {
+        let mut events = statement_events();
+        let mut tracker = TransactionTracker::new();
+        let ends: Vec<_> = (0..events.len())
+            .filter(|i| tracker.update(&events[*i].payload))
+            .collect();
+        // DROP, CREATE and XID after three inserts
+        assert_eq!(ends, [1, 3, 12]);
+        assert!(!tracker.in_transaction());
+
+        let Some(EventPayload::Query(mut commit)) = events.get(5).map(|e| e.payload.clone()) else {
+            panic!("not a query event");
+        };
+        assert_eq!(commit.sql(), "BEGIN");
+        commit.query = Bytes::from_static(b"COMMIT");
+        events[12].payload = EventPayload::Query(commit);
+        for event in &events[4..12] {
+            assert!(!tracker.update(&event.payload));
+            assert!(tracker.in_transaction());
+        }
+        assert!(tracker.update(&events[12].payload));
+        assert!(!tracker.in_transaction());
+    }
+
+    macro_rules! fixture {
+        ($name:literal) => {
+            (
+                $name,
+                &include_bytes!(concat!("../../../tests/events/", $name, "/log.bin"))[..],
+            )
+        };
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let fixtures = [
+            fixture!("02_query"),
+            fixture!("03_stop"),
+            fixture!("04_rotate"),
+            fixture!("05_intvar"),
+            fixture!("13_rand"),
+            fixture!("14_user_var"),
+            fixture!("15_format_desc"),
+            fixture!("16_xid"),
+            fixture!("17_18_load"),
+            fixture!("19_table_map"),
+            fixture!("29_row_query"),
+            fixture!("30_write_rows_v2"),
+            fixture!("31_update_rows_v2"),
+            fixture!("32_delete_rows_v2"),
+            fixture!("33_35_gtid_prev_gtid"),
+            fixture!("34_anonymous_gtid"),
+        ];
+        for (name, data) in fixtures {
+            let mut reader = BinlogFileReader::new(data).unwrap();
+            let mut end = BINLOG_MAGIC.len();
+            while let Some(event) = reader.next() {
+                let (pos, event) = event.unwrap();
+                let pos = pos as usize;
+                assert!(
+                    !matches!(event.payload, EventPayload::Raw(_)),
+                    "{name} {:?}",
+                    event.header
+                );
+                let mut buf = BytesMut::new();
+                event.encode_at(pos as u32, reader.checksum(), &mut buf);
+                end = pos + event.header.event_size.int() as usize;
+                assert_eq!(buf[..], data[pos..end], "{name} at {pos}");
+            }
+            assert_eq!(end, data.len(), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_zero_copy() {
+        for (name, data) in [fixture!("14_user_var"), fixture!("30_write_rows_v2")] {
+            let mut reader = BinlogFileReader::new(data).unwrap();
+            let mut checksum = false;
+            let mut shared = 0;
+            while let Some(mut raw) = reader.next_raw().unwrap() {
+                let range = raw.as_ptr_range();
+                let event = Event::decode_with(&mut raw, checksum).unwrap();
+                let fields: Vec<&Bytes> = match &event.payload {
+                    EventPayload::FormatDescription(fde) => {
+                        checksum = fde.checksum_alg == Some(Int1::from(1));
+                        continue;
+                    }
+                    EventPayload::Query(query) => vec![&query.query],
+                    EventPayload::RowsQuery(query) => vec![&query.query],
+                    EventPayload::UserVar(var) => var.value.iter().map(|v| &v.value).collect(),
+                    EventPayload::TableMap(table) => {
+                        vec![&table.column_types, &table.column_metas, &table.null_bitmap]
+                    }
+                    EventPayload::Rows(rows) => vec![&rows.columns_present, &rows.rows],
+                    _ => continue,
+                };
+                for field in fields {
+                    assert!(range.contains(&field.as_ptr()), "{name} {:?}", event.header);
+                    shared += 1;
+                }
+            }
+            assert!(shared > 0, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_new_format_desc() {
+        let data = include_bytes!("../../../tests/events/15_format_desc/log.bin");
+        let mut input = BytesMut::from(&data[4..]);
+        let event = Event::decode_with(&mut input, false).unwrap();
+        let EventPayload::FormatDescription(fde) = event.payload else {
+            panic!("not a format description event");
+        };
+        let new = FormatDescriptionEvent::new("5.7.30-log", true);
+        assert_eq!(new.post_header_lengths, fde.post_header_lengths);
+        assert_eq!(new.checksum_alg, fde.checksum_alg);
+        let new = FormatDescriptionEvent::new("8.0.34", true);
+        assert_eq!(
+            new.post_header_lengths.len(),
+            EventType::HeartbeatV2 as usize
+        );
+    }
+
+    #[test]
+    fn test_checksum_mismatch() {
+        let data = include_bytes!("../../../tests/events/16_xid/log.bin");
+        let mut input = BytesMut::from(&data[4..]);
+        Event::decode_with(&mut input, false).unwrap();
+        let mut corrupted = input.clone();
+        corrupted[EVENT_HEADER_LEN] ^= 0xff;
+        assert!(matches!(
+            Event::decode_with(&mut corrupted, true),
+            Err(DecodeError::ChecksumMismatch)
+        ));
+    }
 }
@@ -0,0 +1,250 @@
+use bytes::{BufMut, BytesMut};
+use parse_tool::InputBuf;
+
+use crate::codec::{Decode, DecodeError, Encode, Int1, Int2, Int4, Int8};
+
+use super::{has_checksum_alg, EVENT_HEADER_LEN};
+
+/// length of server version field in format description event
+const SERVER_VERSION_LEN: usize = 50;
+
+fn utf8(data: Vec<u8>) -> Result<String, DecodeError> {
+    String::from_utf8(data).map_err(|_| DecodeError::InvalidUtf8)
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/classmysql_1_1binlog_1_1event_1_1Format__description__event.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatDescriptionEvent {
+    pub binlog_version: Int2,
+    pub server_version: String,
+    pub create_timestamp: Int4,
+    pub header_length: Int1,
+    /// post header length of every event type, starts from type 1
+    pub post_header_lengths: Vec<u8>,
+    /// 0 is off and 1 is CRC32, `None` for servers older than 5.6.1
+    pub checksum_alg: Option<Int1>,
+}
+
+/// post header lengths of event types 1 to 38 written by 5.7
+const POST_HEADER_LENGTHS_57: [u8; 38] = [
+    56, 13, 0, 8, 0, 18, 0, 4, 4, 4, 4, 18, 0, 0, 95, 0, 4, 26, 8, 0, 0, 0, 8, 8, 8, 2, 0, 0, 0,
+    10, 10, 10, 42, 42, 0, 18, 52, 0,
+];
+
+/// post header lengths of partial update rows, transaction payload and heartbeat v2
+/// events added by 8.0
+const POST_HEADER_LENGTHS_80: [u8; 3] = [10, 40, 0];
+
+impl FormatDescriptionEvent {
+    /// binlog version 4 format description written by `server_version`, `create_timestamp`
+    /// is 0
+    pub fn new(server_version: impl Into<String>, checksum: bool) -> Self {
+        let server_version = server_version.into();
+        let mut post_header_lengths = POST_HEADER_LENGTHS_57.to_vec();
+        let major = server_version
+            .split('.')
+            .next()
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or_default();
+        if major >= 8 {
+            post_header_lengths.extend_from_slice(&POST_HEADER_LENGTHS_80);
+        }
+        // post header of format description event itself
+        post_header_lengths[14] =
+            (2 + SERVER_VERSION_LEN + 4 + 1 + post_header_lengths.len()) as u8;
+        let checksum_alg = has_checksum_alg(&server_version).then(|| Int1::from(checksum as u8));
+        Self {
+            binlog_version: Int2::from(4),
+            server_version,
+            create_timestamp: Int4::from(0),
+            header_length: Int1::from(EVENT_HEADER_LEN as u8),
+            post_header_lengths,
+            checksum_alg,
+        }
+    }
+}
+
+impl<I: InputBuf> Decode<I> for FormatDescriptionEvent {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        let binlog_version = Int2::decode(input)?;
+        let mut server_version = input.read_vec(SERVER_VERSION_LEN)?;
+        if let Some(end) = server_version.iter().position(|b| *b == 0) {
+            server_version.truncate(end);
+        }
+        let server_version = utf8(server_version)?;
+        let create_timestamp = Int4::decode(input)?;
+        let header_length = Int1::decode(input)?;
+        let mut post_header_lengths = input.read_to_end();
+        let checksum_alg = if has_checksum_alg(&server_version) {
+            let alg = post_header_lengths.pop().ok_or(DecodeError::NoEnoughData)?;
+            Some(Int1::from(alg))
+        } else {
+            None
+        };
+        Ok(Self {
+            binlog_version,
+            server_version,
+            create_timestamp,
+            header_length,
+            post_header_lengths,
+            checksum_alg,
+        })
+    }
+}
+
+impl Encode for FormatDescriptionEvent {
+    fn encode(&self, buf: &mut BytesMut) {
+        self.binlog_version.encode(buf);
+        let mut version = [0; SERVER_VERSION_LEN];
+        let len = self.server_version.len().min(SERVER_VERSION_LEN - 1);
+        version[..len].copy_from_slice(&self.server_version.as_bytes()[..len]);
+        buf.extend_from_slice(&version);
+        self.create_timestamp.encode(buf);
+        self.header_length.encode(buf);
+        buf.extend_from_slice(&self.post_header_lengths);
+        if let Some(alg) = self.checksum_alg {
+            alg.encode(buf);
+        }
+    }
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_replication_binlog_event.html#sect_protocol_replication_event_stop)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StopEvent;
+
+impl<I: InputBuf> Decode<I> for StopEvent {
+    fn decode(_input: &mut I) -> Result<Self, DecodeError> {
+        Ok(Self)
+    }
+}
+
+impl Encode for StopEvent {
+    fn encode(&self, _buf: &mut BytesMut) {}
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_replication_binlog_event.html#sect_protocol_replication_event_rotate)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RotateEvent {
+    pub pos: Int8,
+    pub log: String,
+}
+
+impl<I: InputBuf> Decode<I> for RotateEvent {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        let pos = Int8::decode(input)?;
+        let log = utf8(input.read_to_end())?;
+        Ok(Self { pos, log })
+    }
+}
+
+impl Encode for RotateEvent {
+    fn encode(&self, buf: &mut BytesMut) {
+        self.pos.encode(buf);
+        buf.extend_from_slice(self.log.as_bytes());
+    }
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/classmysql_1_1binlog_1_1event_1_1Xid__event.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XidEvent {
+    pub xid: Int8,
+}
+
+impl<I: InputBuf> Decode<I> for XidEvent {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        Ok(Self {
+            xid: Int8::decode(input)?,
+        })
+    }
+}
+
+impl Encode for XidEvent {
+    fn encode(&self, buf: &mut BytesMut) {
+        self.xid.encode(buf);
+    }
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/classmysql_1_1binlog_1_1event_1_1Incident__event.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncidentEvent {
+    /// 1 means lost events
+    pub ty: Int2,
+    pub message: String,
+}
+
+impl<I: InputBuf> Decode<I> for IncidentEvent {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        let ty = Int2::decode(input)?;
+        let len = input.read_u8_le()? as usize;
+        let message = utf8(input.read_vec(len)?)?;
+        Ok(Self { ty, message })
+    }
+}
+
+impl Encode for IncidentEvent {
+    fn encode(&self, buf: &mut BytesMut) {
+        self.ty.encode(buf);
+        buf.put_u8(self.message.len() as u8);
+        buf.extend_from_slice(self.message.as_bytes());
+    }
+}
+
+/// sent by source when there is no event to send for a period
+///
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/classmysql_1_1binlog_1_1event_1_1Heartbeat__event.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeartbeatEvent {
+    /// current binlog file of source
+    pub log: String,
+}
+
+impl<I: InputBuf> Decode<I> for HeartbeatEvent {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        Ok(Self {
+            log: utf8(input.read_to_end())?,
+        })
+    }
+}
+
+impl Encode for HeartbeatEvent {
+    fn encode(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(self.log.as_bytes());
+    }
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/classmysql_1_1binlog_1_1event_1_1XA__prepare__event.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XaPrepareEvent {
+    pub one_phase: bool,
+    pub format_id: Int4,
+    pub gtrid: Vec<u8>,
+    pub bqual: Vec<u8>,
+}
+
+impl<I: InputBuf> Decode<I> for XaPrepareEvent {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        let one_phase = input.read_u8_le()? != 0;
+        let format_id = Int4::decode(input)?;
+        let gtrid_len = Int4::decode(input)?.int() as usize;
+        let bqual_len = Int4::decode(input)?.int() as usize;
+        let gtrid = input.read_vec(gtrid_len)?;
+        let bqual = input.read_vec(bqual_len)?;
+        Ok(Self {
+            one_phase,
+            format_id,
+            gtrid,
+            bqual,
+        })
+    }
+}
+
+impl Encode for XaPrepareEvent {
+    fn encode(&self, buf: &mut BytesMut) {
+        buf.put_u8(self.one_phase as u8);
+        self.format_id.encode(buf);
+        Int4::from(self.gtrid.len() as u32).encode(buf);
+        Int4::from(self.bqual.len() as u32).encode(buf);
+        buf.extend_from_slice(&self.gtrid);
+        buf.extend_from_slice(&self.bqual);
+    }
+}
@@ -0,0 +1,365 @@
+use std::{collections::HashSet, fmt, str::FromStr};
+
+use regex::Regex;
+
+use crate::binlog::{
+    is_begin, is_commit, sql_starts_with, Event, EventPayload, EventType, Sid, TransactionTracker,
+    EVENT_HEADER_LEN,
+};
+
+/// name pattern of database or table, glob like `shop_*` or regex wrapped by slashes like
+/// `/^shop_\d+$/`
+///
+/// glob supports `*` and `?` only, both glob and regex must match the whole name.
+#[derive(Debug, Clone)]
+pub struct Pattern(Regex);
+
+impl Pattern {
+    pub fn glob(glob: &str) -> Result<Self, regex::Error> {
+        let mut re = String::from("^");
+        for c in glob.chars() {
+            match c {
+                '*' => re.push_str(".*"),
+                '?' => re.push('.'),
+                c => re.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+            }
+        }
+        re.push('$');
+        Regex::new(&re).map(Self)
+    }
+
+    pub fn regex(re: &str) -> Result<Self, regex::Error> {
+        Regex::new(&format!("^(?:{re})$")).map(Self)
+    }
+
+    pub fn is_match(&self, name: &str) -> bool {
+        self.0.is_match(name)
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = regex::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix('/').and_then(|s| s.strip_suffix('/')) {
+            Some(re) => Self::regex(re),
+            None => Self::glob(s),
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// options of [`EventFilter`], empty include list means everything is included and
+/// exclude list wins over include list
+#[derive(Debug, Clone, Default)]
+pub struct EventFilterOpts {
+    /// databases of table map events and default database of query events
+    pub include_dbs: Vec<Pattern>,
+    pub exclude_dbs: Vec<Pattern>,
+    /// tables of table map events, matched against `db.table`
+    pub include_tables: Vec<Pattern>,
+    pub exclude_tables: Vec<Pattern>,
+    pub include_types: Vec<EventType>,
+    pub exclude_types: Vec<EventType>,
+    pub include_server_ids: Vec<u32>,
+    pub exclude_server_ids: Vec<u32>,
+    /// source server uuids of GTIDs, whole transaction is filtered
+    pub include_sources: Vec<Sid>,
+    pub exclude_sources: Vec<Sid>,
+}
+
+impl EventFilterOpts {
+    /// whether no event is filtered
+    pub fn is_empty(&self) -> bool {
+        self.include_dbs.is_empty()
+            && self.exclude_dbs.is_empty()
+            && self.include_tables.is_empty()
+            && self.exclude_tables.is_empty()
+            && self.include_types.is_empty()
+            && self.exclude_types.is_empty()
+            && self.include_server_ids.is_empty()
+            && self.exclude_server_ids.is_empty()
+            && self.include_sources.is_empty()
+            && self.exclude_sources.is_empty()
+    }
+}
+
+/// drops unwanted events of a binlog stream, must see every event in order
+///
+/// [`EventFilter::skip_raw`] is checked before decoding, so rows events of filtered tables
+/// are never decoded. events changing filter state, like table map and GTID events, are
+/// always decoded and then checked by [`EventFilter::accept`].
+///
+/// transaction control statements like `BEGIN` and `COMMIT` are not filtered by database,
+/// rows events are filtered with their table map events.
+#[derive(Debug, Clone)]
+pub struct EventFilter {
+    opts: EventFilterOpts,
+    /// table ids whose table map event is filtered out
+    skipped_tables: HashSet<u64>,
+    /// in a transaction from filtered GTID source
+    skip_transaction: bool,
+    tracker: TransactionTracker,
+}
+
+impl EventFilter {
+    pub fn new(opts: EventFilterOpts) -> Self {
+        Self {
+            opts,
+            skipped_tables: HashSet::new(),
+            skip_transaction: false,
+            tracker: TransactionTracker::new(),
+        }
+    }
+
+    pub fn opts(&self) -> &EventFilterOpts {
+        &self.opts
+    }
+
+    /// whether a complete raw event can be dropped without decoding
+    pub fn skip_raw(&self, event: &[u8]) -> bool {
+        let Some(header) = event.get(..EVENT_HEADER_LEN) else {
+            return false;
+        };
+        let Ok(ty) = EventType::try_from(header[4]) else {
+            return false;
+        };
+        if is_stateful(ty) {
+            return false;
+        }
+        if self.skip_transaction {
+            return true;
+        }
+        let server_id = u32::from_le_bytes([header[5], header[6], header[7], header[8]]);
+        if !self.type_matches(ty) || !self.server_id_matches(server_id) {
+            return true;
+        }
+        // table id(6) right after header
+        match event.get(EVENT_HEADER_LEN..EVENT_HEADER_LEN + 6) {
+            Some(id) if is_rows(ty) => {
+                let id = u64::from_le_bytes([id[0], id[1], id[2], id[3], id[4], id[5], 0, 0]);
+                self.skipped_tables.contains(&id)
+            }
+            _ => false,
+        }
+    }
+
+    /// whether a decoded event is kept, state of table maps and transactions is updated
+    pub fn accept(&mut self, event: &Event<EventPayload>) -> bool {
+        let header = &event.header;
+        let ty = EventType::try_from(header.event_type.int()).unwrap_or(EventType::Unknown);
+        let ends_transaction = self.tracker.update(&event.payload);
+        if self.skip_transaction && !matches!(event.payload, EventPayload::Gtid(_)) {
+            if ends_transaction {
+                self.skip_transaction = false;
+            }
+            return false;
+        }
+        match &event.payload {
+            EventPayload::Gtid(gtid) => {
+                self.skip_transaction = ty == EventType::Gtid && !self.source_matches(&gtid.sid);
+                if self.skip_transaction {
+                    return false;
+                }
+            }
+            EventPayload::TableMap(table) => {
+                let id = table.table_id.int();
+                if !self.table_matches(&table.schema, &table.table) {
+                    self.skipped_tables.insert(id);
+                    return false;
+                }
+                self.skipped_tables.remove(&id);
+            }
+            EventPayload::Rows(rows) if self.skipped_tables.contains(&rows.table_id.int()) => {
+                return false;
+            }
+            EventPayload::Query(query)
+                if !is_transaction_control(&event.payload) && !self.db_matches(&query.schema) =>
+            {
+                return false;
+            }
+            _ => {}
+        }
+        self.type_matches(ty) && self.server_id_matches(header.server_id.int())
+    }
+
+    fn db_matches(&self, db: &str) -> bool {
+        matches(&self.opts.include_dbs, &self.opts.exclude_dbs, |p| {
+            p.is_match(db)
+        })
+    }
+
+    fn table_matches(&self, db: &str, table: &str) -> bool {
+        let name = format!("{db}.{table}");
+        self.db_matches(db)
+            && matches(&self.opts.include_tables, &self.opts.exclude_tables, |p| {
+                p.is_match(&name)
+            })
+    }
+
+    fn type_matches(&self, ty: EventType) -> bool {
+        matches(&self.opts.include_types, &self.opts.exclude_types, |t| {
+            *t == ty
+        })
+    }
+
+    fn server_id_matches(&self, server_id: u32) -> bool {
+        matches(
+            &self.opts.include_server_ids,
+            &self.opts.exclude_server_ids,
+            |id| *id == server_id,
+        )
+    }
+
+    fn source_matches(&self, sid: &Sid) -> bool {
+        matches(
+            &self.opts.include_sources,
+            &self.opts.exclude_sources,
+            |s| s == sid,
+        )
+    }
+}
+
+fn matches<T>(include: &[T], exclude: &[T], f: impl Fn(&T) -> bool) -> bool {
+    (include.is_empty() || include.iter().any(&f)) && !exclude.iter().any(f)
+}
+
+/// events keeping track of files, tables and transactions
+fn is_stateful(ty: EventType) -> bool {
+    matches!(
+        ty,
+        EventType::FormatDescription
+            | EventType::Rotate
+            | EventType::PreviousGtids
+            | EventType::Gtid
+            | EventType::AnonymousGtid
+            | EventType::TableMap
+            | EventType::Query
+            | EventType::Xid
+            | EventType::XaPrepare
+    )
+}
+
+fn is_rows(ty: EventType) -> bool {
+    matches!(
+        ty,
+        EventType::WriteRowsV1
+            | EventType::UpdateRowsV1
+            | EventType::DeleteRowsV1
+            | EventType::WriteRows
+            | EventType::UpdateRows
+            | EventType::DeleteRows
+            | EventType::PartialUpdateRows
+    )
+}
+
+fn is_transaction_control(payload: &EventPayload) -> bool {
+    let EventPayload::Query(query) = payload else {
+        return false;
+    };
+    let sql = query.sql();
+    is_begin(&sql) || is_commit(&sql) || sql_starts_with(&sql, "XA ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binlog::BinlogFileReader;
+
+    /// raw and decoded events of a binlog file
+    fn events(data: &[u8]) -> Vec<(&[u8], Event<EventPayload>)> {
+        BinlogFileReader::new(data)
+            .unwrap()
+            .map(|event| {
+                let (pos, event) = event.unwrap();
+                let pos = pos as usize;
+                let end = pos + event.header.event_size.int() as usize;
+                (&data[pos..end], event)
+            })
+            .collect()
+    }
+
+    fn kept(filter: &mut EventFilter, events: &[(&[u8], Event<EventPayload>)]) -> Vec<u8> {
+        events
+            .iter()
+            .filter(|(raw, event)| !filter.skip_raw(raw) && filter.accept(event))
+            .map(|(_, event)| event.header.event_type.int())
+            .collect()
+    }
+
+    #[test]
+    fn test_event_filter() {
+        let pattern: Pattern = "shop_*.order?".parse().unwrap();
+        assert!(pattern.is_match("shop_1.orders"));
+        assert!(!pattern.is_match("shopx1.orders"));
+        let pattern: Pattern = r"/shop_\d+/".parse().unwrap();
+        assert!(pattern.is_match("shop_12"));
+        assert!(!pattern.is_match("shop_12a"));
+
+        // GTID, Query, TableMap, WriteRows, Xid in third transaction
+        let events = events(include_bytes!(
+            "../../../../tests/events/30_write_rows_v2/log.bin"
+        ));
+        let table = events
+            .iter()
+            .find_map(|(_, event)| match &event.payload {
+                EventPayload::TableMap(table) => Some(format!("{}.{}", table.schema, table.table)),
+                _ => None,
+            })
+            .unwrap();
+        let mut filter = EventFilter::new(EventFilterOpts {
+            exclude_tables: vec![Pattern::glob(&table).unwrap()],
+            ..Default::default()
+        });
+        let types = kept(&mut filter, &events);
+        assert!(!types.contains(&(EventType::TableMap as u8)));
+        assert!(!types.contains(&(EventType::WriteRows as u8)));
+        assert!(types.contains(&(EventType::Xid as u8)));
+
+        let mut filter = EventFilter::new(EventFilterOpts {
+            include_types: vec![EventType::WriteRows],
+            ..Default::default()
+        });
+        assert_eq!(kept(&mut filter, &events), [EventType::WriteRows as u8]);
+
+        let sid = events
+            .iter()
+            .find_map(|(_, event)| match &event.payload {
+                EventPayload::Gtid(gtid) => Some(gtid.sid),
+                _ => None,
+            })
+            .unwrap();
+        let mut filter = EventFilter::new(EventFilterOpts {
+            exclude_sources: vec![sid],
+            ..Default::default()
+        });
+        let types = kept(&mut filter, &events);
+        assert_eq!(types, [15, 35, 4]);
+    }
+
+    #[test]
+    fn test_filter_statement_transaction() {
+        let events = crate::binlog::tests::statement_events();
+        let sid = match &events[4].payload {
+            EventPayload::Gtid(gtid) => gtid.sid,
+            _ => unreachable!(),
+        };
+        // every INSERT of the transaction is dropped, not only the first one
+        let mut filter = EventFilter::new(EventFilterOpts {
+            exclude_sources: vec![sid],
+            ..Default::default()
+        });
+        assert!(events.iter().all(|event| !filter.accept(event)));
+
+        let mut filter = EventFilter::new(EventFilterOpts {
+            include_sources: vec![sid],
+            ..Default::default()
+        });
+        assert!(events.iter().all(|event| filter.accept(event)));
+    }
+}
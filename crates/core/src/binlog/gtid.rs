@@ -0,0 +1,322 @@
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use bytes::{BufMut, BytesMut};
+use parse_tool::InputBuf;
+
+use crate::codec::{Decode, DecodeError, Encode, Int1, Int4, Int8, VLenInt};
+
+/// server uuid, binary form of `source_id` in GTID
+pub type Sid = [u8; 16];
+
+/// parse `3e11fa47-71ca-11e1-9e33-c80aa9429562` into [`Sid`]
+pub fn parse_sid(s: &str) -> Result<Sid, DecodeError> {
+    let hex: Vec<u8> = s.bytes().filter(|b| *b != b'-').collect();
+    if hex.len() != 32 {
+        return Err(DecodeError::InvalidData);
+    }
+    let mut sid = [0u8; 16];
+    for (i, byte) in sid.iter_mut().enumerate() {
+        let digits =
+            std::str::from_utf8(&hex[i * 2..i * 2 + 2]).map_err(|_| DecodeError::InvalidUtf8)?;
+        *byte = u8::from_str_radix(digits, 16).map_err(|_| DecodeError::InvalidData)?;
+    }
+    Ok(sid)
+}
+
+/// format [`Sid`] as uuid string
+pub fn format_sid(sid: &Sid) -> String {
+    let hex: String = sid.iter().map(|b| format!("{b:02x}")).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// set of GTIDs, e.g. `3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5:7`
+///
+/// intervals of each sid are sorted and merged, `end` is exclusive like binary format.
+///
+/// [doc](https://dev.mysql.com/doc/refman/8.0/en/replication-gtids-concepts.html#replication-gtids-concepts-gtid-sets)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GtidSet {
+    sets: BTreeMap<Sid, Vec<(u64, u64)>>,
+}
+
+impl GtidSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sets.is_empty()
+    }
+
+    /// intervals of sid, `end` is exclusive
+    pub fn intervals(&self, sid: &Sid) -> &[(u64, u64)] {
+        self.sets.get(sid).map(|v| v.as_slice()).unwrap_or_default()
+    }
+
+    pub fn sids(&self) -> impl Iterator<Item = &Sid> {
+        self.sets.keys()
+    }
+
+    pub fn contains(&self, sid: &Sid, gno: u64) -> bool {
+        self.intervals(sid)
+            .iter()
+            .any(|(start, end)| *start <= gno && gno < *end)
+    }
+
+    /// add a single GTID, `u64::MAX` is invalid since `end` is exclusive
+    pub fn add(&mut self, sid: Sid, gno: u64) -> Result<(), DecodeError> {
+        let end = gno.checked_add(1).ok_or(DecodeError::InvalidData)?;
+        self.add_interval(sid, gno, end);
+        Ok(())
+    }
+
+    /// add interval `[start, end)`
+    pub fn add_interval(&mut self, sid: Sid, start: u64, end: u64) {
+        if start >= end {
+            return;
+        }
+        let intervals = self.sets.entry(sid).or_default();
+        intervals.push((start, end));
+        intervals.sort_unstable();
+        let mut merged: Vec<(u64, u64)> = Vec::with_capacity(intervals.len());
+        for (start, end) in intervals.drain(..) {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        *intervals = merged;
+    }
+
+    /// add all GTIDs of other set
+    pub fn union(&mut self, other: &GtidSet) {
+        for (sid, intervals) in other.sets.iter() {
+            for (start, end) in intervals {
+                self.add_interval(*sid, *start, *end);
+            }
+        }
+    }
+}
+
+impl FromStr for GtidSet {
+    type Err = DecodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut set = GtidSet::new();
+        for part in s.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            let mut items = part.split(':');
+            let sid = parse_sid(items.next().unwrap_or_default().trim())?;
+            for interval in items {
+                let (start, end) = match interval.split_once('-') {
+                    Some((start, end)) => (start, end),
+                    None => (interval, interval),
+                };
+                let parse = |v: &str| {
+                    v.trim()
+                        .parse::<u64>()
+                        .map_err(|_| DecodeError::InvalidData)
+                };
+                let end = parse(end)?.checked_add(1).ok_or(DecodeError::InvalidData)?;
+                set.add_interval(sid, parse(start)?, end);
+            }
+        }
+        Ok(set)
+    }
+}
+
+impl fmt::Display for GtidSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, (sid, intervals)) in self.sets.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", format_sid(sid))?;
+            for (start, end) in intervals {
+                if end - start == 1 {
+                    write!(f, ":{start}")?;
+                } else {
+                    write!(f, ":{start}-{}", end - 1)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// binary form used by COM_BINLOG_DUMP_GTID and PREVIOUS_GTIDS_EVENT
+impl<I: InputBuf> Decode<I> for GtidSet {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        let mut set = GtidSet::new();
+        let n_sids = Int8::decode(input)?.int();
+        for _ in 0..n_sids {
+            let sid: Sid = input.read_array()?;
+            let n_intervals = Int8::decode(input)?.int();
+            for _ in 0..n_intervals {
+                let start = Int8::decode(input)?.int();
+                let end = Int8::decode(input)?.int();
+                set.add_interval(sid, start, end);
+            }
+        }
+        Ok(set)
+    }
+}
+
+impl Encode for GtidSet {
+    fn encode(&self, buf: &mut BytesMut) {
+        Int8::from(self.sets.len() as u64).encode(buf);
+        for (sid, intervals) in self.sets.iter() {
+            buf.extend_from_slice(sid);
+            Int8::from(intervals.len() as u64).encode(buf);
+            for (start, end) in intervals {
+                Int8::from(*start).encode(buf);
+                Int8::from(*end).encode(buf);
+            }
+        }
+    }
+}
+
+/// logical clock type code of GTID event
+const LOGICAL_TIMESTAMP_TYPECODE: u8 = 2;
+
+/// GTID event and anonymous GTID event, sid and gno of anonymous GTID are zero
+///
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/classmysql_1_1binlog_1_1event_1_1Gtid__event.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GtidEvent {
+    /// 1 if transaction may have changes logged with SBR
+    pub flags: Int1,
+    pub sid: Sid,
+    pub gno: u64,
+    /// `(last_committed, sequence_number)`, since 5.7.6
+    pub logical_clock: Option<(i64, i64)>,
+    /// `(immediate, original)` commit timestamps in microseconds, since 8.0.1
+    pub commit_timestamps: Option<(u64, u64)>,
+    /// since 8.0.2
+    pub transaction_length: Option<u64>,
+    /// `(immediate, original)` server versions, since 8.0.14
+    pub server_versions: Option<(u32, u32)>,
+}
+
+impl<I: InputBuf> Decode<I> for GtidEvent {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        let flags = Int1::decode(input)?;
+        let sid = input.read_array()?;
+        let gno = input.read_u64_le()?;
+        let mut event = Self {
+            flags,
+            sid,
+            gno,
+            logical_clock: None,
+            commit_timestamps: None,
+            transaction_length: None,
+            server_versions: None,
+        };
+        if input.left() == 0 {
+            return Ok(event);
+        }
+        if input.read_u8_le()? != LOGICAL_TIMESTAMP_TYPECODE {
+            return Err(DecodeError::InvalidData);
+        }
+        let last_committed = input.read_u64_le()? as i64;
+        let sequence_number = input.read_u64_le()? as i64;
+        event.logical_clock = Some((last_committed, sequence_number));
+        if input.left() == 0 {
+            return Ok(event);
+        }
+        let immediate = read_u56(input)?;
+        let original = if immediate & (1 << 55) != 0 {
+            read_u56(input)?
+        } else {
+            immediate
+        };
+        event.commit_timestamps = Some((immediate & !(1 << 55), original));
+        if input.left() == 0 {
+            return Ok(event);
+        }
+        event.transaction_length = Some(VLenInt::decode(input)?.int());
+        if input.left() == 0 {
+            return Ok(event);
+        }
+        let immediate = Int4::decode(input)?.int();
+        let original = if immediate & (1 << 31) != 0 {
+            Int4::decode(input)?.int()
+        } else {
+            immediate
+        };
+        event.server_versions = Some((immediate & !(1 << 31), original));
+        Ok(event)
+    }
+}
+
+fn read_u56<I: InputBuf>(input: &mut I) -> Result<u64, DecodeError> {
+    let mut data = [0; 8];
+    data[..7].copy_from_slice(&input.read_array::<7>()?);
+    Ok(u64::from_le_bytes(data))
+}
+
+impl Encode for GtidEvent {
+    fn encode(&self, buf: &mut BytesMut) {
+        self.flags.encode(buf);
+        buf.extend_from_slice(&self.sid);
+        buf.put_u64_le(self.gno);
+        let Some((last_committed, sequence_number)) = self.logical_clock else {
+            return;
+        };
+        buf.put_u8(LOGICAL_TIMESTAMP_TYPECODE);
+        buf.put_i64_le(last_committed);
+        buf.put_i64_le(sequence_number);
+        let Some((immediate, original)) = self.commit_timestamps else {
+            return;
+        };
+        if immediate == original {
+            buf.extend_from_slice(&immediate.to_le_bytes()[..7]);
+        } else {
+            buf.extend_from_slice(&(immediate | 1 << 55).to_le_bytes()[..7]);
+            buf.extend_from_slice(&original.to_le_bytes()[..7]);
+        }
+        let Some(transaction_length) = self.transaction_length else {
+            return;
+        };
+        VLenInt::new(transaction_length).encode(buf);
+        let Some((immediate, original)) = self.server_versions else {
+            return;
+        };
+        if immediate == original {
+            buf.put_u32_le(immediate);
+        } else {
+            buf.put_u32_le(immediate | 1 << 31);
+            buf.put_u32_le(original);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gtid_set() {
+        let text =
+            "3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5:7,e3e2a4ee-b6dc-11ea-8bcf-0242ac150002:1";
+        let mut set: GtidSet = text.parse().unwrap();
+        assert_eq!(set.to_string(), text);
+        let sid = parse_sid("3e11fa47-71ca-11e1-9e33-c80aa9429562").unwrap();
+        assert!(set.contains(&sid, 5));
+        assert!(!set.contains(&sid, 6));
+        set.add(sid, 6).unwrap();
+        assert_eq!(set.intervals(&sid), &[(1, 8)]);
+        assert!(set.add(sid, u64::MAX).is_err());
+        let max = format!("{}:{}", format_sid(&sid), u64::MAX);
+        assert!(max.parse::<GtidSet>().is_err());
+        let mut buf = BytesMut::new();
+        set.encode(&mut buf);
+        assert_eq!(GtidSet::decode(&mut buf).unwrap(), set);
+    }
+}
@@ -0,0 +1,118 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use parse_tool::InputBuf;
+
+use crate::codec::{put_null_term_str, Decode, DecodeError, Encode, Int1, Int2, Int4};
+
+use super::statement::{decode_name, decode_status_vars, encode_status_vars, QueryStatusVar};
+
+/// append block event, also used as begin load query event
+///
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/classmysql_1_1binlog_1_1event_1_1Append__block__event.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppendBlockEvent {
+    pub file_id: Int4,
+    pub data: Bytes,
+}
+
+impl Decode<BytesMut> for AppendBlockEvent {
+    fn decode(input: &mut BytesMut) -> Result<Self, DecodeError> {
+        let file_id = Int4::decode(input)?;
+        let data = input.split().freeze();
+        Ok(Self { file_id, data })
+    }
+}
+
+impl Encode for AppendBlockEvent {
+    fn encode(&self, buf: &mut BytesMut) {
+        self.file_id.encode(buf);
+        buf.extend_from_slice(&self.data);
+    }
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/classmysql_1_1binlog_1_1event_1_1Delete__file__event.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeleteFileEvent {
+    pub file_id: Int4,
+}
+
+impl<I: InputBuf> Decode<I> for DeleteFileEvent {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        Ok(Self {
+            file_id: Int4::decode(input)?,
+        })
+    }
+}
+
+impl Encode for DeleteFileEvent {
+    fn encode(&self, buf: &mut BytesMut) {
+        self.file_id.encode(buf);
+    }
+}
+
+/// query event of `LOAD DATA INFILE`, file content is sent by preceding begin load query
+/// and append block events
+///
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/classmysql_1_1binlog_1_1event_1_1Execute__load__query__event.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecuteLoadQueryEvent {
+    pub slave_proxy_id: Int4,
+    pub exec_time: Int4,
+    pub error_code: Int2,
+    pub file_id: Int4,
+    /// offset of file name in query
+    pub start_pos: Int4,
+    pub end_pos: Int4,
+    /// 0 is error, 1 is ignore and 2 is replace
+    pub dup_handling: Int1,
+    pub status_vars: Vec<QueryStatusVar>,
+    pub schema: String,
+    pub query: Bytes,
+}
+
+impl Decode<BytesMut> for ExecuteLoadQueryEvent {
+    fn decode(input: &mut BytesMut) -> Result<Self, DecodeError> {
+        let slave_proxy_id = Int4::decode(input)?;
+        let exec_time = Int4::decode(input)?;
+        let schema_len = input.read_u8_le()? as usize;
+        let error_code = Int2::decode(input)?;
+        let status_vars_len = input.read_u16_le()? as usize;
+        let file_id = Int4::decode(input)?;
+        let start_pos = Int4::decode(input)?;
+        let end_pos = Int4::decode(input)?;
+        let dup_handling = Int1::decode(input)?;
+        let status_vars = decode_status_vars(input, status_vars_len)?;
+        let schema = decode_name(input, schema_len)?;
+        let query = input.split().freeze();
+        Ok(Self {
+            slave_proxy_id,
+            exec_time,
+            error_code,
+            file_id,
+            start_pos,
+            end_pos,
+            dup_handling,
+            status_vars,
+            schema,
+            query,
+        })
+    }
+}
+
+impl Encode for ExecuteLoadQueryEvent {
+    fn encode(&self, buf: &mut BytesMut) {
+        let mut status_vars = BytesMut::new();
+        encode_status_vars(&self.status_vars, &mut status_vars);
+        self.slave_proxy_id.encode(buf);
+        self.exec_time.encode(buf);
+        buf.put_u8(self.schema.len() as u8);
+        self.error_code.encode(buf);
+        buf.put_u16_le(status_vars.len() as u16);
+        self.file_id.encode(buf);
+        self.start_pos.encode(buf);
+        self.end_pos.encode(buf);
+        self.dup_handling.encode(buf);
+        buf.extend_from_slice(&status_vars);
+        put_null_term_str(&self.schema, buf);
+        buf.extend_from_slice(&self.query);
+    }
+}
@@ -0,0 +1,806 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use bytes::BytesMut;
+use thiserror::Error;
+
+use crate::{
+    binlog::{
+        binlog_files, format_desc_checksum, index_file_name, read_index, Event, EventFilter,
+        EventFilterOpts, EventPayload, EventType, GtidSet, TransactionTracker, BINLOG_MAGIC,
+        EVENT_HEADER_LEN,
+    },
+    checkpoint::BinlogPosition,
+    codec::DecodeError,
+};
+
+#[derive(Debug, Error)]
+pub enum ReaderError {
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    #[error("decode error at {offset}: {error}")]
+    Decode { offset: u64, error: DecodeError },
+    #[error("not a binlog file, invalid magic header")]
+    InvalidMagic,
+    #[error("position {0} is not at the beginning of an event")]
+    InvalidPosition(u64),
+    #[error("incomplete event at {0}")]
+    Incomplete(u64),
+    #[error("binlog file {} is missing", .0.display())]
+    MissingFile(PathBuf),
+    #[error("binlog file {0} is not in sequence")]
+    UnknownFile(String),
+}
+
+/// reads events of a binlog file one by one from any [`Read`], only one event is buffered
+/// at a time
+///
+/// every event is yielded with its offset in file, CRC32 of events is verified when
+/// format description event enables it.
+#[derive(Debug)]
+pub struct BinlogFileReader<R> {
+    input: R,
+    offset: u64,
+    checksum: bool,
+    /// bytes of event being read, kept across calls when input ends in the middle of it
+    buf: BytesMut,
+}
+
+impl BinlogFileReader<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ReaderError> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> BinlogFileReader<R> {
+    /// read and validate magic header, `input` must be at the beginning of a binlog file
+    pub fn new(mut input: R) -> Result<Self, ReaderError> {
+        let mut magic = [0; 4];
+        match input.read_exact(&mut magic) {
+            Ok(()) if magic == BINLOG_MAGIC => {}
+            Ok(()) => return Err(ReaderError::InvalidMagic),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                return Err(ReaderError::InvalidMagic)
+            }
+            Err(e) => return Err(e.into()),
+        }
+        Ok(Self {
+            input,
+            offset: BINLOG_MAGIC.len() as u64,
+            checksum: false,
+            buf: BytesMut::new(),
+        })
+    }
+
+    /// offset of next event
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// whether events carry CRC32, decided by last format description event
+    pub fn checksum(&self) -> bool {
+        self.checksum
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.input
+    }
+
+    pub fn into_inner(self) -> R {
+        self.input
+    }
+
+    /// next complete event as raw bytes, `None` at the end of input
+    ///
+    /// when input ends in the middle of an event, [`ReaderError::Incomplete`] is returned
+    /// and read bytes are kept, so reading again continues the event once input grows.
+    pub fn next_raw(&mut self) -> Result<Option<BytesMut>, ReaderError> {
+        if !self.fill(EVENT_HEADER_LEN)? {
+            return Ok(None);
+        }
+        let size = u32::from_le_bytes([self.buf[9], self.buf[10], self.buf[11], self.buf[12]]);
+        if (size as usize) < EVENT_HEADER_LEN {
+            return Err(ReaderError::Decode {
+                offset: self.offset,
+                error: DecodeError::InvalidData,
+            });
+        }
+        self.fill(size as usize)?;
+        self.offset += size as u64;
+        Ok(Some(self.buf.split()))
+    }
+
+    /// read until `buf` has `len` bytes, `false` if input ends before any byte is read
+    fn fill(&mut self, len: usize) -> Result<bool, ReaderError> {
+        let mut chunk = [0; 8192];
+        while self.buf.len() < len {
+            let want = (len - self.buf.len()).min(chunk.len());
+            match self.input.read(&mut chunk[..want]) {
+                Ok(0) if self.buf.is_empty() => return Ok(false),
+                Ok(0) => return Err(ReaderError::Incomplete(self.offset)),
+                Ok(n) => self.buf.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(true)
+    }
+
+    fn read_event(&mut self) -> Result<Option<(u64, Event<EventPayload>)>, ReaderError> {
+        let offset = self.offset;
+        let Some(data) = self.next_raw()? else {
+            return Ok(None);
+        };
+        Ok(Some((offset, self.decode(offset, data)?)))
+    }
+
+    /// decode raw event read at `offset`
+    fn decode(
+        &mut self,
+        offset: u64,
+        mut data: BytesMut,
+    ) -> Result<Event<EventPayload>, ReaderError> {
+        if data[4] == EventType::FormatDescription as u8 {
+            self.checksum = format_desc_checksum(&data) == Some(1);
+        }
+        Event::decode_with(&mut data, self.checksum)
+            .map_err(|error| ReaderError::Decode { offset, error })
+    }
+}
+
+impl<R: Read + Seek> BinlogFileReader<R> {
+    /// seek to `pos`, format description event is still read to setup checksum
+    ///
+    /// `pos` must be the offset of an event or end of file, it's checked by log position
+    /// in header of the event at `pos` when the header is written.
+    pub fn skip_to(&mut self, pos: u64) -> Result<(), ReaderError> {
+        if self.offset == BINLOG_MAGIC.len() as u64 && pos > self.offset {
+            if let Some(event) = self.next_raw()? {
+                if event[4] == EventType::FormatDescription as u8 {
+                    self.checksum = format_desc_checksum(&event) == Some(1);
+                }
+            }
+        }
+        if pos == self.offset {
+            return Ok(());
+        }
+        if pos < self.offset || pos > self.input.seek(SeekFrom::End(0))? {
+            return Err(ReaderError::InvalidPosition(pos));
+        }
+        self.input.seek(SeekFrom::Start(pos))?;
+        self.offset = pos;
+        self.buf.clear();
+        match self.fill(EVENT_HEADER_LEN) {
+            Ok(true) => {
+                let size =
+                    u32::from_le_bytes([self.buf[9], self.buf[10], self.buf[11], self.buf[12]]);
+                let log_pos =
+                    u32::from_le_bytes([self.buf[13], self.buf[14], self.buf[15], self.buf[16]]);
+                if (size as usize) < EVENT_HEADER_LEN
+                    || (log_pos != 0 && log_pos as u64 != pos + size as u64)
+                {
+                    return Err(ReaderError::InvalidPosition(pos));
+                }
+                Ok(())
+            }
+            // header is not written yet
+            Ok(false) | Err(ReaderError::Incomplete(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl<R: Read> Iterator for BinlogFileReader<R> {
+    /// offset of event in file and the event
+    type Item = Result<(u64, Event<EventPayload>), ReaderError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_event().transpose()
+    }
+}
+
+/// options of [`BinlogSequenceReader`]
+#[derive(Debug, Clone)]
+pub struct BinlogSequenceOpts {
+    /// first event to read, beginning of first file by default
+    pub start: Option<BinlogPosition>,
+    /// stop before the event at or after this position
+    pub stop: Option<BinlogPosition>,
+    /// skip events before this unix timestamp, files created later are found by binary
+    /// search on timestamps of their first events
+    pub start_datetime: Option<u32>,
+    /// stop at the first event at or after this unix timestamp
+    pub stop_datetime: Option<u32>,
+    /// only read transactions of these GTIDs, like `mysqlbinlog --include-gtids`
+    pub include_gtids: Option<GtidSet>,
+    /// skip transactions of these GTIDs, like `mysqlbinlog --exclude-gtids`
+    pub exclude_gtids: Option<GtidSet>,
+    /// wait for new events at the end of last file like `tail -F` instead of stopping
+    pub follow: bool,
+    /// how often a growing file is checked in follow mode
+    pub poll_interval: Duration,
+    /// drop unwanted events, rows events of filtered tables are not decoded
+    pub filter: Option<EventFilterOpts>,
+}
+
+impl Default for BinlogSequenceOpts {
+    fn default() -> Self {
+        Self {
+            start: None,
+            stop: None,
+            start_datetime: None,
+            stop_datetime: None,
+            include_gtids: None,
+            exclude_gtids: None,
+            follow: false,
+            poll_interval: Duration::from_millis(200),
+            filter: None,
+        }
+    }
+}
+
+/// reads events of a sequence of binlog files listed by index file or found in a
+/// directory, like `mysqlbinlog mysql-bin.*`
+///
+/// next file is decided by rotate event at the end of each file, or the next listed file
+/// when there is none. events are yielded with their positions.
+///
+/// in follow mode, reader waits at the end of last file for the server to write more,
+/// partially written event is kept until it's complete.
+#[derive(Debug)]
+pub struct BinlogSequenceReader {
+    /// index file or directory files are listed from
+    source: Option<PathBuf>,
+    /// name and path of files in order
+    files: Vec<(String, PathBuf)>,
+    opts: BinlogSequenceOpts,
+    /// index of current file in `files`
+    idx: usize,
+    reader: Option<BinlogFileReader<BufReader<File>>>,
+    /// file and position named by last rotate event
+    rotate: Option<(String, u64)>,
+    checksum: bool,
+    /// an event at or after `start_datetime` is read
+    started: bool,
+    /// in a transaction filtered out by GTID
+    skip_transaction: bool,
+    tracker: TransactionTracker,
+    filter: Option<EventFilter>,
+    done: bool,
+}
+
+impl BinlogSequenceReader {
+    /// `path` is either an index file like `mysql-bin.index` or a directory, binlog files
+    /// in directory are read in name order
+    pub fn open(path: impl AsRef<Path>, opts: BinlogSequenceOpts) -> Result<Self, ReaderError> {
+        let path = path.as_ref();
+        let mut this = Self::with_files(list_files(path)?, opts)?;
+        this.source = Some(path.to_path_buf());
+        Ok(this)
+    }
+
+    /// read given files in order, files are named by their file names
+    pub fn with_files(
+        files: Vec<(String, PathBuf)>,
+        opts: BinlogSequenceOpts,
+    ) -> Result<Self, ReaderError> {
+        let mut this = Self {
+            source: None,
+            files,
+            filter: opts.filter.clone().map(EventFilter::new),
+            opts,
+            idx: 0,
+            reader: None,
+            rotate: None,
+            checksum: false,
+            started: false,
+            skip_transaction: false,
+            tracker: TransactionTracker::new(),
+            done: false,
+        };
+        let (mut idx, mut pos) = match &this.opts.start {
+            Some(start) => (this.position_of(&start.file)?, start.pos),
+            None if this.files.is_empty() => {
+                this.done = true;
+                return Ok(this);
+            }
+            None => (0, BINLOG_MAGIC.len() as u64),
+        };
+        if let Some(datetime) = this.opts.start_datetime {
+            let found = this.file_at(datetime, idx)?;
+            if found > idx {
+                (idx, pos) = (found, BINLOG_MAGIC.len() as u64);
+            }
+        }
+        this.idx = idx;
+        this.open_file()?.skip_to(pos)?;
+        Ok(this)
+    }
+
+    pub fn opts(&self) -> &BinlogSequenceOpts {
+        &self.opts
+    }
+
+    /// name of file being read
+    pub fn file(&self) -> Option<&str> {
+        self.files.get(self.idx).map(|(name, _)| name.as_str())
+    }
+
+    fn position_of(&self, name: &str) -> Result<usize, ReaderError> {
+        self.files
+            .iter()
+            .position(|(file, _)| file == name)
+            .ok_or_else(|| ReaderError::UnknownFile(name.to_string()))
+    }
+
+    /// last file from `from` whose first event is not after `datetime`
+    fn file_at(&self, datetime: u32, from: usize) -> Result<usize, ReaderError> {
+        let (mut low, mut high) = (from + 1, self.files.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if first_timestamp(&self.files[mid].1)? <= datetime {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        Ok(low - 1)
+    }
+
+    fn open_file(&mut self) -> Result<&mut BinlogFileReader<BufReader<File>>, ReaderError> {
+        let file = open_binlog(&self.files[self.idx].1)?;
+        let mut reader = BinlogFileReader::new(BufReader::new(file))?;
+        // format description event of file decides again, previous file is the best guess
+        reader.checksum = self.checksum;
+        Ok(self.reader.insert(reader))
+    }
+
+    /// move to file named by last rotate event, or next file in sequence, `false` if
+    /// there is no more file
+    fn next_file(&mut self) -> Result<bool, ReaderError> {
+        let (idx, pos) = match self.rotate.clone() {
+            Some((name, pos)) => match self.position_of(&name) {
+                Ok(idx) => (idx, pos),
+                // rotated into a file not listed yet, e.g. index file is stale
+                Err(_) => {
+                    let dir = self.files[self.idx].1.parent().unwrap_or(Path::new("."));
+                    let path = dir.join(&name);
+                    if !path.exists() {
+                        if self.opts.follow {
+                            return self.wait();
+                        }
+                        return Err(ReaderError::MissingFile(path));
+                    }
+                    self.files.insert(self.idx + 1, (name, path));
+                    (self.idx + 1, pos)
+                }
+            },
+            None if self.idx + 1 < self.files.len() => (self.idx + 1, BINLOG_MAGIC.len() as u64),
+            None => return Ok(false),
+        };
+        // file is created by server but magic header is not written yet
+        let created = fs::metadata(&self.files[idx].1)
+            .map_or(true, |meta| meta.len() >= BINLOG_MAGIC.len() as u64);
+        if self.opts.follow && !created {
+            return self.wait();
+        }
+        self.rotate = None;
+        self.idx = idx;
+        self.open_file()?.skip_to(pos)?;
+        Ok(true)
+    }
+
+    /// whether current file is followed by another one, listing is reloaded in follow mode
+    /// since server adds new file to index only after previous file is complete
+    fn has_next_file(&mut self) -> Result<bool, ReaderError> {
+        if let (true, Some(source)) = (self.opts.follow, &self.source) {
+            let files = list_files(source)?;
+            let current = &self.files[self.idx].0;
+            if let Some(idx) = files.iter().position(|(name, _)| name == current) {
+                self.files = files;
+                self.idx = idx;
+            }
+        }
+        Ok(self.idx + 1 < self.files.len())
+    }
+
+    fn wait(&self) -> Result<bool, ReaderError> {
+        thread::sleep(self.opts.poll_interval);
+        Ok(true)
+    }
+
+    fn read_event(&mut self) -> Result<Option<(BinlogPosition, Event<EventPayload>)>, ReaderError> {
+        while !self.done {
+            let Some(reader) = self.reader.as_mut() else {
+                if !self.next_file()? {
+                    self.done = true;
+                }
+                continue;
+            };
+            let pos = BinlogPosition::new(&self.files[self.idx].0, reader.offset());
+            if self.opts.stop.as_ref().is_some_and(|stop| pos >= *stop) {
+                self.done = true;
+                break;
+            }
+            let data = match reader.next_raw() {
+                Ok(data) => data,
+                // event is being written, otherwise file is truncated
+                Err(ReaderError::Incomplete(_)) if self.opts.follow && !self.has_next_file()? => {
+                    self.wait()?;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            let Some(data) = data else {
+                self.checksum = reader.checksum;
+                if self.opts.follow && !self.has_next_file()? {
+                    self.wait()?;
+                } else {
+                    self.reader = None;
+                }
+                continue;
+            };
+            if self.filter.as_ref().is_some_and(|f| f.skip_raw(&data)) {
+                continue;
+            }
+            let event = reader.decode(pos.pos, data)?;
+            if let EventPayload::Rotate(rotate) = &event.payload {
+                let next = BinlogPosition::new(&rotate.log, rotate.pos.int());
+                // rotate event pointing backwards is ignored to avoid reading files forever
+                if next > pos {
+                    self.checksum = reader.checksum;
+                    self.reader = None;
+                    // next file is not required to exist when reading stops before it
+                    self.done = self.opts.stop.as_ref().is_some_and(|stop| next >= *stop);
+                    self.rotate = Some((next.file, next.pos));
+                }
+            }
+            // filter sees every decoded event to keep track of tables and transactions
+            let kept = self.filter.as_mut().is_none_or(|f| f.accept(&event));
+            if self.accept(&event) && kept {
+                return Ok(Some((pos, event)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// apply datetime and GTID options
+    fn accept(&mut self, event: &Event<EventPayload>) -> bool {
+        let ends_transaction = self.tracker.update(&event.payload);
+        let timestamp = event.header.timestamp.int();
+        if self
+            .opts
+            .stop_datetime
+            .is_some_and(|stop| timestamp >= stop)
+        {
+            self.done = true;
+            return false;
+        }
+        if !self.started {
+            if self
+                .opts
+                .start_datetime
+                .is_some_and(|start| timestamp < start)
+            {
+                return false;
+            }
+            self.started = true;
+        }
+        if self.opts.include_gtids.is_none() && self.opts.exclude_gtids.is_none() {
+            return true;
+        }
+        match &event.payload {
+            EventPayload::Gtid(gtid) if event.header.event_type.int() == EventType::Gtid as u8 => {
+                let included = self
+                    .opts
+                    .include_gtids
+                    .as_ref()
+                    .is_none_or(|set| set.contains(&gtid.sid, gtid.gno));
+                let excluded = self
+                    .opts
+                    .exclude_gtids
+                    .as_ref()
+                    .is_some_and(|set| set.contains(&gtid.sid, gtid.gno));
+                self.skip_transaction = !included || excluded;
+            }
+            // end of skipped transaction
+            _ if self.skip_transaction && ends_transaction => {
+                self.skip_transaction = false;
+                return false;
+            }
+            _ => {}
+        }
+        !self.skip_transaction
+    }
+}
+
+fn open_binlog(path: &Path) -> Result<File, ReaderError> {
+    match File::open(path) {
+        Ok(file) => Ok(file),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            Err(ReaderError::MissingFile(path.to_path_buf()))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// timestamp of first event, which is creation time of file, newly created file without
+/// any event is treated as the latest
+fn first_timestamp(path: &Path) -> Result<u32, ReaderError> {
+    let mut head = [0; BINLOG_MAGIC.len() + EVENT_HEADER_LEN];
+    match open_binlog(path)?.read_exact(&mut head) {
+        Ok(()) => Ok(u32::from_le_bytes([head[4], head[5], head[6], head[7]])),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(u32::MAX),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// name and path of binlog files listed by index file or in directory
+fn list_files(path: &Path) -> io::Result<Vec<(String, PathBuf)>> {
+    if path.is_dir() {
+        Ok(binlog_files(path)?
+            .into_iter()
+            .map(|(name, _)| {
+                let file = path.join(&name);
+                (name, file)
+            })
+            .collect())
+    } else {
+        let dir = path.parent().unwrap_or(Path::new("."));
+        Ok(read_index(path)?
+            .iter()
+            .map(|entry| (index_file_name(entry).to_string(), dir.join(entry)))
+            .collect())
+    }
+}
+
+impl Iterator for BinlogSequenceReader {
+    /// position of event and the event
+    type Item = Result<(BinlogPosition, Event<EventPayload>), ReaderError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_event() {
+            Ok(event) => event.map(Ok),
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        binlog::{BinlogWriter, BinlogWriterOpts},
+        test_util::temp_dir,
+    };
+
+    #[test]
+    fn test_binlog_file_reader() {
+        let data = include_bytes!("../../../../tests/events/02_query/log.bin");
+        let reader = BinlogFileReader::new(&data[..]).unwrap();
+        let events: Vec<_> = reader.map(|e| e.unwrap()).collect();
+        assert_eq!(events.len(), 7);
+        assert_eq!(events[0].0, 4);
+        for pair in events.windows(2) {
+            assert_eq!(pair[1].0, pair[0].1.header.log_pos.int() as u64);
+        }
+
+        let mut reader = BinlogFileReader::new(io::Cursor::new(&data[..])).unwrap();
+        reader.skip_to(events[3].0).unwrap();
+        assert!(reader.checksum());
+        let (offset, event) = reader.next().unwrap().unwrap();
+        assert_eq!(offset, events[3].0);
+        assert!(matches!(event.payload, EventPayload::Query(_)));
+
+        let mut reader = BinlogFileReader::new(io::Cursor::new(&data[..])).unwrap();
+        assert!(matches!(
+            reader.skip_to(events[3].0 + 1),
+            Err(ReaderError::InvalidPosition(_))
+        ));
+        let mut reader = BinlogFileReader::new(io::Cursor::new(&data[..])).unwrap();
+        assert!(matches!(
+            reader.skip_to(data.len() as u64 + 1),
+            Err(ReaderError::InvalidPosition(_))
+        ));
+        // end of file is a valid position
+        let mut reader = BinlogFileReader::new(io::Cursor::new(&data[..])).unwrap();
+        reader.skip_to(data.len() as u64).unwrap();
+        assert!(reader.next().is_none());
+        assert!(matches!(
+            BinlogFileReader::new(&data[1..]),
+            Err(ReaderError::InvalidMagic)
+        ));
+        let mut reader = BinlogFileReader::new(&data[..data.len() - 1]).unwrap();
+        assert!(matches!(
+            reader.nth(6),
+            Some(Err(ReaderError::Incomplete(_)))
+        ));
+    }
+
+    #[test]
+    fn test_binlog_sequence_reader() {
+        let dir = temp_dir("sequence");
+        let data = include_bytes!("../../../../tests/events/02_query/log.bin");
+        let mut opts = BinlogWriterOpts::new(&dir, "mysql-bin");
+        opts.max_size = 1;
+        let mut writer = BinlogWriter::open(opts).unwrap();
+        for event in BinlogFileReader::new(&data[..]).unwrap().skip(2).take(4) {
+            writer.append(&event.unwrap().1).unwrap();
+        }
+        writer.close().unwrap();
+
+        // every file has FDE, previous GTIDs and rotate or stop, 2 transactions in 3 files
+        let index = dir.join("mysql-bin.index");
+        let events: Vec<_> = BinlogSequenceReader::open(&index, Default::default())
+            .unwrap()
+            .map(|e| e.unwrap().0.to_string())
+            .collect();
+        assert_eq!(events.len(), 13);
+        assert_eq!(events[5], "mysql-bin.000002:4");
+        let from_dir = BinlogSequenceReader::open(&dir, Default::default()).unwrap();
+        assert_eq!(from_dir.count(), 13);
+
+        let opts = BinlogSequenceOpts {
+            start: Some(events[3].parse().unwrap()),
+            stop: Some(events[10].parse().unwrap()),
+            ..Default::default()
+        };
+        let positions: Vec<_> = BinlogSequenceReader::open(&index, opts)
+            .unwrap()
+            .map(|e| e.unwrap().0.to_string())
+            .collect();
+        assert_eq!(positions, events[3..10]);
+
+        std::fs::remove_file(dir.join("mysql-bin.000002")).unwrap();
+        let result: Result<Vec<_>, _> = BinlogSequenceReader::open(&index, Default::default())
+            .unwrap()
+            .collect();
+        assert!(matches!(result, Err(ReaderError::MissingFile(_))));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_binlog_sequence_follow() {
+        let dir = temp_dir("follow");
+        let file1 = include_bytes!("../../../../tests/events/02_query/log.bin");
+        let file2 = include_bytes!("../../../../tests/events/04_rotate/log.bin");
+        // in the middle of format description event
+        std::fs::write(dir.join("mysql_bin.000001"), &file1[..100]).unwrap();
+
+        let opts = BinlogSequenceOpts {
+            stop: Some(BinlogPosition::new("mysql_bin.000002", file2.len() as u64)),
+            follow: true,
+            poll_interval: Duration::from_millis(5),
+            ..Default::default()
+        };
+        let reader = BinlogSequenceReader::open(&dir, opts).unwrap();
+        let handle = thread::spawn(move || reader.map(|e| e.unwrap().0).collect::<Vec<_>>());
+        thread::sleep(Duration::from_millis(30));
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .open(dir.join("mysql_bin.000001"))
+            .unwrap();
+        std::io::Write::write_all(&mut file, &file1[100..]).unwrap();
+        thread::sleep(Duration::from_millis(30));
+        // rotated file is created before its content is written
+        std::fs::write(dir.join("mysql_bin.000002"), b"").unwrap();
+        thread::sleep(Duration::from_millis(30));
+        std::fs::write(dir.join("mysql_bin.000002"), file2).unwrap();
+
+        let positions = handle.join().unwrap();
+        assert_eq!(positions.len(), 10);
+        assert_eq!(positions[7], BinlogPosition::new("mysql_bin.000002", 4));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_binlog_sequence_filter() {
+        let dir = temp_dir("filter");
+        let file1 = include_bytes!("../../../../tests/events/02_query/log.bin");
+        let file2 = include_bytes!("../../../../tests/events/03_stop/log.bin");
+        let file3 = include_bytes!("../../../../tests/events/05_intvar/log.bin");
+        for (i, data) in [&file1[..], file2, file3].iter().enumerate() {
+            std::fs::write(dir.join(format!("mysql_bin.00000{}", i + 1)), data).unwrap();
+        }
+        let index = dir.join("mysql_bin.index");
+        std::fs::write(
+            &index,
+            "./mysql_bin.000001\n./mysql_bin.000002\n./mysql_bin.000003\n",
+        )
+        .unwrap();
+        // between creation of the second and third file
+        let datetime = 1594395400;
+        let read = |opts: BinlogSequenceOpts| {
+            BinlogSequenceReader::open(&index, opts)
+                .unwrap()
+                .map(|e| e.unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let events = read(BinlogSequenceOpts {
+            stop_datetime: Some(datetime),
+            ..Default::default()
+        });
+        assert_eq!(events.len(), 10);
+
+        let gtids: Vec<_> = events
+            .iter()
+            .filter_map(|(_, event)| match &event.payload {
+                EventPayload::Gtid(gtid) => Some((gtid.sid, gtid.gno)),
+                _ => None,
+            })
+            .collect();
+        let mut first = GtidSet::new();
+        first.add(gtids[0].0, gtids[0].1).unwrap();
+        let stop = Some(BinlogPosition::new("mysql_bin.000002", 4));
+        let included = read(BinlogSequenceOpts {
+            stop: stop.clone(),
+            include_gtids: Some(first.clone()),
+            ..Default::default()
+        });
+        let excluded = read(BinlogSequenceOpts {
+            stop,
+            exclude_gtids: Some(first),
+            ..Default::default()
+        });
+        let sql = |events: &[(BinlogPosition, Event<EventPayload>)]| {
+            events
+                .iter()
+                .filter_map(|(_, event)| match &event.payload {
+                    EventPayload::Query(query) => Some(query.sql().to_lowercase()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(included.len(), 5);
+        assert_eq!(excluded.len(), 5);
+        assert!(sql(&included)[0].starts_with("drop table"));
+        assert!(sql(&excluded)[0].starts_with("create table"));
+
+        // first file is never opened since it's created before the second one
+        std::fs::remove_file(dir.join("mysql_bin.000001")).unwrap();
+        let events = read(BinlogSequenceOpts {
+            start_datetime: Some(datetime),
+            ..Default::default()
+        });
+        assert_eq!(events.len(), 12);
+        assert_eq!(events[0].0, BinlogPosition::new("mysql_bin.000003", 4));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_exclude_statement_transaction() {
+        let dir = temp_dir("exclude");
+        let events = crate::binlog::tests::statement_events();
+        let mut writer = BinlogWriter::open(BinlogWriterOpts::new(&dir, "mysql-bin")).unwrap();
+        for event in &events {
+            writer.append(event).unwrap();
+        }
+        writer.close().unwrap();
+
+        // GTID of transaction with three inserts
+        let Some(EventPayload::Gtid(gtid)) = events.get(4).map(|e| &e.payload) else {
+            panic!("not a GTID event");
+        };
+        let mut exclude = GtidSet::new();
+        exclude.add(gtid.sid, gtid.gno).unwrap();
+        let opts = BinlogSequenceOpts {
+            exclude_gtids: Some(exclude),
+            ..Default::default()
+        };
+        let types: Vec<_> = BinlogSequenceReader::open(&dir, opts)
+            .unwrap()
+            .map(|e| e.unwrap().1.header.event_type.int())
+            .collect();
+        assert_eq!(types, [15, 35, 33, 2, 33, 2, 3]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,468 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use parse_tool::InputBuf;
+
+use crate::{
+    codec::{get_bytes, put_var_bytes, Decode, DecodeError, Encode, Int2, Int6, VLenInt},
+    value::{binlog_value_len, ColumnType, Value, ValueRef},
+};
+
+use super::{statement::decode_name, EventType};
+
+fn bitmap_len(column_count: u64) -> usize {
+    (column_count as usize).div_ceil(8)
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/classmysql_1_1binlog_1_1event_1_1Table__map__event.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableMapEvent {
+    pub table_id: Int6,
+    pub flags: Int2,
+    pub schema: String,
+    pub table: String,
+    /// column type of every column
+    pub column_types: Bytes,
+    /// metadata of all columns, length of each column depends on its type
+    pub column_metas: Bytes,
+    pub null_bitmap: Bytes,
+    /// optional metadata fields in TLV format, since 8.0.1
+    pub optional_metadata: Bytes,
+}
+
+impl Decode<BytesMut> for TableMapEvent {
+    fn decode(input: &mut BytesMut) -> Result<Self, DecodeError> {
+        let table_id = Int6::decode(input)?;
+        let flags = Int2::decode(input)?;
+        let schema_len = input.read_u8_le()? as usize;
+        let schema = decode_name(input, schema_len)?;
+        let table_len = input.read_u8_le()? as usize;
+        let table = decode_name(input, table_len)?;
+        let column_count = VLenInt::decode(input)?.int();
+        let column_types = get_bytes(input, column_count as usize)?;
+        let metas_len = VLenInt::decode(input)?.int() as usize;
+        let column_metas = get_bytes(input, metas_len)?;
+        let null_bitmap = get_bytes(input, bitmap_len(column_count))?;
+        let optional_metadata = input.split().freeze();
+        Ok(Self {
+            table_id,
+            flags,
+            schema,
+            table,
+            column_types,
+            column_metas,
+            null_bitmap,
+            optional_metadata,
+        })
+    }
+}
+
+/// column definition in table map event, used to decode rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BinlogColumn {
+    pub ty: ColumnType,
+    /// type specific metadata, e.g. max length of varchar or precision and scale of decimal
+    pub meta: u16,
+    pub nullable: bool,
+    /// only known when `binlog_row_metadata` includes signedness, signed by default
+    pub unsigned: bool,
+}
+
+impl TableMapEvent {
+    /// definitions of all columns
+    pub fn columns(&self) -> Result<Vec<BinlogColumn>, DecodeError> {
+        let unsigned = self.unsigned_columns()?;
+        let mut metas = &self.column_metas[..];
+        let mut numeric = 0;
+        let mut columns = Vec::with_capacity(self.column_types.len());
+        for (i, ty) in self.column_types.iter().enumerate() {
+            let ty = ColumnType::try_from(*ty)?;
+            let meta_len = match ty {
+                ColumnType::Float
+                | ColumnType::Double
+                | ColumnType::Blob
+                | ColumnType::TinyBlob
+                | ColumnType::MediumBlob
+                | ColumnType::LongBlob
+                | ColumnType::Json
+                | ColumnType::Geometry
+                | ColumnType::Vector
+                | ColumnType::Time2
+                | ColumnType::Timestamp2
+                | ColumnType::DateTime2 => 1,
+                ColumnType::VarChar
+                | ColumnType::VarString
+                | ColumnType::Bit
+                | ColumnType::NewDecimal
+                | ColumnType::String
+                | ColumnType::Enum
+                | ColumnType::Set => 2,
+                _ => 0,
+            };
+            let (meta, rest) = metas
+                .split_at_checked(meta_len)
+                .ok_or(DecodeError::NoEnoughData)?;
+            metas = rest;
+            let meta = match (ty, meta) {
+                (_, []) => 0,
+                (_, [m]) => *m as u16,
+                // precision and scale, real type and length
+                (
+                    ColumnType::NewDecimal
+                    | ColumnType::String
+                    | ColumnType::Enum
+                    | ColumnType::Set,
+                    [a, b],
+                ) => u16::from_be_bytes([*a, *b]),
+                (_, [a, b]) => u16::from_le_bytes([*a, *b]),
+                _ => unreachable!(),
+            };
+            let is_numeric = matches!(
+                ty,
+                ColumnType::Tiny
+                    | ColumnType::Short
+                    | ColumnType::Int24
+                    | ColumnType::Long
+                    | ColumnType::LongLong
+                    | ColumnType::Float
+                    | ColumnType::Double
+                    | ColumnType::NewDecimal
+            );
+            columns.push(BinlogColumn {
+                ty,
+                meta,
+                nullable: self
+                    .null_bitmap
+                    .get(i / 8)
+                    .is_some_and(|b| b >> (i % 8) & 1 == 1),
+                unsigned: is_numeric && unsigned.get(numeric).copied().unwrap_or(false),
+            });
+            numeric += is_numeric as usize;
+        }
+        Ok(columns)
+    }
+
+    /// unsigned flags of numeric columns from optional metadata, empty if not present
+    fn unsigned_columns(&self) -> Result<Vec<bool>, DecodeError> {
+        // signedness, bitmap of numeric columns with most significant bit first
+        const SIGNEDNESS: u8 = 1;
+        let mut input = BytesMut::from(&self.optional_metadata[..]);
+        while !input.is_empty() {
+            let ty = input.read_u8_le()?;
+            let len = VLenInt::decode(&mut input)?.int() as usize;
+            let value = input.read_vec(len)?;
+            if ty == SIGNEDNESS {
+                return Ok(value
+                    .iter()
+                    .flat_map(|b| (0..8).rev().map(move |i| b >> i & 1 == 1))
+                    .collect());
+            }
+        }
+        Ok(vec![])
+    }
+}
+
+impl Encode for TableMapEvent {
+    fn encode(&self, buf: &mut BytesMut) {
+        self.table_id.encode(buf);
+        self.flags.encode(buf);
+        for name in [&self.schema, &self.table] {
+            buf.put_u8(name.len() as u8);
+            buf.extend_from_slice(name.as_bytes());
+            buf.put_u8(0);
+        }
+        put_var_bytes(&self.column_types, buf);
+        put_var_bytes(&self.column_metas, buf);
+        buf.extend_from_slice(&self.null_bitmap);
+        buf.extend_from_slice(&self.optional_metadata);
+    }
+}
+
+bitflags::bitflags! {
+    /// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/classmysql_1_1binlog_1_1event_1_1Rows__event.html)
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct RowsEventFlags: u16 {
+        /// last event of a statement
+        const STMT_END = 0x1;
+        const NO_FOREIGN_KEY_CHECKS = 0x2;
+        const RELAXED_UNIQUE_CHECKS = 0x4;
+        /// rows contain all columns, not only the ones needed to identify the row
+        const COMPLETE_ROWS = 0x8;
+    }
+}
+
+/// write, update and delete rows event, rows are kept undecoded since column types
+/// come from preceding [`TableMapEvent`]
+///
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/classmysql_1_1binlog_1_1event_1_1Rows__event.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowsEvent {
+    pub table_id: Int6,
+    pub flags: RowsEventFlags,
+    /// extra row info of v2 events, `None` for v1 events
+    pub extra_data: Option<Bytes>,
+    pub column_count: u64,
+    /// bitmap of columns present in before image of update and other images
+    pub columns_present: Bytes,
+    /// bitmap of columns present in after image, only for update events
+    pub columns_present_after: Option<Bytes>,
+    /// undecoded row images, see [`RowsEvent::rows`]
+    pub rows: Bytes,
+    /// partial update rows event, JSON columns of after images are partial updates
+    pub partial: bool,
+}
+
+impl RowsEvent {
+    pub fn decode_with(ty: EventType, input: &mut BytesMut) -> Result<Self, DecodeError> {
+        let table_id = Int6::decode(input)?;
+        let flags = RowsEventFlags::from_bits_retain(Int2::decode(input)?.int());
+        let extra_data = match ty {
+            EventType::WriteRows
+            | EventType::UpdateRows
+            | EventType::DeleteRows
+            | EventType::PartialUpdateRows => {
+                // length includes itself
+                let len = (Int2::decode(input)?.int() as usize)
+                    .checked_sub(2)
+                    .ok_or(DecodeError::InvalidData)?;
+                Some(get_bytes(input, len)?)
+            }
+            _ => None,
+        };
+        let column_count = VLenInt::decode(input)?.int();
+        let columns_present = get_bytes(input, bitmap_len(column_count))?;
+        let columns_present_after = match ty {
+            EventType::UpdateRowsV1 | EventType::UpdateRows | EventType::PartialUpdateRows => {
+                Some(get_bytes(input, bitmap_len(column_count))?)
+            }
+            _ => None,
+        };
+        let rows = input.split().freeze();
+        Ok(Self {
+            table_id,
+            flags,
+            extra_data,
+            column_count,
+            columns_present,
+            columns_present_after,
+            rows,
+            partial: ty == EventType::PartialUpdateRows,
+        })
+    }
+
+    /// lazily decoded rows with column definitions of `table`, which is the table map
+    /// event of the same table id
+    ///
+    /// partial update rows events are not supported, their after images can't be decoded
+    /// as full rows.
+    pub fn rows<'a>(&'a self, table: &TableMapEvent) -> Result<Rows<'a>, DecodeError> {
+        if self.partial {
+            return Err(DecodeError::InvalidData);
+        }
+        let columns = table.columns()?;
+        if table.table_id != self.table_id || columns.len() as u64 != self.column_count {
+            return Err(DecodeError::InvalidData);
+        }
+        Ok(Rows {
+            event: self,
+            columns,
+            selected: None,
+            offset: 0,
+        })
+    }
+}
+
+/// values of a row image by column index, `None` for columns not in the image or not
+/// selected, strings and bytes are borrowed from [`RowsEvent::rows`]
+pub type RowImage<'a> = Vec<Option<ValueRef<'a>>>;
+
+/// iterator over rows of a [`RowsEvent`], a row is decoded only when it's reached
+///
+/// every item is the row image of write and delete rows event, or before and after images
+/// of update rows event.
+#[derive(Debug, Clone)]
+pub struct Rows<'a> {
+    event: &'a RowsEvent,
+    columns: Vec<BinlogColumn>,
+    /// columns to decode, others are skipped
+    selected: Option<Vec<bool>>,
+    offset: usize,
+}
+
+impl<'a> Rows<'a> {
+    /// only decode columns at these indexes, index out of range is invalid
+    pub fn select(mut self, columns: &[usize]) -> Result<Self, DecodeError> {
+        let mut selected = vec![false; self.columns.len()];
+        for idx in columns {
+            *selected.get_mut(*idx).ok_or(DecodeError::InvalidData)? = true;
+        }
+        self.selected = Some(selected);
+        Ok(self)
+    }
+
+    fn read_image(&mut self, present: &[u8]) -> Result<RowImage<'a>, DecodeError> {
+        let event: &'a RowsEvent = self.event;
+        let data = &event.rows[self.offset..];
+        let is_set = |bitmap: &[u8], i: usize| bitmap[i / 8] >> (i % 8) & 1 == 1;
+        let present_count = (0..self.columns.len())
+            .filter(|i| is_set(present, *i))
+            .count();
+        let nulls = data
+            .get(..bitmap_len(present_count as u64))
+            .ok_or(DecodeError::NoEnoughData)?;
+        let mut pos = nulls.len();
+        let mut image = vec![None; self.columns.len()];
+        // null bitmap only has bits of present columns
+        let mut present_idx = 0;
+        for (i, column) in self.columns.iter().enumerate() {
+            if !is_set(present, i) {
+                continue;
+            }
+            let null = is_set(nulls, present_idx);
+            present_idx += 1;
+            let selected = self.selected.as_ref().is_none_or(|s| s[i]);
+            if null {
+                if selected {
+                    image[i] = Some(ValueRef::Owned(Value::Null));
+                }
+            } else if selected {
+                let (value, len) =
+                    ValueRef::from_binlog(column.ty, column.meta, column.unsigned, &data[pos..])?;
+                image[i] = Some(value);
+                pos += len;
+            } else {
+                pos += binlog_value_len(column.ty, column.meta, &data[pos..])?;
+            }
+        }
+        self.offset += pos;
+        Ok(image)
+    }
+
+    fn read_row(&mut self) -> Result<(RowImage<'a>, Option<RowImage<'a>>), DecodeError> {
+        let event = self.event;
+        let image = self.read_image(&event.columns_present)?;
+        let after = match &event.columns_present_after {
+            Some(after) => Some(self.read_image(after)?),
+            None => None,
+        };
+        Ok((image, after))
+    }
+}
+
+impl<'a> Iterator for Rows<'a> {
+    type Item = Result<(RowImage<'a>, Option<RowImage<'a>>), DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.event.rows.len() {
+            return None;
+        }
+        let row = self.read_row();
+        if row.is_err() {
+            // rest of rows can not be located
+            self.offset = self.event.rows.len();
+        }
+        Some(row)
+    }
+}
+
+impl Encode for RowsEvent {
+    fn encode(&self, buf: &mut BytesMut) {
+        self.table_id.encode(buf);
+        buf.put_u16_le(self.flags.bits());
+        if let Some(extra) = &self.extra_data {
+            buf.put_u16_le(extra.len() as u16 + 2);
+            buf.extend_from_slice(extra);
+        }
+        VLenInt::new(self.column_count).encode(buf);
+        buf.extend_from_slice(&self.columns_present);
+        if let Some(after) = &self.columns_present_after {
+            buf.extend_from_slice(after);
+        }
+        buf.extend_from_slice(&self.rows);
+    }
+}
+
+/// original statement of rows events, written when `binlog_rows_query_log_events` is on
+///
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/classmysql_1_1binlog_1_1event_1_1Rows__query__event.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowsQueryEvent {
+    pub query: Bytes,
+}
+
+impl Decode<BytesMut> for RowsQueryEvent {
+    fn decode(input: &mut BytesMut) -> Result<Self, DecodeError> {
+        // length is truncated to one byte so it's ignored
+        input.jump_to(1)?;
+        Ok(Self {
+            query: input.split().freeze(),
+        })
+    }
+}
+
+impl Encode for RowsQueryEvent {
+    fn encode(&self, buf: &mut BytesMut) {
+        buf.put_u8(self.query.len() as u8);
+        buf.extend_from_slice(&self.query);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binlog::{BinlogFileReader, EventPayload};
+
+    #[test]
+    fn test_rows() {
+        let data = include_bytes!("../../../../tests/events/31_update_rows_v2/log.bin");
+        let mut table = None;
+        let mut rows = None;
+        for event in BinlogFileReader::new(&data[..]).unwrap() {
+            match event.unwrap().1.payload {
+                EventPayload::TableMap(event) => table = Some(event),
+                EventPayload::Rows(event) => rows = Some(event),
+                _ => {}
+            }
+        }
+        let (table, rows) = (table.unwrap(), rows.unwrap());
+        let all: Vec<_> = rows.rows(&table).unwrap().map(|row| row.unwrap()).collect();
+        assert_eq!(all.len(), 1);
+        let (before, after) = &all[0];
+        assert_eq!(before[0], Some(ValueRef::Owned(Value::Int(1))));
+        assert_eq!(before[1], Some(ValueRef::String("abc")));
+        assert_eq!(
+            before[8],
+            Some(ValueRef::Owned(Value::Decimal("3.0000".into())))
+        );
+        let after = after.as_ref().unwrap();
+        assert_eq!(after[5], Some(ValueRef::String("xd")));
+        assert_eq!(after[7], Some(ValueRef::Owned(Value::Double(4.0))));
+
+        let selected: Vec<_> = rows
+            .rows(&table)
+            .unwrap()
+            .select(&[0, 6])
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect();
+        let (before, after) = &selected[0];
+        assert_eq!(before[0], Some(ValueRef::Owned(Value::Int(1))));
+        assert_eq!(before[1], None);
+        assert_eq!(
+            after.as_ref().unwrap()[6],
+            Some(ValueRef::Owned(Value::Float(4.0)))
+        );
+        let columns = table.columns().unwrap().len();
+        assert!(matches!(
+            rows.rows(&table).unwrap().select(&[0, columns]),
+            Err(DecodeError::InvalidData)
+        ));
+
+        let partial = RowsEvent {
+            partial: true,
+            ..rows.clone()
+        };
+        assert!(matches!(
+            partial.rows(&table),
+            Err(DecodeError::InvalidData)
+        ));
+    }
+}
@@ -0,0 +1,482 @@
+use std::borrow::Cow;
+
+use bytes::{BufMut, Bytes, BytesMut};
+use parse_tool::InputBuf;
+
+use crate::codec::{
+    get_bytes, get_null_term_str, put_null_term_str, split_input, Decode, DecodeError, Encode,
+    Int1, Int2, Int3, Int4, Int8,
+};
+
+/// count of updated db names when there are too many to be listed
+const OVER_MAX_DBS_IN_EVENT_MTS: u8 = 254;
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/classmysql_1_1binlog_1_1event_1_1Query__event.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryEvent {
+    /// thread id
+    pub slave_proxy_id: Int4,
+    pub exec_time: Int4,
+    pub error_code: Int2,
+    pub status_vars: Vec<QueryStatusVar>,
+    pub schema: String,
+    pub query: Bytes,
+}
+
+impl QueryEvent {
+    pub fn sql(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.query)
+    }
+}
+
+impl Decode<BytesMut> for QueryEvent {
+    fn decode(input: &mut BytesMut) -> Result<Self, DecodeError> {
+        let slave_proxy_id = Int4::decode(input)?;
+        let exec_time = Int4::decode(input)?;
+        let schema_len = input.read_u8_le()? as usize;
+        let error_code = Int2::decode(input)?;
+        let status_vars_len = input.read_u16_le()? as usize;
+        let status_vars = decode_status_vars(input, status_vars_len)?;
+        let schema = decode_name(input, schema_len)?;
+        let query = input.split().freeze();
+        Ok(Self {
+            slave_proxy_id,
+            exec_time,
+            error_code,
+            status_vars,
+            schema,
+            query,
+        })
+    }
+}
+
+impl Encode for QueryEvent {
+    fn encode(&self, buf: &mut BytesMut) {
+        let mut status_vars = BytesMut::new();
+        encode_status_vars(&self.status_vars, &mut status_vars);
+        self.slave_proxy_id.encode(buf);
+        self.exec_time.encode(buf);
+        buf.put_u8(self.schema.len() as u8);
+        self.error_code.encode(buf);
+        buf.put_u16_le(status_vars.len() as u16);
+        buf.extend_from_slice(&status_vars);
+        put_null_term_str(&self.schema, buf);
+        buf.extend_from_slice(&self.query);
+    }
+}
+
+pub(super) fn decode_name<I: InputBuf>(input: &mut I, len: usize) -> Result<String, DecodeError> {
+    let schema = input.read_vec(len)?;
+    if input.read_u8_le()? != 0 {
+        return Err(DecodeError::MissingNull);
+    }
+    String::from_utf8(schema).map_err(|_| DecodeError::InvalidUtf8)
+}
+
+pub(super) fn decode_status_vars(
+    input: &mut BytesMut,
+    len: usize,
+) -> Result<Vec<QueryStatusVar>, DecodeError> {
+    let mut input = split_input(input, len)?;
+    let mut vars = vec![];
+    while !input.is_empty() {
+        vars.push(QueryStatusVar::decode(&mut input)?);
+    }
+    Ok(vars)
+}
+
+pub(super) fn encode_status_vars(vars: &[QueryStatusVar], buf: &mut BytesMut) {
+    for var in vars {
+        var.encode(buf);
+    }
+}
+
+fn get_short_str<I: InputBuf>(input: &mut I) -> Result<String, DecodeError> {
+    let len = input.read_u8_le()? as usize;
+    String::from_utf8(input.read_vec(len)?).map_err(|_| DecodeError::InvalidUtf8)
+}
+
+fn put_short_str(s: &str, buf: &mut BytesMut) {
+    buf.put_u8(s.len() as u8);
+    buf.extend_from_slice(s.as_bytes());
+}
+
+/// status variable of query event, stored as code and value pairs
+///
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_replication_binlog_event.html#sect_protocol_replication_event_query_03)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryStatusVar {
+    Flags2(QFlag2Code),
+    SqlMode(QSqlModeCode),
+    /// only written by 5.0.0 to 5.0.3
+    Catalog(String),
+    AutoIncrement(QAutoIncrement),
+    Charset {
+        client: Int2,
+        connection: Int2,
+        server: Int2,
+    },
+    TimeZone(String),
+    CatalogNz(String),
+    LcTimeNames(Int2),
+    CharsetDatabase(Int2),
+    TableMapForUpdate(Int8),
+    MasterDataWritten(Int4),
+    Invoker {
+        user: String,
+        host: String,
+    },
+    /// `None` when there are too many databases to be listed
+    UpdatedDbNames(Option<Vec<String>>),
+    Microseconds(Int3),
+    ExplicitDefaultsForTimestamp(bool),
+    DdlLoggedWithXid(Int8),
+    DefaultCollationForUtf8mb4(Int2),
+    SqlRequirePrimaryKey(Int1),
+    DefaultTableEncryption(Int1),
+    /// unknown code, length of value is unknown either so all remaining status vars
+    /// are kept as is
+    Unknown {
+        code: u8,
+        data: Vec<u8>,
+    },
+}
+
+impl Decode<BytesMut> for QueryStatusVar {
+    fn decode(input: &mut BytesMut) -> Result<Self, DecodeError> {
+        let code = input.read_u8_le()?;
+        let var = match code {
+            0 => Self::Flags2(QFlag2Code::from_bits_retain(Int4::decode(input)?.int())),
+            1 => Self::SqlMode(QSqlModeCode::from_bits_retain(input.read_u64_le()?)),
+            2 => {
+                let catalog = get_short_str(input)?;
+                input.jump_to(1)?;
+                Self::Catalog(catalog)
+            }
+            3 => Self::AutoIncrement(QAutoIncrement {
+                increment: Int2::decode(input)?,
+                offset: Int2::decode(input)?,
+            }),
+            4 => Self::Charset {
+                client: Int2::decode(input)?,
+                connection: Int2::decode(input)?,
+                server: Int2::decode(input)?,
+            },
+            5 => Self::TimeZone(get_short_str(input)?),
+            6 => Self::CatalogNz(get_short_str(input)?),
+            7 => Self::LcTimeNames(Int2::decode(input)?),
+            8 => Self::CharsetDatabase(Int2::decode(input)?),
+            9 => Self::TableMapForUpdate(Int8::decode(input)?),
+            10 => Self::MasterDataWritten(Int4::decode(input)?),
+            11 => Self::Invoker {
+                user: get_short_str(input)?,
+                host: get_short_str(input)?,
+            },
+            12 => {
+                let count = input.read_u8_le()?;
+                if count == OVER_MAX_DBS_IN_EVENT_MTS {
+                    Self::UpdatedDbNames(None)
+                } else {
+                    let names = (0..count)
+                        .map(|_| get_null_term_str(input))
+                        .collect::<Result<_, _>>()?;
+                    Self::UpdatedDbNames(Some(names))
+                }
+            }
+            13 => Self::Microseconds(Int3::decode(input)?),
+            16 => Self::ExplicitDefaultsForTimestamp(input.read_u8_le()? != 0),
+            17 => Self::DdlLoggedWithXid(Int8::decode(input)?),
+            18 => Self::DefaultCollationForUtf8mb4(Int2::decode(input)?),
+            19 => Self::SqlRequirePrimaryKey(Int1::decode(input)?),
+            20 => Self::DefaultTableEncryption(Int1::decode(input)?),
+            code => Self::Unknown {
+                code,
+                data: input.read_to_end(),
+            },
+        };
+        Ok(var)
+    }
+}
+
+impl Encode for QueryStatusVar {
+    fn encode(&self, buf: &mut BytesMut) {
+        match self {
+            Self::Flags2(flags) => {
+                buf.put_u8(0);
+                buf.put_u32_le(flags.bits());
+            }
+            Self::SqlMode(mode) => {
+                buf.put_u8(1);
+                buf.put_u64_le(mode.bits());
+            }
+            Self::Catalog(catalog) => {
+                buf.put_u8(2);
+                put_short_str(catalog, buf);
+                buf.put_u8(0);
+            }
+            Self::AutoIncrement(QAutoIncrement { increment, offset }) => {
+                buf.put_u8(3);
+                increment.encode(buf);
+                offset.encode(buf);
+            }
+            Self::Charset {
+                client,
+                connection,
+                server,
+            } => {
+                buf.put_u8(4);
+                client.encode(buf);
+                connection.encode(buf);
+                server.encode(buf);
+            }
+            Self::TimeZone(tz) => {
+                buf.put_u8(5);
+                put_short_str(tz, buf);
+            }
+            Self::CatalogNz(catalog) => {
+                buf.put_u8(6);
+                put_short_str(catalog, buf);
+            }
+            Self::LcTimeNames(v) => {
+                buf.put_u8(7);
+                v.encode(buf);
+            }
+            Self::CharsetDatabase(v) => {
+                buf.put_u8(8);
+                v.encode(buf);
+            }
+            Self::TableMapForUpdate(v) => {
+                buf.put_u8(9);
+                v.encode(buf);
+            }
+            Self::MasterDataWritten(v) => {
+                buf.put_u8(10);
+                v.encode(buf);
+            }
+            Self::Invoker { user, host } => {
+                buf.put_u8(11);
+                put_short_str(user, buf);
+                put_short_str(host, buf);
+            }
+            Self::UpdatedDbNames(names) => {
+                buf.put_u8(12);
+                match names {
+                    Some(names) => {
+                        buf.put_u8(names.len() as u8);
+                        for name in names {
+                            put_null_term_str(name, buf);
+                        }
+                    }
+                    None => buf.put_u8(OVER_MAX_DBS_IN_EVENT_MTS),
+                }
+            }
+            Self::Microseconds(v) => {
+                buf.put_u8(13);
+                v.encode(buf);
+            }
+            Self::ExplicitDefaultsForTimestamp(v) => {
+                buf.put_u8(16);
+                buf.put_u8(*v as u8);
+            }
+            Self::DdlLoggedWithXid(v) => {
+                buf.put_u8(17);
+                v.encode(buf);
+            }
+            Self::DefaultCollationForUtf8mb4(v) => {
+                buf.put_u8(18);
+                v.encode(buf);
+            }
+            Self::SqlRequirePrimaryKey(v) => {
+                buf.put_u8(19);
+                v.encode(buf);
+            }
+            Self::DefaultTableEncryption(v) => {
+                buf.put_u8(20);
+                v.encode(buf);
+            }
+            Self::Unknown { code, data } => {
+                buf.put_u8(*code);
+                buf.extend_from_slice(data);
+            }
+        }
+    }
+}
+
+bitflags::bitflags! {
+    /// bit mask of flags that are usually set with the SET command
+    ///
+    /// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_replication_binlog_event.html#sect_protocol_replication_event_query_00)
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct QFlag2Code : u32 {
+        const OPTION_AUTO_IS_NULL =  0x00004000;
+        const OPTION_NOT_AUTOCOMMIT =  0x00080000;
+        const OPTION_NO_FOREIGN_KEY_CHECKS =  0x04000000;
+        const OPTION_RELAXED_UNIQUE_CHECKS =  0x08000000;
+    }
+
+    /// bit mask of flags that are usually set with SET sql_mode
+    ///
+    /// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_replication_binlog_event.html#sect_protocol_replication_event_query_00)
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct QSqlModeCode : u64 {
+        const MODE_REAL_AS_FLOAT = 0x00000001;
+        const MODE_PIPES_AS_CONCAT = 0x00000002;
+        const MODE_ANSI_QUOTES = 0x00000004;
+        const MODE_IGNORE_SPACE = 0x00000008;
+        const MODE_NOT_USED = 0x00000010;
+        const MODE_ONLY_FULL_GROUP_BY = 0x00000020;
+        const MODE_NO_UNSIGNED_SUBTRACTION = 0x00000040;
+        const MODE_NO_DIR_IN_CREATE = 0x00000080;
+        const MODE_POSTGRESQL = 0x00000100;
+        const MODE_ORACLE = 0x00000200;
+        const MODE_MSSQL = 0x00000400;
+        const MODE_DB2 = 0x00000800;
+        const MODE_MAXDB = 0x00001000;
+        const MODE_NO_KEY_OPTIONS = 0x00002000;
+        const MODE_NO_TABLE_OPTIONS = 0x00004000;
+        const MODE_NO_FIELD_OPTIONS = 0x00008000;
+        const MODE_MYSQL323 = 0x00010000;
+        const MODE_MYSQL40 = 0x00020000;
+        const MODE_ANSI = 0x00040000;
+        const MODE_NO_AUTO_VALUE_ON_ZERO = 0x00080000;
+        const MODE_NO_BACKSLASH_ESCAPES = 0x00100000;
+        const MODE_STRICT_TRANS_TABLES = 0x00200000;
+        const MODE_STRICT_ALL_TABLES = 0x00400000;
+        const MODE_NO_ZERO_IN_DATE = 0x00800000;
+        const MODE_NO_ZERO_DATE = 0x01000000;
+        const MODE_INVALID_DATES = 0x02000000;
+        const MODE_ERROR_FOR_DIVISION_BY_ZERO = 0x04000000;
+        const MODE_TRADITIONAL = 0x08000000;
+        const MODE_NO_AUTO_CREATE_USER = 0x10000000;
+        const MODE_HIGH_NOT_PRECEDENCE = 0x20000000;
+        const MODE_NO_ENGINE_SUBSTITUTION = 0x40000000;
+        const MODE_PAD_CHAR_TO_FULL_LENGTH = 0x80000000;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QAutoIncrement {
+    pub increment: Int2,
+    pub offset: Int2,
+}
+
+/// [source](https://dev.mysql.com/doc/dev/mysql-server/latest/classbinary__log_1_1Intvar__event.html#details)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntVarEvent {
+    pub ty: u8,
+    pub value: u64,
+}
+
+impl<I: InputBuf> Decode<I> for IntVarEvent {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        let ty = input.read_u8_le()?;
+        let value = input.read_u64_le()?;
+        Ok(Self { ty, value })
+    }
+}
+
+impl Encode for IntVarEvent {
+    fn encode(&self, buf: &mut BytesMut) {
+        buf.put_u8(self.ty);
+        buf.put_u64_le(self.value);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum IntVarEventType {
+    InvalidIntEvent = 0,
+    LastInsertIdEvent = 1,
+    InsertIdEvent = 2,
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/classmysql_1_1binlog_1_1event_1_1Rand__event.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RandEvent {
+    pub seed1: Int8,
+    pub seed2: Int8,
+}
+
+impl<I: InputBuf> Decode<I> for RandEvent {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        Ok(Self {
+            seed1: Int8::decode(input)?,
+            seed2: Int8::decode(input)?,
+        })
+    }
+}
+
+impl Encode for RandEvent {
+    fn encode(&self, buf: &mut BytesMut) {
+        self.seed1.encode(buf);
+        self.seed2.encode(buf);
+    }
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/classmysql_1_1binlog_1_1event_1_1User__var__event.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserVarEvent {
+    pub name: String,
+    /// `None` if value is NULL
+    pub value: Option<UserVarValue>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserVarValue {
+    /// item result type, 0 is string, 1 is real, 2 is int and 4 is decimal
+    pub ty: Int1,
+    pub charset: Int4,
+    pub value: Bytes,
+    /// 1 means unsigned, written since 5.6
+    pub flags: Option<Int1>,
+}
+
+impl Decode<BytesMut> for UserVarEvent {
+    fn decode(input: &mut BytesMut) -> Result<Self, DecodeError> {
+        let name_len = Int4::decode(input)?.int() as usize;
+        let name =
+            String::from_utf8(input.read_vec(name_len)?).map_err(|_| DecodeError::InvalidUtf8)?;
+        if input.read_u8_le()? != 0 {
+            return Ok(Self { name, value: None });
+        }
+        let ty = Int1::decode(input)?;
+        let charset = Int4::decode(input)?;
+        let value_len = Int4::decode(input)?.int() as usize;
+        let value = get_bytes(input, value_len)?;
+        let flags = if input.left() > 0 {
+            Some(Int1::decode(input)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            name,
+            value: Some(UserVarValue {
+                ty,
+                charset,
+                value,
+                flags,
+            }),
+        })
+    }
+}
+
+impl Encode for UserVarEvent {
+    fn encode(&self, buf: &mut BytesMut) {
+        Int4::from(self.name.len() as u32).encode(buf);
+        buf.extend_from_slice(self.name.as_bytes());
+        let Some(value) = &self.value else {
+            buf.put_u8(1);
+            return;
+        };
+        buf.put_u8(0);
+        value.ty.encode(buf);
+        value.charset.encode(buf);
+        Int4::from(value.value.len() as u32).encode(buf);
+        buf.extend_from_slice(&value.value);
+        if let Some(flags) = value.flags {
+            flags.encode(buf);
+        }
+    }
+}
@@ -0,0 +1,401 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bytes::BytesMut;
+
+use crate::{
+    binlog::{
+        next_event, Event, EventHeader, EventHeaderFlag, EventPayload, EventType,
+        FormatDescriptionEvent, GtidSet, RotateEvent, StopEvent, TransactionTracker, BINLOG_MAGIC,
+        CHECKSUM_LEN, EVENT_HEADER_LEN,
+    },
+    codec::{Decode, DecodeError, Int1, Int4, Int8},
+};
+
+/// options of [`BinlogWriter`]
+#[derive(Debug, Clone)]
+pub struct BinlogWriterOpts {
+    pub dir: PathBuf,
+    /// files are named `{base_name}.000001` and so on, index file is `{base_name}.index`
+    pub base_name: String,
+    pub server_id: u32,
+    pub server_version: String,
+    pub checksum: bool,
+    /// rotate to next file once file size reaches it, only at transaction boundaries,
+    /// at most 4 GiB since positions in events are 32 bits
+    pub max_size: u64,
+    /// GTIDs executed before first file, GTIDs of existing files are added when opening
+    pub previous_gtids: GtidSet,
+}
+
+impl BinlogWriterOpts {
+    pub fn new(dir: impl Into<PathBuf>, base_name: impl Into<String>) -> Self {
+        Self {
+            dir: dir.into(),
+            base_name: base_name.into(),
+            server_id: 1,
+            server_version: "8.0.34-boxercrab".into(),
+            checksum: true,
+            max_size: 1 << 30,
+            previous_gtids: GtidSet::new(),
+        }
+    }
+}
+
+/// writes events into binlog files like a source server, a new file is started every time
+/// writer is opened
+///
+/// every file starts with format description and previous GTIDs events and ends with a
+/// rotate event pointing to next file, file names are appended to index file.
+#[derive(Debug)]
+pub struct BinlogWriter {
+    opts: BinlogWriterOpts,
+    seq: u32,
+    name: String,
+    file: BufWriter<File>,
+    pos: u64,
+    gtids: GtidSet,
+    tracker: TransactionTracker,
+}
+
+impl BinlogWriter {
+    pub fn open(opts: BinlogWriterOpts) -> io::Result<Self> {
+        if opts.max_size > u32::MAX as u64 {
+            return Err(invalid_input(format!(
+                "max size {} is larger than 4 GiB",
+                opts.max_size
+            )));
+        }
+        fs::create_dir_all(&opts.dir)?;
+        let files = index_files(&opts)?;
+        let mut gtids = opts.previous_gtids.clone();
+        let mut seq = 0;
+        if let Some(last) = files.last() {
+            seq = last
+                .rsplit_once('.')
+                .and_then(|(_, ext)| ext.parse().ok())
+                .ok_or_else(|| invalid_input(format!("invalid binlog file name {last}")))?;
+            let data = fs::read(opts.dir.join(last))?;
+            gtids.union(
+                &file_gtids(&data).map_err(|e| {
+                    io::Error::new(io::ErrorKind::InvalidData, format!("{last}: {e}"))
+                })?,
+            );
+        }
+        let (name, file) = create_file(&opts, seq + 1)?;
+        let mut writer = Self {
+            opts,
+            seq: seq + 1,
+            name,
+            file,
+            pos: 0,
+            gtids,
+            tracker: TransactionTracker::new(),
+        };
+        writer.write_header()?;
+        Ok(writer)
+    }
+
+    pub fn opts(&self) -> &BinlogWriterOpts {
+        &self.opts
+    }
+
+    /// name of file being written
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// end of last event in current file
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// GTIDs written so far, including previous GTIDs
+    pub fn gtids(&self) -> &GtidSet {
+        &self.gtids
+    }
+
+    /// append event at the end of current file, `event_size` and `log_pos` are set by
+    /// writer and position after the event is returned
+    ///
+    /// file is rotated after the event when it reaches `max_size` and event is not in
+    /// the middle of a transaction.
+    pub fn append(&mut self, event: &Event<EventPayload>) -> io::Result<u64> {
+        match &event.payload {
+            EventPayload::FormatDescription(_)
+            | EventPayload::PreviousGtids(_)
+            | EventPayload::Rotate(_) => {
+                return Err(invalid_input("event is written by writer itself".into()));
+            }
+            EventPayload::Gtid(gtid) if event.header.event_type.int() == EventType::Gtid as u8 => {
+                self.gtids
+                    .add(gtid.sid, gtid.gno)
+                    .map_err(|_| invalid_input(format!("invalid GTID number {}", gtid.gno)))?;
+            }
+            _ => {}
+        }
+        self.tracker.update(&event.payload);
+        self.write_event(event)?;
+        if !self.tracker.in_transaction() && self.pos >= self.opts.max_size {
+            self.rotate()?;
+        }
+        Ok(self.pos)
+    }
+
+    /// end current file with a rotate event and start next file
+    pub fn rotate(&mut self) -> io::Result<()> {
+        let (name, file) = create_file(&self.opts, self.seq + 1)?;
+        let rotate = EventPayload::Rotate(RotateEvent {
+            pos: Int8::from(BINLOG_MAGIC.len() as u64),
+            log: name.clone(),
+        });
+        let rotate = self.new_event(EventType::Rotate, rotate);
+        self.write_event(&rotate)?;
+        self.sync()?;
+        self.seq += 1;
+        self.name = name;
+        self.file = file;
+        self.pos = 0;
+        self.tracker = TransactionTracker::new();
+        self.write_header()
+    }
+
+    /// write buffered events into file
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+
+    /// flush and fsync current file
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        self.file.get_ref().sync_data()
+    }
+
+    /// end current file with a stop event like a server being shut down
+    pub fn close(mut self) -> io::Result<()> {
+        let stop = self.new_event(EventType::Stop, EventPayload::Stop(StopEvent));
+        self.write_event(&stop)?;
+        self.sync()
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        self.file.write_all(&BINLOG_MAGIC)?;
+        self.pos = BINLOG_MAGIC.len() as u64;
+        let mut fde = FormatDescriptionEvent::new(&self.opts.server_version, self.opts.checksum);
+        fde.create_timestamp = Int4::from(now());
+        let fde = self.new_event(
+            EventType::FormatDescription,
+            EventPayload::FormatDescription(fde),
+        );
+        self.write_event(&fde)?;
+        let gtids = EventPayload::PreviousGtids(self.gtids.clone());
+        let gtids = self.new_event(EventType::PreviousGtids, gtids);
+        self.write_event(&gtids)?;
+        self.flush()?;
+        append_index(&self.opts, &self.name)
+    }
+
+    fn new_event(&self, ty: EventType, payload: EventPayload) -> Event<EventPayload> {
+        Event {
+            header: EventHeader {
+                timestamp: Int4::from(now()),
+                event_type: Int1::from(ty as u8),
+                server_id: Int4::from(self.opts.server_id),
+                event_size: Int4::from(0),
+                log_pos: Int4::from(0),
+                flags: EventHeaderFlag::empty(),
+            },
+            payload,
+        }
+    }
+
+    fn write_event(&mut self, event: &Event<EventPayload>) -> io::Result<()> {
+        let checksum = self.opts.checksum;
+        let mut buf = BytesMut::new();
+        event.encode_with(checksum, &mut buf);
+        // log_pos is end of event, files can't grow over 4 GiB
+        let end = u32::try_from(self.pos + buf.len() as u64)
+            .map_err(|_| invalid_input(format!("binlog file {} exceeds 4 GiB", self.name)))?;
+        buf[13..17].copy_from_slice(&end.to_le_bytes());
+        if checksum {
+            let len = buf.len() - CHECKSUM_LEN;
+            let crc = crc32fast::hash(&buf[..len]);
+            buf[len..].copy_from_slice(&crc.to_le_bytes());
+        }
+        self.file.write_all(&buf)?;
+        self.pos += buf.len() as u64;
+        Ok(())
+    }
+}
+
+fn now() -> u32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as u32
+}
+
+fn invalid_input(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+fn index_path(opts: &BinlogWriterOpts) -> PathBuf {
+    opts.dir.join(format!("{}.index", opts.base_name))
+}
+
+/// file names listed in index file
+fn index_files(opts: &BinlogWriterOpts) -> io::Result<Vec<String>> {
+    match read_index(&index_path(opts)) {
+        Ok(entries) => Ok(entries
+            .iter()
+            .map(|entry| index_file_name(entry).to_string())
+            .collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(e),
+    }
+}
+
+/// lines of index file, e.g. `./mysql-bin.000001`
+pub(crate) fn read_index(path: &Path) -> io::Result<Vec<String>> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// file name of index entry, which may be written by server on another platform
+pub(crate) fn index_file_name(entry: &str) -> &str {
+    entry.rsplit(['/', '\\']).next().unwrap_or(entry)
+}
+
+fn append_index(opts: &BinlogWriterOpts, name: &str) -> io::Result<()> {
+    let mut index = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(index_path(opts))?;
+    writeln!(index, "./{name}")?;
+    index.sync_data()
+}
+
+fn create_file(opts: &BinlogWriterOpts, seq: u32) -> io::Result<(String, BufWriter<File>)> {
+    let name = format!("{}.{seq:06}", opts.base_name);
+    let file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(opts.dir.join(&name))?;
+    Ok((name, BufWriter::new(file)))
+}
+
+/// previous GTIDs and GTIDs of GTID events of a binlog file
+fn file_gtids(data: &[u8]) -> Result<GtidSet, DecodeError> {
+    let mut gtids = GtidSet::new();
+    let mut offset = BINLOG_MAGIC.len();
+    while let Some(event) = next_event(data, offset) {
+        offset += event.len();
+        let mut body = BytesMut::from(&event[EVENT_HEADER_LEN..]);
+        if event[4] == EventType::PreviousGtids as u8 {
+            gtids.union(&GtidSet::decode(&mut body)?);
+        } else if event[4] == EventType::Gtid as u8 {
+            // commit flag(1) + sid(16) + gno(8)
+            let sid = body.get(1..17).ok_or(DecodeError::InvalidData)?;
+            let gno = body.get(17..25).ok_or(DecodeError::InvalidData)?;
+            let gno = u64::from_le_bytes(gno.try_into().unwrap());
+            gtids.add(sid.try_into().unwrap(), gno)?;
+        }
+    }
+    Ok(gtids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{binlog::BinlogFileReader, test_util::temp_dir};
+
+    #[test]
+    fn test_binlog_writer() {
+        let dir = temp_dir("writer");
+        let data = include_bytes!("../../../../tests/events/02_query/log.bin");
+        let events: Vec<_> = BinlogFileReader::new(&data[..])
+            .unwrap()
+            .map(|event| event.unwrap().1)
+            .collect();
+
+        let mut opts = BinlogWriterOpts::new(&dir, "mysql-bin");
+        opts.max_size = 1;
+        let mut writer = BinlogWriter::open(opts.clone()).unwrap();
+        for event in &events[2..6] {
+            writer.append(event).unwrap();
+        }
+        writer.close().unwrap();
+        // a new file is started by reopening
+        let writer = BinlogWriter::open(opts.clone()).unwrap();
+        assert_eq!(writer.name(), "mysql-bin.000004");
+        assert_eq!(writer.gtids().to_string(), writer_gtids(&events));
+        drop(writer);
+
+        let index = fs::read_to_string(dir.join("mysql-bin.index")).unwrap();
+        assert_eq!(
+            index,
+            "./mysql-bin.000001\n./mysql-bin.000002\n./mysql-bin.000003\n./mysql-bin.000004\n"
+        );
+        let path = dir.join("mysql-bin.000001");
+        let mut pos = 4;
+        let mut types = vec![];
+        for event in BinlogFileReader::open(&path).unwrap() {
+            let event = event.unwrap().1;
+            pos += event.header.event_size.int();
+            assert_eq!(event.header.log_pos.int(), pos);
+            if let EventPayload::Rotate(rotate) = &event.payload {
+                assert_eq!(rotate.log, "mysql-bin.000002");
+            }
+            types.push(event.header.event_type.int());
+        }
+        assert_eq!(types, [15, 35, 33, 2, 4]);
+        assert_eq!(pos as u64, fs::metadata(path).unwrap().len());
+
+        // GTIDs can not be restored without last indexed file
+        fs::remove_file(dir.join("mysql-bin.000004")).unwrap();
+        let err = BinlogWriter::open(opts).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_statement_transaction() {
+        let dir = temp_dir("writer-stmt");
+        let mut opts = BinlogWriterOpts::new(&dir, "mysql-bin");
+        opts.max_size = u32::MAX as u64 + 1;
+        assert!(BinlogWriter::open(opts.clone()).is_err());
+
+        // rotated after DROP and CREATE, inserts stay with their transaction
+        opts.max_size = 1;
+        let mut writer = BinlogWriter::open(opts).unwrap();
+        for event in crate::binlog::tests::statement_events() {
+            writer.append(&event).unwrap();
+        }
+        assert_eq!(writer.name(), "mysql-bin.000004");
+        drop(writer);
+        let types: Vec<_> = BinlogFileReader::open(dir.join("mysql-bin.000003"))
+            .unwrap()
+            .map(|event| event.unwrap().1.header.event_type.int())
+            .collect();
+        assert_eq!(types, [15, 35, 33, 2, 5, 2, 5, 2, 5, 2, 16, 4]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    fn writer_gtids(events: &[Event<EventPayload>]) -> String {
+        let mut gtids = GtidSet::new();
+        for event in events {
+            if let EventPayload::Gtid(gtid) = &event.payload {
+                gtids.add(gtid.sid, gtid.gno).unwrap();
+            }
+        }
+        gtids.to_string()
+    }
+}
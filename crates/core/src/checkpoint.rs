@@ -0,0 +1,179 @@
+use std::{
+    cmp::Ordering,
+    fmt, fs,
+    io::{self, Write},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use thiserror::Error;
+
+use crate::binlog::GtidSet;
+
+#[derive(Debug, Error)]
+pub enum CheckpointError {
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid checkpoint: {0}")]
+    Invalid(String),
+}
+
+/// position in binlog files, e.g. `mysql-bin.000003:1234`
+///
+/// positions are ordered by sequence number of file first, so a position in rotated file
+/// is always after positions in previous files.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BinlogPosition {
+    pub file: String,
+    pub pos: u64,
+}
+
+impl BinlogPosition {
+    pub fn new(file: impl Into<String>, pos: u64) -> Self {
+        Self {
+            file: file.into(),
+            pos,
+        }
+    }
+
+    /// numeric extension of file name
+    pub fn seq(&self) -> Option<u64> {
+        self.file
+            .rsplit_once('.')
+            .and_then(|(_, ext)| ext.parse().ok())
+    }
+}
+
+impl Ord for BinlogPosition {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.seq()
+            .cmp(&other.seq())
+            .then_with(|| self.file.cmp(&other.file))
+            .then_with(|| self.pos.cmp(&other.pos))
+    }
+}
+
+impl PartialOrd for BinlogPosition {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BinlogPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.pos)
+    }
+}
+
+impl FromStr for BinlogPosition {
+    type Err = CheckpointError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || CheckpointError::Invalid(format!("invalid position {s:?}"));
+        let (file, pos) = s.trim().rsplit_once(':').ok_or_else(invalid)?;
+        let pos = pos.parse().map_err(|_| invalid())?;
+        if file.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self::new(file, pos))
+    }
+}
+
+/// where a consumer stopped, always at a transaction boundary
+///
+/// `gtids` contains all transactions before `position` when GTID is enabled, so either
+/// of them can be used to resume.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub position: BinlogPosition,
+    pub gtids: GtidSet,
+}
+
+/// durable storage of [`Checkpoint`]
+pub trait CheckpointStore {
+    /// last saved checkpoint, `None` if nothing is saved yet
+    fn load(&self) -> Result<Option<Checkpoint>, CheckpointError>;
+
+    fn save(&self, checkpoint: &Checkpoint) -> Result<(), CheckpointError>;
+}
+
+/// stores checkpoint in a text file, position at first line and GTID set at second line
+///
+/// checkpoint is written into a temporary file which is fsynced and renamed, so the file
+/// always contains either old or new checkpoint.
+#[derive(Debug, Clone)]
+pub struct FileCheckpointStore {
+    path: PathBuf,
+}
+
+impl FileCheckpointStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+}
+
+impl CheckpointStore for FileCheckpointStore {
+    fn load(&self) -> Result<Option<Checkpoint>, CheckpointError> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let mut lines = content.lines();
+        let position = lines.next().unwrap_or_default().parse()?;
+        let gtids = lines
+            .next()
+            .unwrap_or_default()
+            .parse()
+            .map_err(|_| CheckpointError::Invalid("invalid GTID set".into()))?;
+        Ok(Some(Checkpoint { position, gtids }))
+    }
+
+    fn save(&self, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        let mut file = fs::File::create(&tmp)?;
+        writeln!(file, "{}", checkpoint.position)?;
+        writeln!(file, "{}", checkpoint.gtids)?;
+        file.sync_all()?;
+        fs::rename(&tmp, &self.path)?;
+        // make rename durable, not supported on every platform
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            if let Ok(dir) = fs::File::open(dir) {
+                let _ = dir.sync_all();
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    #[test]
+    fn test_checkpoint_store() {
+        let a: BinlogPosition = "mysql-bin.000009:1000".parse().unwrap();
+        let b: BinlogPosition = "mysql-bin.000010:4".parse().unwrap();
+        assert!(a < b);
+        assert!(BinlogPosition::new("mysql-bin.000010", 120) > b);
+
+        let dir = temp_dir("ckpt");
+        let path = dir.join("checkpoint");
+        let store = FileCheckpointStore::new(&path);
+        assert!(store.load().unwrap().is_none());
+        let checkpoint = Checkpoint {
+            position: b,
+            gtids: "3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5".parse().unwrap(),
+        };
+        store.save(&checkpoint).unwrap();
+        assert_eq!(store.load().unwrap(), Some(checkpoint));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,188 @@
+use bytes::BytesMut;
+use thiserror::Error;
+
+use crate::{
+    checkpoint::BinlogPosition,
+    codec::{Decode, DecodeError, Encode},
+    connector::{
+        ClientMachine, ClientOpts, ComBinLogDump, ComBinLogDumpGtid, ComQuery, ErrPacket,
+        MachineError, MachineEvent, OkPacket, SslMode,
+    },
+};
+
+mod transport;
+pub use transport::*;
+mod query;
+pub use query::*;
+mod stmt;
+pub use stmt::*;
+mod command;
+mod stream;
+pub use stream::*;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("decode error: {0}")]
+    Decode(#[from] DecodeError),
+    #[error(transparent)]
+    Machine(#[from] MachineError),
+    #[error("server error {}: {}", .0.code, .0.error_msg)]
+    Server(ErrPacket),
+    #[error("statement expects {expect} params, got {got}")]
+    ParamsMismatch { expect: usize, got: usize },
+    #[error("LOCAL INFILE request is not supported")]
+    LocalInfileUnsupported,
+    #[error("ssl mode {0:?} is not supported")]
+    SslUnsupported(SslMode),
+    #[error("unexpected event {0:?}")]
+    Unexpected(MachineEvent),
+    #[error("binary log is not enabled on server")]
+    BinlogDisabled,
+    #[error("binlog position {0} does not fit in 32 bits of dump command")]
+    PositionTooLarge(BinlogPosition),
+}
+
+/// blocking client drives [`ClientMachine`] over any [`Transport`]
+#[derive(Debug)]
+pub struct Client<S> {
+    stream: S,
+    machine: ClientMachine,
+}
+
+impl Client<Stream> {
+    /// connect by unix socket or tcp according to [`ClientOpts`]
+    pub fn connect(opts: ClientOpts) -> Result<Self, ClientError> {
+        Self::connect_with(&DefaultConnector, opts)
+    }
+}
+
+impl<S: Transport> Client<S> {
+    pub fn connect_with<C: Connector<Stream = S>>(
+        connector: &C,
+        opts: ClientOpts,
+    ) -> Result<Self, ClientError> {
+        if matches!(
+            opts.ssl_mode,
+            SslMode::Required | SslMode::VerifyCa | SslMode::VerifyIdentity
+        ) {
+            return Err(ClientError::SslUnsupported(opts.ssl_mode));
+        }
+        let stream = connector.connect(&opts)?;
+        Self::handshake(stream, opts)
+    }
+
+    /// perform connection phase over connected stream
+    pub fn handshake(stream: S, opts: ClientOpts) -> Result<Self, ClientError> {
+        let mut machine = ClientMachine::new(opts);
+        machine.set_secure(stream.is_secure());
+        let mut client = Self { stream, machine };
+        loop {
+            if let MachineEvent::Connected(_) = client.next_event()? {
+                return Ok(client);
+            }
+        }
+    }
+
+    pub fn machine(&self) -> &ClientMachine {
+        &self.machine
+    }
+
+    pub fn stream(&self) -> &S {
+        &self.stream
+    }
+
+    /// wait for next event of state machine
+    pub fn next_event(&mut self) -> Result<MachineEvent, ClientError> {
+        let mut buf = [0; 8192];
+        loop {
+            let event = self.machine.poll_event()?;
+            self.flush()?;
+            if let Some(event) = event {
+                return Ok(event);
+            }
+            let n = self.stream.read(&mut buf)?;
+            if n == 0 {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            }
+            self.machine.feed(&buf[..n]);
+        }
+    }
+
+    pub fn command<P: Encode>(&mut self, cmd: &P) -> Result<(), ClientError> {
+        self.machine.command(cmd)?;
+        self.flush()
+    }
+
+    /// payload of next response packet
+    pub fn read_response(&mut self) -> Result<BytesMut, ClientError> {
+        match self.next_event()? {
+            MachineEvent::Response(buf) => Ok(buf),
+            event => Err(ClientError::Unexpected(event)),
+        }
+    }
+
+    /// decode next response packet
+    pub fn read_payload<P: Decode<BytesMut>>(&mut self) -> Result<P, ClientError> {
+        let mut buf = self.read_response()?;
+        Ok(P::decode(&mut buf)?)
+    }
+
+    /// read OK packet, ERR packet is returned as [`ClientError::Server`]
+    pub fn read_ok(&mut self) -> Result<OkPacket, ClientError> {
+        let mut buf = self.read_response()?;
+        match buf.first() {
+            Some(0xff) => Err(ClientError::Server(ErrPacket::decode(&mut buf)?)),
+            _ => Ok(OkPacket::decode(&mut buf)?),
+        }
+    }
+
+    /// execute statements by COM_QUERY, rows are streamed from returned result
+    pub fn query(&mut self, sql: &str) -> Result<QueryResult<'_, S>, ClientError> {
+        self.command(&ComQuery::from(sql))?;
+        QueryResult::new(self)
+    }
+
+    /// execute statements and discard all results
+    pub fn query_drop(&mut self, sql: &str) -> Result<(), ClientError> {
+        let mut result = self.query(sql)?;
+        while result.next_result_set()? {}
+        Ok(())
+    }
+
+    /// send COM_BINLOG_DUMP, events are received by [`Client::recv_binlog`]
+    pub fn binlog_dump(&mut self, dump: &ComBinLogDump) -> Result<(), ClientError> {
+        self.machine.binlog_dump(dump)?;
+        self.flush()
+    }
+
+    /// send COM_BINLOG_DUMP_GTID, events are received by [`Client::recv_binlog`]
+    pub fn binlog_dump_gtid(&mut self, dump: &ComBinLogDumpGtid) -> Result<(), ClientError> {
+        self.machine.binlog_dump_gtid(dump)?;
+        self.flush()
+    }
+
+    /// receive next raw binlog event, return `None` when server ends the stream
+    pub fn recv_binlog(&mut self) -> Result<Option<BytesMut>, ClientError> {
+        match self.next_event()? {
+            MachineEvent::Binlog(event) => Ok(Some(event)),
+            MachineEvent::BinlogEof => Ok(None),
+            event => Err(ClientError::Unexpected(event)),
+        }
+    }
+
+    /// send COM_QUIT and return underlying stream
+    pub fn quit(mut self) -> Result<S, ClientError> {
+        self.machine.quit()?;
+        self.flush()?;
+        Ok(self.stream)
+    }
+
+    fn flush(&mut self) -> Result<(), ClientError> {
+        if let Some(buf) = self.machine.take_output() {
+            self.stream.write_all(&buf)?;
+        }
+        Ok(())
+    }
+}
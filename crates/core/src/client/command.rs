@@ -0,0 +1,83 @@
+use crate::{
+    codec::{Decode, Int4},
+    connector::{
+        ComInitDb, ComPing, ComProcessKill, ComResetConnection, ComSetOption, ComStatistics,
+        EofPacket, ErrPacket, MachineEvent, OkPacket, SetOption, Statistics,
+    },
+};
+
+use super::{Client, ClientError, Transport};
+
+impl<S: Transport> Client<S> {
+    /// check whether server is alive by COM_PING
+    pub fn ping(&mut self) -> Result<(), ClientError> {
+        self.command(&ComPing)?;
+        self.read_ok()?;
+        Ok(())
+    }
+
+    /// change default schema by COM_INIT_DB
+    pub fn init_db(&mut self, schema: &str) -> Result<(), ClientError> {
+        self.command(&ComInitDb {
+            schema: schema.to_string(),
+        })?;
+        self.read_ok()?;
+        Ok(())
+    }
+
+    /// reset session state without re-authentication, prepared statements are closed
+    pub fn reset_connection(&mut self) -> Result<(), ClientError> {
+        self.command(&ComResetConnection)?;
+        self.read_ok()?;
+        Ok(())
+    }
+
+    /// re-authenticate as another user by COM_CHANGE_USER, session state is reset
+    pub fn change_user(
+        &mut self,
+        user: &str,
+        password: &str,
+        database: Option<&str>,
+    ) -> Result<OkPacket, ClientError> {
+        self.machine.change_user(user, password, database)?;
+        self.flush()?;
+        loop {
+            if let MachineEvent::Connected(ok) = self.next_event()? {
+                return Ok(ok);
+            }
+        }
+    }
+
+    /// human readable server statistics by COM_STATISTICS
+    pub fn statistics(&mut self) -> Result<String, ClientError> {
+        self.command(&ComStatistics)?;
+        let stat: Statistics = self.read_payload()?;
+        Ok(stat.info)
+    }
+
+    /// enable or disable multi statements by COM_SET_OPTION
+    pub fn set_option(&mut self, option: SetOption) -> Result<(), ClientError> {
+        self.command(&ComSetOption { option })?;
+        let mut buf = self.read_response()?;
+        match buf.first() {
+            Some(0xff) => Err(ClientError::Server(ErrPacket::decode(&mut buf)?)),
+            _ if EofPacket::is_eof(&buf) && buf.len() == 5 => {
+                EofPacket::decode(&mut buf)?;
+                Ok(())
+            }
+            _ => {
+                OkPacket::decode(&mut buf)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// ask server to terminate a connection by COM_PROCESS_KILL
+    pub fn kill(&mut self, connection_id: u32) -> Result<(), ClientError> {
+        self.command(&ComProcessKill {
+            connection_id: Int4::from(connection_id),
+        })?;
+        self.read_ok()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,430 @@
+use std::sync::Arc;
+
+use bytes::BytesMut;
+
+use crate::{
+    codec::{Decode, DecodeError, VLenInt},
+    connector::{
+        decode_binary_row, Capabilities, ColDef, EofPacket, ErrPacket, OkPacket, ServerStatus,
+        TextResult, MAX_PAYLOAD_LEN,
+    },
+    value::Value,
+};
+
+use super::{Client, ClientError, Transport};
+
+/// one row of result set
+#[derive(Debug, Clone)]
+pub struct Row {
+    columns: Arc<[ColDef]>,
+    values: Vec<Value>,
+}
+
+impl Row {
+    pub fn new(columns: Arc<[ColDef]>, values: Vec<Value>) -> Self {
+        Self { columns, values }
+    }
+
+    pub fn columns(&self) -> &[ColDef] {
+        &self.columns
+    }
+
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    pub fn into_values(self) -> Vec<Value> {
+        self.values
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&Value> {
+        self.values.get(idx)
+    }
+
+    /// get value by column name or alias
+    pub fn get_by_name(&self, name: &str) -> Option<&Value> {
+        let idx = self.columns.iter().position(|col| col.name == name)?;
+        self.values.get(idx)
+    }
+}
+
+/// streaming result of COM_QUERY, COM_STMT_EXECUTE or COM_STMT_FETCH, may contain
+/// several result sets
+///
+/// rows are read from connection on demand, unread rows and result sets are
+/// discarded when dropped.
+#[derive(Debug)]
+pub struct QueryResult<'a, S: Transport> {
+    client: &'a mut Client<S>,
+    columns: Arc<[ColDef]>,
+    ok: Option<OkPacket>,
+    status: ServerStatus,
+    /// rows are in binary protocol
+    binary: bool,
+    rows_done: bool,
+    more: bool,
+}
+
+impl<'a, S: Transport> QueryResult<'a, S> {
+    /// result of COM_QUERY, rows are in text protocol
+    pub(crate) fn new(client: &'a mut Client<S>) -> Result<Self, ClientError> {
+        Self::with_header(client, false)
+    }
+
+    /// result of COM_STMT_EXECUTE, rows are in binary protocol
+    pub(crate) fn binary(client: &'a mut Client<S>) -> Result<Self, ClientError> {
+        Self::with_header(client, true)
+    }
+
+    /// result of COM_STMT_FETCH, only binary rows are sent without column definitions
+    pub(crate) fn fetch(client: &'a mut Client<S>, columns: Arc<[ColDef]>) -> Self {
+        Self {
+            client,
+            columns,
+            ok: None,
+            status: ServerStatus::empty(),
+            binary: true,
+            rows_done: false,
+            more: false,
+        }
+    }
+
+    fn with_header(client: &'a mut Client<S>, binary: bool) -> Result<Self, ClientError> {
+        let mut result = Self {
+            client,
+            columns: Arc::new([]),
+            ok: None,
+            status: ServerStatus::empty(),
+            binary,
+            rows_done: true,
+            more: false,
+        };
+        result.read_header()?;
+        Ok(result)
+    }
+
+    /// columns of current result set, empty if statement returns no rows
+    pub fn columns(&self) -> &[ColDef] {
+        &self.columns
+    }
+
+    /// OK packet of current result set, available after all rows are read
+    pub fn ok_packet(&self) -> Option<&OkPacket> {
+        self.ok.as_ref()
+    }
+
+    pub fn affected_rows(&self) -> u64 {
+        self.ok
+            .as_ref()
+            .map(|ok| ok.affected_rows.int())
+            .unwrap_or_default()
+    }
+
+    pub fn last_insert_id(&self) -> u64 {
+        self.ok
+            .as_ref()
+            .map(|ok| ok.last_insert_id.int())
+            .unwrap_or_default()
+    }
+
+    /// whether there are more result sets after current one
+    pub fn has_more(&self) -> bool {
+        self.more
+    }
+
+    /// server status of last OK or EOF packet
+    pub fn status(&self) -> ServerStatus {
+        self.status
+    }
+
+    /// whether a cursor is opened by COM_STMT_EXECUTE, rows should be read by
+    /// [`Client::fetch`]
+    pub fn cursor_exists(&self) -> bool {
+        self.status
+            .contains(ServerStatus::SERVER_STATUS_CURSOR_EXISTS)
+    }
+
+    /// whether all rows of cursor are sent
+    pub fn last_row_sent(&self) -> bool {
+        self.status
+            .contains(ServerStatus::SERVER_STATUS_LAST_ROW_SENT)
+    }
+
+    /// read next row of current result set
+    pub fn next_row(&mut self) -> Result<Option<Row>, ClientError> {
+        if self.rows_done {
+            return Ok(None);
+        }
+        let mut payload = self.client.read_response()?;
+        let deprecate_eof = self.caps().contains(Capabilities::CLIENT_DEPRECATE_EOF);
+        match payload.first() {
+            Some(0xff) => {
+                self.rows_done = true;
+                self.more = false;
+                Err(ClientError::Server(ErrPacket::decode(&mut payload)?))
+            }
+            Some(0xfe) if deprecate_eof && payload.len() < MAX_PAYLOAD_LEN => {
+                let ok = OkPacket::decode(&mut payload)?;
+                self.finish(ok.status_flags);
+                self.ok = Some(ok);
+                Ok(None)
+            }
+            _ if !deprecate_eof && EofPacket::is_eof(&payload) => {
+                let eof = EofPacket::decode(&mut payload)?;
+                self.finish(eof.status_flags);
+                Ok(None)
+            }
+            _ if self.binary => {
+                let values = decode_binary_row(&mut payload, &self.columns)?;
+                Ok(Some(Row::new(self.columns.clone(), values)))
+            }
+            _ => {
+                let row = TextResult::decode(&mut payload)?;
+                if row.columns.len() != self.columns.len() {
+                    return Err(DecodeError::InvalidData.into());
+                }
+                let values = self
+                    .columns
+                    .iter()
+                    .zip(row.columns)
+                    .map(|(col, data)| match data {
+                        None => Ok(Value::Null),
+                        Some(data) => Value::from_text(
+                            col.column_type()?,
+                            col.column_flags(),
+                            col.charset.int(),
+                            data,
+                        ),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Some(Row::new(self.columns.clone(), values)))
+            }
+        }
+    }
+
+    /// read all remaining rows of current result set
+    pub fn collect_rows(&mut self) -> Result<Vec<Row>, ClientError> {
+        let mut rows = vec![];
+        while let Some(row) = self.next_row()? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    /// discard remaining rows and move to next result set
+    ///
+    /// return `false` if there is no more result set
+    pub fn next_result_set(&mut self) -> Result<bool, ClientError> {
+        while self.next_row()?.is_some() {}
+        if !self.more {
+            return Ok(false);
+        }
+        self.read_header()?;
+        Ok(true)
+    }
+
+    fn caps(&self) -> Capabilities {
+        self.client.machine().caps()
+    }
+
+    fn finish(&mut self, status_flags: ServerStatus) {
+        self.status = status_flags;
+        self.rows_done = true;
+        self.more = status_flags.contains(ServerStatus::SERVER_MORE_RESULTS_EXISTS);
+    }
+
+    /// read first packet of a result set, and column definitions if any
+    fn read_header(&mut self) -> Result<(), ClientError> {
+        self.ok = None;
+        self.columns = Arc::new([]);
+        let mut payload = self.client.read_response()?;
+        match payload.first() {
+            Some(0x00) => {
+                let ok = OkPacket::decode(&mut payload)?;
+                self.finish(ok.status_flags);
+                self.ok = Some(ok);
+            }
+            Some(0xff) => {
+                self.more = false;
+                return Err(ClientError::Server(ErrPacket::decode(&mut payload)?));
+            }
+            Some(0xfb) => {
+                self.more = false;
+                return Err(ClientError::LocalInfileUnsupported);
+            }
+            _ => {
+                let count = VLenInt::decode(&mut payload)?.int() as usize;
+                let mut columns = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let mut payload = self.client.read_response()?;
+                    columns.push(ColDef::decode(&mut payload)?);
+                }
+                self.columns = columns.into();
+                self.rows_done = false;
+                if !self.caps().contains(Capabilities::CLIENT_DEPRECATE_EOF) {
+                    let mut payload: BytesMut = self.client.read_response()?;
+                    let eof = EofPacket::decode(&mut payload)?;
+                    // no rows follow when cursor opened
+                    if eof
+                        .status_flags
+                        .contains(ServerStatus::SERVER_STATUS_CURSOR_EXISTS)
+                    {
+                        self.finish(eof.status_flags);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S: Transport> Iterator for QueryResult<'_, S> {
+    type Item = Result<Row, ClientError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_row().transpose()
+    }
+}
+
+impl<S: Transport> Drop for QueryResult<'_, S> {
+    fn drop(&mut self) {
+        while let Ok(true) = self.next_result_set() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read, Write};
+
+    use super::*;
+    use crate::connector::ClientOpts;
+
+    pub(crate) struct MockStream {
+        input: Cursor<Vec<u8>>,
+        pub output: Vec<u8>,
+    }
+
+    impl Read for MockStream {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for MockStream {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Transport for MockStream {}
+
+    fn packet(seq_id: u8, payload: &[u8]) -> Vec<u8> {
+        let mut data = (payload.len() as u32).to_le_bytes()[..3].to_vec();
+        data.push(seq_id);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    fn col_def(name: &str, ty: u8, flags: u16, charset: u16) -> Vec<u8> {
+        let mut data = b"\x03def\x00\x00\x00".to_vec();
+        data.push(name.len() as u8);
+        data.extend_from_slice(name.as_bytes());
+        data.extend_from_slice(&[0, 0x0c]);
+        data.extend_from_slice(&charset.to_le_bytes());
+        data.extend_from_slice(&[0xff, 0, 0, 0, ty]);
+        data.extend_from_slice(&flags.to_le_bytes());
+        data.extend_from_slice(&[0, 0, 0]);
+        data
+    }
+
+    fn client(response: Vec<u8>) -> Client<MockStream> {
+        let mut input = include_bytes!("../../../../tests/data/handshake_v10.bin").to_vec();
+        input.extend(packet(2, &[0, 0, 0, 2, 0, 0, 0]));
+        input.extend(response);
+        let stream = MockStream {
+            input: Cursor::new(input),
+            output: vec![],
+        };
+        Client::handshake(stream, ClientOpts::default()).unwrap()
+    }
+
+    #[test]
+    fn test_multi_result() {
+        let mut response = vec![];
+        response.extend(packet(1, &[2]));
+        response.extend(packet(2, &col_def("id", 8, 32, 63)));
+        response.extend(packet(3, &col_def("name", 253, 0, 255)));
+        response.extend(packet(4, b"\x011\x03foo"));
+        response.extend(packet(5, b"\x012\xfb"));
+        // OK packet as EOF, SERVER_MORE_RESULTS_EXISTS set
+        response.extend(packet(6, &[0xfe, 0, 0, 0x0a, 0, 0, 0]));
+        response.extend(packet(7, &[0, 3, 0, 2, 0, 0, 0]));
+        let mut client = client(response);
+        let mut result = client
+            .query("select id, name from t; delete from t")
+            .unwrap();
+        assert_eq!(result.columns().len(), 2);
+        let row = result.next().unwrap().unwrap();
+        assert_eq!(row.get(0), Some(&Value::UInt(1)));
+        assert_eq!(row.get_by_name("name"), Some(&Value::String("foo".into())));
+        let row = result.next().unwrap().unwrap();
+        assert_eq!(row.get(1), Some(&Value::Null));
+        assert!(result.next().is_none());
+        assert!(result.has_more());
+        assert!(result.next_result_set().unwrap());
+        assert!(result.columns().is_empty());
+        assert_eq!(result.affected_rows(), 3);
+        assert!(!result.next_result_set().unwrap());
+    }
+
+    #[test]
+    fn test_drop_unread() {
+        let mut response = vec![];
+        response.extend(packet(1, &[1]));
+        response.extend(packet(2, &col_def("id", 3, 0, 63)));
+        response.extend(packet(3, b"\x011"));
+        response.extend(packet(4, b"\x012"));
+        response.extend(packet(5, &[0xfe, 0, 0, 2, 0, 0, 0]));
+        response.extend(packet(
+            1,
+            &[0xff, 0x7a, 0x04, b'#', b'4', b'2', b'0', b'0', b'0', b'e'],
+        ));
+        let mut client = client(response);
+        client.query("select 1 union select 2").unwrap();
+        match client.query_drop("select x") {
+            Err(ClientError::Server(err)) => assert_eq!(err.code.int(), 1146),
+            other => panic!("should be server error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_prepared() {
+        let mut response = vec![];
+        response.extend(packet(1, &[0, 1, 0, 0, 0, 1, 0, 1, 0, 0, 0, 0]));
+        response.extend(packet(2, &col_def("?", 253, 0, 63)));
+        response.extend(packet(3, &col_def("id", 8, 32, 63)));
+        response.extend(packet(1, &[1]));
+        response.extend(packet(2, &col_def("id", 8, 32, 63)));
+        response.extend(packet(3, &[0, 0, 7, 0, 0, 0, 0, 0, 0, 0]));
+        response.extend(packet(4, &[0, 0b100]));
+        response.extend(packet(5, &[0xfe, 0, 0, 2, 0, 0, 0]));
+        let mut client = client(response);
+        let stmt = client.prepare("select id from t where name = ?").unwrap();
+        assert_eq!(stmt.params().len(), 1);
+        assert!(matches!(
+            client.execute(&stmt, &[]),
+            Err(ClientError::ParamsMismatch { expect: 1, got: 0 })
+        ));
+        let rows = client
+            .execute(&stmt, &[Value::String("foo".into())])
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(rows[0].values(), &[Value::UInt(7)]);
+        assert_eq!(rows[1].values(), &[Value::Null]);
+    }
+}
@@ -0,0 +1,132 @@
+use std::sync::Arc;
+
+use crate::{
+    codec::{Decode, Int4},
+    connector::{
+        Capabilities, ColDef, ComStmtClose, ComStmtExecute, ComStmtFetch, ComStmtPrepare,
+        ComStmtReset, CursorType, EofPacket, ErrPacket, StmtPrepareOk,
+    },
+    value::Value,
+};
+
+use super::{Client, ClientError, QueryResult, Transport};
+
+/// server side prepared statement
+#[derive(Debug, Clone)]
+pub struct Statement {
+    id: u32,
+    params: Vec<ColDef>,
+    columns: Arc<[ColDef]>,
+}
+
+impl Statement {
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// definitions of parameters, most of fields are not filled by server
+    pub fn params(&self) -> &[ColDef] {
+        &self.params
+    }
+
+    pub fn columns(&self) -> &[ColDef] {
+        &self.columns
+    }
+}
+
+impl<S: Transport> Client<S> {
+    /// prepare statement by COM_STMT_PREPARE
+    pub fn prepare(&mut self, sql: &str) -> Result<Statement, ClientError> {
+        self.command(&ComStmtPrepare::from(sql))?;
+        let mut payload = self.read_response()?;
+        if payload.first() == Some(&0xff) {
+            return Err(ClientError::Server(ErrPacket::decode(&mut payload)?));
+        }
+        let ok = StmtPrepareOk::decode(&mut payload)?;
+        let params = self.read_col_defs(ok.num_params.int() as usize)?;
+        let columns = self.read_col_defs(ok.num_columns.int() as usize)?;
+        Ok(Statement {
+            id: ok.statement_id.int(),
+            params,
+            columns: columns.into(),
+        })
+    }
+
+    /// execute prepared statement without cursor
+    pub fn execute(
+        &mut self,
+        stmt: &Statement,
+        params: &[Value],
+    ) -> Result<QueryResult<'_, S>, ClientError> {
+        self.execute_with(stmt, params, CursorType::CURSOR_TYPE_NO_CURSOR)
+    }
+
+    /// execute prepared statement, if cursor is opened, result contains only column
+    /// definitions, rows should be read by [`Client::fetch`]
+    pub fn execute_with(
+        &mut self,
+        stmt: &Statement,
+        params: &[Value],
+        cursor: CursorType,
+    ) -> Result<QueryResult<'_, S>, ClientError> {
+        if params.len() != stmt.params.len() {
+            return Err(ClientError::ParamsMismatch {
+                expect: stmt.params.len(),
+                got: params.len(),
+            });
+        }
+        self.command(&ComStmtExecute {
+            statement_id: Int4::from(stmt.id),
+            flags: cursor,
+            params: params.to_vec(),
+        })?;
+        QueryResult::binary(self)
+    }
+
+    /// fetch at most `num_rows` rows from opened cursor by COM_STMT_FETCH
+    pub fn fetch(
+        &mut self,
+        stmt: &Statement,
+        num_rows: u32,
+    ) -> Result<QueryResult<'_, S>, ClientError> {
+        self.command(&ComStmtFetch {
+            statement_id: Int4::from(stmt.id),
+            num_rows: Int4::from(num_rows),
+        })?;
+        Ok(QueryResult::fetch(self, stmt.columns.clone()))
+    }
+
+    /// reset data of prepared statement and close cursor
+    pub fn reset_stmt(&mut self, stmt: &Statement) -> Result<(), ClientError> {
+        self.command(&ComStmtReset {
+            statement_id: Int4::from(stmt.id),
+        })?;
+        self.read_ok()?;
+        Ok(())
+    }
+
+    /// deallocate prepared statement, server sends no response
+    pub fn close_stmt(&mut self, stmt: Statement) -> Result<(), ClientError> {
+        self.command(&ComStmtClose {
+            statement_id: Int4::from(stmt.id),
+        })
+    }
+
+    fn read_col_defs(&mut self, count: usize) -> Result<Vec<ColDef>, ClientError> {
+        let mut defs = Vec::with_capacity(count);
+        for _ in 0..count {
+            let mut payload = self.read_response()?;
+            defs.push(ColDef::decode(&mut payload)?);
+        }
+        if count > 0
+            && !self
+                .machine()
+                .caps()
+                .contains(Capabilities::CLIENT_DEPRECATE_EOF)
+        {
+            let mut payload = self.read_response()?;
+            EofPacket::decode(&mut payload)?;
+        }
+        Ok(defs)
+    }
+}
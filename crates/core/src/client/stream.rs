@@ -0,0 +1,483 @@
+use crate::{
+    binlog::{
+        Event, EventFilter, EventFilterOpts, EventHeader, EventHeaderFlag, EventPayload, EventType,
+        GtidSet, Sid, TransactionTracker, BINLOG_MAGIC, EVENT_HEADER_LEN,
+    },
+    checkpoint::{BinlogPosition, Checkpoint},
+    codec::{Decode, Int2, Int4, Int8},
+    connector::{
+        ClientOpts, ComBinLogDump, ComBinLogDumpGtid, BINLOG_DUMP_NON_BLOCK, BINLOG_THROUGH_GTID,
+    },
+};
+
+use super::{Client, ClientError, Connector, Transport};
+
+/// options of [`Client::binlog_stream`]
+#[derive(Debug, Clone)]
+pub struct BinlogStreamOpts {
+    /// server id to register on source, must be unique among replicas
+    pub server_id: u32,
+    /// where to start when there is no checkpoint, current position of
+    /// `SHOW MASTER STATUS` by default
+    pub start: Option<BinlogPosition>,
+    /// resume by GTID set of checkpoint with COM_BINLOG_DUMP_GTID instead of position
+    pub gtid: bool,
+    /// stop at the end of last binlog file instead of waiting for new events
+    pub non_block: bool,
+    /// see [`BinlogSequenceOpts::filter`](crate::binlog::BinlogSequenceOpts::filter)
+    pub filter: Option<EventFilterOpts>,
+}
+
+impl BinlogStreamOpts {
+    pub fn new(server_id: u32) -> Self {
+        Self {
+            server_id,
+            start: None,
+            gtid: false,
+            non_block: false,
+            filter: None,
+        }
+    }
+}
+
+/// decoded binlog events with position tracking, created by [`Client::binlog_stream`]
+///
+/// [`BinlogStream::checkpoint`] only moves at transaction boundaries, consumer saves it
+/// after handling an event, resuming from it never starts in the middle of a transaction.
+#[derive(Debug)]
+pub struct BinlogStream<'a, S: Transport> {
+    client: &'a mut Client<S>,
+    checksum: bool,
+    position: BinlogPosition,
+    gtids: GtidSet,
+    pending_gtid: Option<(Sid, u64)>,
+    tracker: TransactionTracker,
+    checkpoint: Checkpoint,
+    filter: Option<EventFilter>,
+}
+
+impl<S: Transport> Client<S> {
+    /// start binlog dump from `checkpoint`, or start position of `opts` if there is none
+    pub fn binlog_stream(
+        &mut self,
+        opts: &BinlogStreamOpts,
+        checkpoint: Option<Checkpoint>,
+    ) -> Result<BinlogStream<'_, S>, ClientError> {
+        self.query_drop("SET @master_binlog_checksum = @@global.binlog_checksum")?;
+        let checksum = self
+            .query("SELECT @@global.binlog_checksum")?
+            .next()
+            .transpose()?
+            .and_then(|row| row.get(0).and_then(|v| v.as_str()).map(str::to_string))
+            .is_some_and(|v| v.eq_ignore_ascii_case("CRC32"));
+        let checkpoint = match (checkpoint, &opts.start) {
+            (Some(checkpoint), _) => checkpoint,
+            (None, Some(start)) => Checkpoint {
+                position: start.clone(),
+                gtids: GtidSet::new(),
+            },
+            (None, None) => self.master_status()?,
+        };
+        let flags = if opts.non_block {
+            BINLOG_DUMP_NON_BLOCK
+        } else {
+            0
+        };
+        if opts.gtid && !checkpoint.gtids.is_empty() {
+            self.binlog_dump_gtid(&ComBinLogDumpGtid {
+                flags: Int2::from(flags | BINLOG_THROUGH_GTID),
+                server_id: Int4::from(opts.server_id),
+                filename: String::new(),
+                pos: Int8::from(4),
+                gtid_set: checkpoint.gtids.clone(),
+            })?;
+        } else {
+            let pos = u32::try_from(checkpoint.position.pos)
+                .map_err(|_| ClientError::PositionTooLarge(checkpoint.position.clone()))?;
+            self.binlog_dump(&ComBinLogDump {
+                pos: Int4::from(pos),
+                flags: Int2::from(flags),
+                server_id: Int4::from(opts.server_id),
+                filename: checkpoint.position.file.clone(),
+            })?;
+        }
+        Ok(BinlogStream {
+            client: self,
+            checksum,
+            position: checkpoint.position.clone(),
+            gtids: checkpoint.gtids.clone(),
+            pending_gtid: None,
+            tracker: TransactionTracker::new(),
+            checkpoint,
+            filter: opts.filter.clone().map(EventFilter::new),
+        })
+    }
+
+    /// start binlog dump from the earliest transaction at or after unix `timestamp`, see
+    /// [`Client::binlog_position_at`]
+    pub fn binlog_stream_at<C: Connector<Stream = S>>(
+        &mut self,
+        connector: &C,
+        opts: &BinlogStreamOpts,
+        timestamp: u32,
+    ) -> Result<BinlogStream<'_, S>, ClientError> {
+        let start = self.binlog_position_at(connector, opts.server_id, timestamp)?;
+        let opts = BinlogStreamOpts {
+            start: Some(start),
+            ..opts.clone()
+        };
+        self.binlog_stream(&opts, None)
+    }
+
+    /// earliest transaction boundary at or after unix `timestamp`, end of last binlog file
+    /// if there is none
+    ///
+    /// files are listed by `SHOW BINARY LOGS`, file containing `timestamp` is found by
+    /// binary search on their first events, then scanned. every probe is a short
+    /// non-blocking dump on a new connection by `connector`, this client is kept intact.
+    pub fn binlog_position_at<C: Connector<Stream = S>>(
+        &mut self,
+        connector: &C,
+        server_id: u32,
+        timestamp: u32,
+    ) -> Result<BinlogPosition, ClientError> {
+        let mut files = vec![];
+        for row in self.query("SHOW BINARY LOGS")? {
+            let row = row?;
+            let name = row.get(0).and_then(|v| v.as_str()).unwrap_or_default();
+            let size = row.get(1).and_then(|v| v.as_u64()).unwrap_or_default();
+            files.push((name.to_string(), size));
+        }
+        let Some((last, size)) = files.last().cloned() else {
+            return Err(ClientError::BinlogDisabled);
+        };
+        let prober = Prober {
+            connector,
+            opts: self.machine.opts().clone(),
+            server_id,
+        };
+        // number of files whose first event is not after `timestamp`
+        let (mut low, mut high) = (0, files.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let first =
+                prober.probe(&files[mid].0, |_, event| Some(event.header.timestamp.int()))?;
+            if first.is_some_and(|first| first <= timestamp) {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        if let Some((file, _)) = low.checked_sub(1).map(|idx| &files[idx]) {
+            let found = prober.probe(file, |boundary, event| {
+                (event.header.timestamp.int() >= timestamp).then(|| boundary.clone())
+            })?;
+            if let Some(found) = found {
+                return Ok(found);
+            }
+        }
+        Ok(match files.get(low) {
+            Some((file, _)) => BinlogPosition::new(file, BINLOG_MAGIC.len() as u64),
+            None => BinlogPosition::new(last, size),
+        })
+    }
+
+    /// current position and executed GTIDs of source
+    fn master_status(&mut self) -> Result<Checkpoint, ClientError> {
+        let row = self
+            .query("SHOW MASTER STATUS")?
+            .next()
+            .transpose()?
+            .ok_or(ClientError::BinlogDisabled)?;
+        let file = row.get(0).and_then(|v| v.as_str()).unwrap_or_default();
+        let pos = row.get(1).and_then(|v| v.as_u64()).unwrap_or(4);
+        let gtids = row
+            .get(4)
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .parse()?;
+        Ok(Checkpoint {
+            position: BinlogPosition::new(file, pos),
+            gtids,
+        })
+    }
+}
+
+impl<S: Transport> BinlogStream<'_, S> {
+    /// position after last received event
+    pub fn position(&self) -> &BinlogPosition {
+        &self.position
+    }
+
+    /// position and GTIDs at the end of last complete transaction
+    pub fn checkpoint(&self) -> &Checkpoint {
+        &self.checkpoint
+    }
+
+    fn recv(&mut self) -> Result<Option<Event<EventPayload>>, ClientError> {
+        loop {
+            let Some(mut data) = self.client.recv_binlog()? else {
+                return Ok(None);
+            };
+            if self.filter.as_ref().is_some_and(|f| f.skip_raw(&data)) {
+                // skipped events never start or end a transaction, only position moves
+                let header = EventHeader::decode(&mut data.split_to(EVENT_HEADER_LEN))?;
+                self.move_past(&header);
+                self.update_checkpoint();
+                continue;
+            }
+            let event = Event::decode_with(&mut data, self.checksum)?;
+            self.track(&event)?;
+            if self.filter.as_mut().is_none_or(|f| f.accept(&event)) {
+                return Ok(Some(event));
+            }
+        }
+    }
+
+    fn track(&mut self, event: &Event<EventPayload>) -> Result<(), ClientError> {
+        let header = &event.header;
+        match &event.payload {
+            EventPayload::FormatDescription(fde) => {
+                self.checksum = fde.checksum_alg.is_some_and(|alg| alg.int() == 1);
+            }
+            EventPayload::Rotate(rotate) => {
+                self.position = BinlogPosition::new(&rotate.log, rotate.pos.int());
+            }
+            EventPayload::PreviousGtids(gtids) => self.gtids.union(gtids),
+            EventPayload::Gtid(gtid) if header.event_type.int() == EventType::Gtid as u8 => {
+                self.pending_gtid = Some((gtid.sid, gtid.gno));
+            }
+            _ => {}
+        }
+        if !matches!(event.payload, EventPayload::Rotate(_)) {
+            self.move_past(header);
+        }
+        if self.tracker.update(&event.payload) {
+            if let Some((sid, gno)) = self.pending_gtid.take() {
+                self.gtids.add(sid, gno)?;
+            }
+        }
+        self.update_checkpoint();
+        Ok(())
+    }
+
+    /// artificial events and heartbeats are not in binlog file
+    fn move_past(&mut self, header: &EventHeader) {
+        let artificial = header
+            .flags
+            .contains(EventHeaderFlag::LOG_EVENT_ARTIFICIAL_F)
+            || header.event_type.int() == EventType::Heartbeat as u8;
+        if !artificial && header.log_pos.int() != 0 {
+            self.position.pos = header.log_pos.int() as u64;
+        }
+    }
+
+    fn update_checkpoint(&mut self) {
+        if !self.tracker.in_transaction() {
+            self.checkpoint = Checkpoint {
+                position: self.position.clone(),
+                gtids: self.gtids.clone(),
+            };
+        }
+    }
+}
+
+/// dumps a single binlog file by new connections
+struct Prober<'a, C> {
+    connector: &'a C,
+    opts: ClientOpts,
+    server_id: u32,
+}
+
+impl<C: Connector> Prober<'_, C> {
+    /// read events of `file` until `f` returns a value, `f` is called with the last
+    /// transaction boundary and each event, artificial events are skipped
+    fn probe<T>(
+        &self,
+        file: &str,
+        mut f: impl FnMut(&BinlogPosition, &Event<EventPayload>) -> Option<T>,
+    ) -> Result<Option<T>, ClientError> {
+        let mut client = Client::connect_with(self.connector, self.opts.clone())?;
+        let opts = BinlogStreamOpts {
+            start: Some(BinlogPosition::new(file, BINLOG_MAGIC.len() as u64)),
+            non_block: true,
+            ..BinlogStreamOpts::new(self.server_id)
+        };
+        let mut stream = client.binlog_stream(&opts, None)?;
+        loop {
+            let boundary = stream.checkpoint().position.clone();
+            let Some(event) = stream.next().transpose()? else {
+                return Ok(None);
+            };
+            if boundary.file != file {
+                return Ok(None);
+            }
+            let header = &event.header;
+            if header.log_pos.int() == 0
+                || header
+                    .flags
+                    .contains(EventHeaderFlag::LOG_EVENT_ARTIFICIAL_F)
+            {
+                continue;
+            }
+            if let Some(found) = f(&boundary, &event) {
+                return Ok(Some(found));
+            }
+        }
+    }
+}
+
+impl<S: Transport> Iterator for BinlogStream<'_, S> {
+    type Item = Result<Event<EventPayload>, ClientError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        binlog::{BinlogWriter, BinlogWriterOpts},
+        server::{FakeSource, FakeSourceOpts},
+        test_util::temp_dir,
+    };
+
+    #[test]
+    fn test_binlog_stream_resume() {
+        let dir = temp_dir("stream");
+        let data = include_bytes!("../../../../tests/events/02_query/log.bin");
+        std::fs::write(dir.join("mysql_bin.000001"), data).unwrap();
+        let addr = FakeSource::new(FakeSourceOpts::new(&dir))
+            .spawn("127.0.0.1:0")
+            .unwrap();
+        let opts = FakeSource::client_opts(addr);
+        let mut stream_opts = BinlogStreamOpts::new(100);
+        stream_opts.non_block = true;
+        stream_opts.start = Some(BinlogPosition::new("mysql_bin.000001", 4));
+
+        // stop in the middle of second transaction
+        let mut client = Client::connect(opts.clone()).unwrap();
+        let mut stream = client.binlog_stream(&stream_opts, None).unwrap();
+        let mut gtids = 0;
+        for event in stream.by_ref() {
+            if let EventPayload::Gtid(_) = event.unwrap().payload {
+                gtids += 1;
+                if gtids == 2 {
+                    break;
+                }
+            }
+        }
+        let checkpoint = stream.checkpoint().clone();
+        assert!(checkpoint.position < *stream.position());
+        assert_eq!(checkpoint.gtids.to_string().matches(':').count(), 1);
+
+        let mut client = Client::connect(opts.clone()).unwrap();
+        let mut stream = client
+            .binlog_stream(&stream_opts, Some(checkpoint.clone()))
+            .unwrap();
+        let mut queries = vec![];
+        for event in stream.by_ref() {
+            if let EventPayload::Query(query) = event.unwrap().payload {
+                queries.push(query.sql().to_string());
+            }
+        }
+        assert_eq!(queries.len(), 1);
+        assert!(queries[0].to_lowercase().starts_with("create table"));
+        assert_eq!(
+            stream.checkpoint().position,
+            BinlogPosition::new("mysql_bin.000002", 4)
+        );
+
+        let mut client = Client::connect(opts).unwrap();
+        let checkpoint = Checkpoint {
+            position: BinlogPosition::new("mysql_bin.000002", u32::MAX as u64 + 1),
+            gtids: GtidSet::new(),
+        };
+        assert!(matches!(
+            client.binlog_stream(&stream_opts, Some(checkpoint)),
+            Err(ClientError::PositionTooLarge(_))
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_statement_checkpoint() {
+        let dir = temp_dir("stream-stmt");
+        let mut writer = BinlogWriter::open(BinlogWriterOpts::new(&dir, "mysql-bin")).unwrap();
+        for event in crate::binlog::tests::statement_events() {
+            writer.append(&event).unwrap();
+        }
+        writer.close().unwrap();
+        let addr = FakeSource::new(FakeSourceOpts::new(&dir))
+            .spawn("127.0.0.1:0")
+            .unwrap();
+        let opts = FakeSource::client_opts(addr);
+        let mut stream_opts = BinlogStreamOpts::new(100);
+        stream_opts.non_block = true;
+        stream_opts.start = Some(BinlogPosition::new("mysql-bin.000001", 4));
+        let mut client = Client::connect(opts).unwrap();
+        let mut stream = client.binlog_stream(&stream_opts, None).unwrap();
+        let mut boundary = None;
+        while let Some(event) = stream.next().transpose().unwrap() {
+            match event.payload {
+                EventPayload::Query(query) if query.sql() == "BEGIN" => {
+                    boundary = Some(stream.checkpoint().clone());
+                }
+                EventPayload::Xid(_) => break,
+                _ if boundary.is_some() => {
+                    assert_eq!(boundary.as_ref(), Some(stream.checkpoint()));
+                }
+                _ => {}
+            }
+        }
+        let boundary = boundary.unwrap();
+        assert_eq!(stream.checkpoint().position, *stream.position());
+        assert!(boundary.position < stream.checkpoint().position);
+        assert_eq!(boundary.gtids.to_string().matches(':').count(), 1);
+        assert_ne!(boundary.gtids, stream.checkpoint().gtids);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_binlog_position_at() {
+        let dir = temp_dir("position");
+        let files = [
+            &include_bytes!("../../../../tests/events/02_query/log.bin")[..],
+            include_bytes!("../../../../tests/events/03_stop/log.bin"),
+            include_bytes!("../../../../tests/events/05_intvar/log.bin"),
+        ];
+        for (i, data) in files.iter().enumerate() {
+            std::fs::write(dir.join(format!("mysql_bin.00000{}", i + 1)), data).unwrap();
+        }
+        let addr = FakeSource::new(FakeSourceOpts::new(&dir))
+            .spawn("127.0.0.1:0")
+            .unwrap();
+        let opts = FakeSource::client_opts(addr);
+        let connector = crate::client::TcpConnector;
+        let mut client = Client::connect_with(&connector, opts).unwrap();
+        let mut position_at = |timestamp| {
+            client
+                .binlog_position_at(&connector, 100, timestamp)
+                .unwrap()
+                .to_string()
+        };
+        // first events of files are created at 1594395328, 1594395361 and 1596029392
+        assert_eq!(position_at(0), "mysql_bin.000001:4");
+        assert_eq!(position_at(1594395328), "mysql_bin.000001:4");
+        assert_eq!(position_at(1594395340), "mysql_bin.000002:4");
+        let end = format!("mysql_bin.000003:{}", files[2].len());
+        assert_eq!(position_at(1600000000), end);
+
+        let mut stream_opts = BinlogStreamOpts::new(100);
+        stream_opts.non_block = true;
+        let mut stream = client
+            .binlog_stream_at(&connector, &stream_opts, 1594395340)
+            .unwrap();
+        assert_eq!(stream.position().file, "mysql_bin.000002");
+        // artificial rotate events are sent before both files
+        assert_eq!(stream.by_ref().count(), 1 + 3 + 1 + 12);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
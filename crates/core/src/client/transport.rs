@@ -0,0 +1,291 @@
+use std::{
+    io::{Read, Write},
+    net::{IpAddr, TcpStream, ToSocketAddrs},
+};
+
+use crate::connector::ClientOpts;
+
+/// byte stream client connection runs on
+pub trait Transport: Read + Write {
+    /// whether transport is secure enough to send clear password
+    fn is_secure(&self) -> bool {
+        false
+    }
+}
+
+impl Transport for TcpStream {}
+
+#[cfg(unix)]
+impl Transport for std::os::unix::net::UnixStream {
+    fn is_secure(&self) -> bool {
+        true
+    }
+}
+
+/// open transport to server described by [`ClientOpts`]
+pub trait Connector {
+    type Stream: Transport;
+
+    fn connect(&self, opts: &ClientOpts) -> std::io::Result<Self::Stream>;
+}
+
+/// connect to `host:port` directly
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TcpConnector;
+
+impl Connector for TcpConnector {
+    type Stream = TcpStream;
+
+    fn connect(&self, opts: &ClientOpts) -> std::io::Result<Self::Stream> {
+        let stream = TcpStream::connect((opts.host.as_str(), opts.port))?;
+        stream.set_nodelay(true)?;
+        Ok(stream)
+    }
+}
+
+/// connect to unix domain socket, path is taken from [`ClientOpts::socket`]
+#[cfg(unix)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnixConnector;
+
+#[cfg(unix)]
+impl Connector for UnixConnector {
+    type Stream = std::os::unix::net::UnixStream;
+
+    fn connect(&self, opts: &ClientOpts) -> std::io::Result<Self::Stream> {
+        let path = opts
+            .socket
+            .as_deref()
+            .unwrap_or("/var/run/mysqld/mysqld.sock");
+        std::os::unix::net::UnixStream::connect(path)
+    }
+}
+
+/// stream opened by [`DefaultConnector`]
+#[derive(Debug)]
+pub enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixStream),
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Stream::Tcp(s) => s.read(buf),
+            #[cfg(unix)]
+            Stream::Unix(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Stream::Tcp(s) => s.write(buf),
+            #[cfg(unix)]
+            Stream::Unix(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Stream::Tcp(s) => s.flush(),
+            #[cfg(unix)]
+            Stream::Unix(s) => s.flush(),
+        }
+    }
+}
+
+impl Transport for Stream {
+    fn is_secure(&self) -> bool {
+        match self {
+            Stream::Tcp(s) => s.is_secure(),
+            #[cfg(unix)]
+            Stream::Unix(s) => s.is_secure(),
+        }
+    }
+}
+
+/// use unix socket if [`ClientOpts::socket`] is set, otherwise tcp
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultConnector;
+
+impl Connector for DefaultConnector {
+    type Stream = Stream;
+
+    fn connect(&self, opts: &ClientOpts) -> std::io::Result<Self::Stream> {
+        #[cfg(unix)]
+        if opts.socket.is_some() {
+            return UnixConnector.connect(opts).map(Stream::Unix);
+        }
+        TcpConnector.connect(opts).map(Stream::Tcp)
+    }
+}
+
+/// connect to server through SOCKS5 proxy, target host is resolved by proxy
+///
+/// [rfc1928](https://www.rfc-editor.org/rfc/rfc1928)
+#[derive(Debug, Clone)]
+pub struct Socks5Connector<A> {
+    pub proxy: A,
+    /// username and password, [rfc1929](https://www.rfc-editor.org/rfc/rfc1929)
+    pub auth: Option<(String, String)>,
+}
+
+impl<A: ToSocketAddrs> Socks5Connector<A> {
+    pub fn new(proxy: A) -> Self {
+        Self { proxy, auth: None }
+    }
+
+    pub fn with_auth(mut self, user: impl Into<String>, password: impl Into<String>) -> Self {
+        self.auth = Some((user.into(), password.into()));
+        self
+    }
+
+    fn negotiate(&self, stream: &mut TcpStream) -> std::io::Result<()> {
+        let method = if self.auth.is_some() { 0x02 } else { 0x00 };
+        stream.write_all(&[0x05, 0x01, method])?;
+        let mut resp = [0u8; 2];
+        stream.read_exact(&mut resp)?;
+        if resp[0] != 0x05 || resp[1] != method {
+            return Err(proxy_error("no acceptable authentication method"));
+        }
+        if let Some((user, password)) = self.auth.as_ref() {
+            if user.len() > 255 || password.len() > 255 {
+                return Err(proxy_error("username or password too long"));
+            }
+            let mut req = vec![0x01, user.len() as u8];
+            req.extend_from_slice(user.as_bytes());
+            req.push(password.len() as u8);
+            req.extend_from_slice(password.as_bytes());
+            stream.write_all(&req)?;
+            stream.read_exact(&mut resp)?;
+            if resp[1] != 0x00 {
+                return Err(proxy_error("authentication failed"));
+            }
+        }
+        Ok(())
+    }
+
+    fn request(&self, stream: &mut TcpStream, host: &str, port: u16) -> std::io::Result<()> {
+        let mut req = vec![0x05, 0x01, 0x00];
+        match host.parse::<IpAddr>() {
+            Ok(IpAddr::V4(ip)) => {
+                req.push(0x01);
+                req.extend_from_slice(&ip.octets());
+            }
+            Ok(IpAddr::V6(ip)) => {
+                req.push(0x04);
+                req.extend_from_slice(&ip.octets());
+            }
+            Err(_) => {
+                if host.len() > 255 {
+                    return Err(proxy_error("host name too long"));
+                }
+                req.push(0x03);
+                req.push(host.len() as u8);
+                req.extend_from_slice(host.as_bytes());
+            }
+        }
+        req.extend_from_slice(&port.to_be_bytes());
+        stream.write_all(&req)?;
+        let mut resp = [0u8; 4];
+        stream.read_exact(&mut resp)?;
+        if resp[0] != 0x05 {
+            return Err(proxy_error("invalid reply"));
+        }
+        if resp[1] != 0x00 {
+            return Err(proxy_error(reply_msg(resp[1])));
+        }
+        // skip bound address and port
+        let addr_len = match resp[3] {
+            0x01 => 4,
+            0x04 => 16,
+            0x03 => {
+                let mut len = [0u8; 1];
+                stream.read_exact(&mut len)?;
+                len[0] as usize
+            }
+            _ => return Err(proxy_error("invalid address type")),
+        };
+        let mut bound = vec![0u8; addr_len + 2];
+        stream.read_exact(&mut bound)?;
+        Ok(())
+    }
+}
+
+impl<A: ToSocketAddrs> Connector for Socks5Connector<A> {
+    type Stream = TcpStream;
+
+    fn connect(&self, opts: &ClientOpts) -> std::io::Result<Self::Stream> {
+        let mut stream = TcpStream::connect(&self.proxy)?;
+        stream.set_nodelay(true)?;
+        self.negotiate(&mut stream)?;
+        self.request(&mut stream, &opts.host, opts.port)?;
+        Ok(stream)
+    }
+}
+
+fn proxy_error(msg: &str) -> std::io::Error {
+    std::io::Error::other(format!("socks5 proxy: {msg}"))
+}
+
+fn reply_msg(rep: u8) -> &'static str {
+    match rep {
+        0x01 => "general failure",
+        0x02 => "connection not allowed by ruleset",
+        0x03 => "network unreachable",
+        0x04 => "host unreachable",
+        0x05 => "connection refused",
+        0x06 => "TTL expired",
+        0x07 => "command not supported",
+        0x08 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn test_socks5() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 3];
+            stream.read_exact(&mut buf).unwrap();
+            assert_eq!(buf, [5, 1, 2]);
+            stream.write_all(&[5, 2]).unwrap();
+            let mut buf = [0u8; 11];
+            stream.read_exact(&mut buf).unwrap();
+            assert_eq!(&buf, b"\x01\x04user\x04pass");
+            stream.write_all(&[1, 0]).unwrap();
+            let mut buf = [0u8; 4 + 1 + 8 + 2];
+            stream.read_exact(&mut buf).unwrap();
+            assert_eq!(&buf[..5], &[5, 1, 0, 3, 8]);
+            assert_eq!(&buf[5..13], b"db.local");
+            assert_eq!(&buf[13..], &3306u16.to_be_bytes());
+            stream
+                .write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0x0c, 0xea])
+                .unwrap();
+            stream.write_all(b"hello").unwrap();
+        });
+        let opts = ClientOpts {
+            host: "db.local".into(),
+            ..Default::default()
+        };
+        let mut stream = Socks5Connector::new(proxy)
+            .with_auth("user", "pass")
+            .connect(&opts)
+            .unwrap();
+        let mut buf = [0u8; 5];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+        server.join().unwrap();
+    }
+}
@@ -1,8 +1,14 @@
 use bytes::BufMut;
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 
 use parse_tool::{CheckError, InputBuf};
 use thiserror::Error;
+
+#[cfg(feature = "tokio")]
+mod packet;
+#[cfg(feature = "tokio")]
+pub use packet::*;
+
 #[derive(Debug, Clone, Error)]
 pub enum DecodeError {
     #[error("no enough data")]
@@ -13,6 +19,8 @@ pub enum DecodeError {
     MissingNull,
     #[error("invalid data")]
     InvalidData,
+    #[error("checksum mismatch")]
+    ChecksumMismatch,
 }
 
 impl From<CheckError> for DecodeError {
@@ -98,14 +106,16 @@ macro_rules! custom_impl {
 
 macro_rules! fix {
     ($name:ident, $len:literal, $min_ty:ty, $max:expr) => {
-        #[derive(Default, Debug, Clone, Copy)]
+        #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
         pub struct $name(pub(crate) [u8; $len]);
 
         from_prime!($min_ty, $name, $max, $len - 1);
         custom_impl!($min_ty, $name, $len);
     };
     ($name:ident, $len:literal, $min_ty:ty) => {
-        #[derive(Default, Debug, Clone, Copy)]
+        #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
         pub struct $name(pub(crate) [u8; $len]);
         from_prime!($min_ty, $name);
         custom_impl!($min_ty, $name, $len);
@@ -120,7 +130,8 @@ fix!(Int6, 6, u64, u64::MAX >> 2);
 fix!(Int8, 8, u64);
 
 /// variable length int
-#[derive(Default, Debug, Clone, Copy)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VLenInt(pub u64);
 
 impl VLenInt {
@@ -164,7 +175,7 @@ impl Encode for VLenInt {
             }
             65536..=16777215 => {
                 buf.put_u8(0xfd);
-                buf.extend_from_slice(&(self.0 as u32).to_le_bytes()[..2]);
+                buf.extend_from_slice(&(self.0 as u32).to_le_bytes()[..3]);
             }
             16777216.. => {
                 buf.put_u8(0xfe);
@@ -174,26 +185,39 @@ impl Encode for VLenInt {
     }
 }
 
-pub fn get_null_term_bytes<I: InputBuf>(input: &mut I) -> Result<Vec<u8>, DecodeError> {
+/// slice bytes before null terminator from input without copying
+pub fn get_null_term_bytes(input: &mut BytesMut) -> Result<Bytes, DecodeError> {
     let pos = input
-        .slice()
         .iter()
         .position(|b| *b == b'\0')
         .ok_or(DecodeError::MissingNull)?;
-    let data = input.read_vec(pos)?;
+    let data = input.split_to(pos).freeze();
     input.jump_to(1)?;
     Ok(data)
 }
 
+/// slice `len` bytes from input without copying
+pub fn get_bytes(input: &mut BytesMut, len: usize) -> Result<Bytes, DecodeError> {
+    split_input(input, len).map(BytesMut::freeze)
+}
+
+/// split `len` bytes from input to be decoded on their own, without copying
+pub fn split_input(input: &mut BytesMut, len: usize) -> Result<BytesMut, DecodeError> {
+    if input.len() < len {
+        return Err(DecodeError::NoEnoughData);
+    }
+    Ok(input.split_to(len))
+}
+
 pub fn put_null_term_bytes(input: impl AsRef<[u8]>, buf: &mut BytesMut) {
     buf.extend_from_slice(input.as_ref());
     buf.put_u8(b'\0');
 }
 
-pub fn get_var_bytes<I: InputBuf>(input: &mut I) -> Result<Vec<u8>, DecodeError> {
+/// slice length encoded bytes from input without copying
+pub fn get_var_bytes(input: &mut BytesMut) -> Result<Bytes, DecodeError> {
     let len = VLenInt::decode(input)?.0 as usize;
-    let data = input.read_vec(len)?;
-    Ok(data)
+    get_bytes(input, len)
 }
 
 pub fn put_var_bytes(input: impl AsRef<[u8]>, buf: &mut BytesMut) {
@@ -203,18 +227,24 @@ pub fn put_var_bytes(input: impl AsRef<[u8]>, buf: &mut BytesMut) {
     buf.extend_from_slice(input.as_ref());
 }
 
-pub fn get_null_term_str<I: InputBuf>(input: &mut I) -> Result<String, DecodeError> {
-    let raw = get_null_term_bytes(input)?;
-    String::from_utf8(raw).map_err(|_| DecodeError::InvalidUtf8)
+/// validated and copied into owned string, use [`get_null_term_bytes`] to avoid copying
+pub fn get_null_term_str(input: &mut BytesMut) -> Result<String, DecodeError> {
+    utf8(&get_null_term_bytes(input)?)
 }
 
 pub fn put_null_term_str(s: &str, buf: &mut BytesMut) {
     put_null_term_bytes(s.as_bytes(), buf)
 }
 
-pub fn get_var_str<I: InputBuf>(input: &mut I) -> Result<String, DecodeError> {
-    let raw = get_var_bytes(input)?;
-    String::from_utf8(raw).map_err(|_| DecodeError::InvalidUtf8)
+/// validated and copied into owned string, use [`get_var_bytes`] to avoid copying
+pub fn get_var_str(input: &mut BytesMut) -> Result<String, DecodeError> {
+    utf8(&get_var_bytes(input)?)
+}
+
+fn utf8(data: &[u8]) -> Result<String, DecodeError> {
+    std::str::from_utf8(data)
+        .map(str::to_string)
+        .map_err(|_| DecodeError::InvalidUtf8)
 }
 
 pub fn put_var_str(s: &str, buf: &mut BytesMut) {
@@ -0,0 +1,297 @@
+use std::{
+    io::{Read, Write},
+    marker::PhantomData,
+};
+
+use bytes::{Buf, BytesMut};
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use thiserror::Error;
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::connector::{Packet, MAX_PAYLOAD_LEN};
+
+use super::{Decode, DecodeError, Encode, Int1, Int3};
+
+/// payload shorter than this will not be compressed
+pub const MIN_COMPRESS_LEN: usize = 50;
+
+#[derive(Debug, Error)]
+pub enum CodecError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("decode error: {0}")]
+    Decode(#[from] DecodeError),
+    #[error("packet out of order, expect seq id {expect}, got {got}")]
+    OutOfOrder { expect: u8, got: u8 },
+}
+
+/// [`Decoder`] and [`Encoder`] framing mysql packets
+///
+/// decoded payload is `P`, any [`Encode`] type can be encoded. Sequence id is tracked
+/// by codec, call [`PacketCodec::reset_seq_id`] before sending a new command.
+///
+/// [compression doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_basic_compression.html)
+#[derive(Debug)]
+pub struct PacketCodec<P> {
+    seq_id: u8,
+    compressed_seq_id: u8,
+    compress: bool,
+    /// decompressed bytes waiting for framing
+    plain: BytesMut,
+    _payload: PhantomData<fn() -> P>,
+}
+
+impl<P> Default for PacketCodec<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P> PacketCodec<P> {
+    pub fn new() -> Self {
+        Self {
+            seq_id: 0,
+            compressed_seq_id: 0,
+            compress: false,
+            plain: BytesMut::new(),
+            _payload: PhantomData,
+        }
+    }
+
+    /// enable compression protocol, should be called after `CLIENT_COMPRESS` negotiated
+    pub fn with_compression(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    pub fn set_compression(&mut self, compress: bool) {
+        self.compress = compress;
+    }
+
+    /// sequence id of next packet
+    pub fn seq_id(&self) -> u8 {
+        self.seq_id
+    }
+
+    pub fn set_seq_id(&mut self, seq_id: u8) {
+        self.seq_id = seq_id;
+    }
+
+    /// reset sequence ids, used when starting a new command
+    pub fn reset_seq_id(&mut self) {
+        self.seq_id = 0;
+        self.compressed_seq_id = 0;
+    }
+
+    /// switch payload type, keeping sequence id and buffered data
+    pub fn cast<T>(self) -> PacketCodec<T> {
+        PacketCodec {
+            seq_id: self.seq_id,
+            compressed_seq_id: self.compressed_seq_id,
+            compress: self.compress,
+            plain: self.plain,
+            _payload: PhantomData,
+        }
+    }
+
+    /// move all complete compressed packets in `src` into plain buffer
+    fn decompress(&mut self, src: &mut BytesMut) -> Result<(), CodecError> {
+        while src.len() >= 7 {
+            let len = u32::from_le_bytes([src[0], src[1], src[2], 0]) as usize;
+            if src.len() < 7 + len {
+                break;
+            }
+            check_seq_id(&mut self.compressed_seq_id, src[3])?;
+            let plain_len = u32::from_le_bytes([src[4], src[5], src[6], 0]) as usize;
+            src.advance(7);
+            let body = src.split_to(len);
+            if plain_len == 0 {
+                self.plain.extend_from_slice(&body);
+            } else {
+                let mut data = Vec::with_capacity(plain_len);
+                ZlibDecoder::new(&body[..]).read_to_end(&mut data)?;
+                if data.len() != plain_len {
+                    return Err(DecodeError::InvalidData.into());
+                }
+                self.plain.extend_from_slice(&data);
+            }
+        }
+        Ok(())
+    }
+
+    fn write_compressed(
+        &mut self,
+        mut packets: BytesMut,
+        dst: &mut BytesMut,
+    ) -> Result<(), CodecError> {
+        loop {
+            let chunk = packets.split_to(packets.len().min(MAX_PAYLOAD_LEN));
+            if chunk.len() < MIN_COMPRESS_LEN {
+                Int3::from(chunk.len() as u32).encode(dst);
+                Int1::from(self.compressed_seq_id).encode(dst);
+                Int3::from(0).encode(dst);
+                dst.extend_from_slice(&chunk);
+            } else {
+                let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+                encoder.write_all(&chunk)?;
+                let data = encoder.finish()?;
+                Int3::from(data.len() as u32).encode(dst);
+                Int1::from(self.compressed_seq_id).encode(dst);
+                Int3::from(chunk.len() as u32).encode(dst);
+                dst.extend_from_slice(&data);
+            }
+            self.compressed_seq_id = self.compressed_seq_id.wrapping_add(1);
+            if packets.is_empty() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_seq_id(expect: &mut u8, got: u8) -> Result<(), CodecError> {
+    if *expect != got {
+        return Err(CodecError::OutOfOrder {
+            expect: *expect,
+            got,
+        });
+    }
+    *expect = expect.wrapping_add(1);
+    Ok(())
+}
+
+/// split a complete logical packet, return sequence id of first chunk and joined payload
+fn split_packet(buf: &mut BytesMut, seq_id: &mut u8) -> Result<Option<(u8, BytesMut)>, CodecError> {
+    let mut total = 0;
+    loop {
+        let Some(header) = buf.get(total..total + 4) else {
+            return Ok(None);
+        };
+        let len = u32::from_le_bytes([header[0], header[1], header[2], 0]) as usize;
+        total += 4 + len;
+        if buf.len() < total {
+            buf.reserve(total - buf.len());
+            return Ok(None);
+        }
+        if len < MAX_PAYLOAD_LEN {
+            break;
+        }
+    }
+    let first = buf[3];
+    let mut chunks = buf.split_to(total);
+    let mut payload = BytesMut::with_capacity(total);
+    while !chunks.is_empty() {
+        let len = u32::from_le_bytes([chunks[0], chunks[1], chunks[2], 0]) as usize;
+        check_seq_id(seq_id, chunks[3])?;
+        chunks.advance(4);
+        payload.extend_from_slice(&chunks.split_to(len));
+    }
+    Ok(Some((first, payload)))
+}
+
+impl<P: Decode<BytesMut>> Decoder for PacketCodec<P> {
+    type Item = Packet<P>;
+    type Error = CodecError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let framed = if self.compress {
+            self.decompress(src)?;
+            split_packet(&mut self.plain, &mut self.seq_id)?
+        } else {
+            split_packet(src, &mut self.seq_id)?
+        };
+        let Some((seq_id, mut payload)) = framed else {
+            return Ok(None);
+        };
+        let len = Int3::from(payload.len().min(MAX_PAYLOAD_LEN) as u32);
+        let payload = P::decode(&mut payload)?;
+        Ok(Some(Packet {
+            len,
+            seq_id: Int1::from(seq_id),
+            payload,
+        }))
+    }
+}
+
+impl<P, E: Encode> Encoder<E> for PacketCodec<P> {
+    type Error = CodecError;
+
+    fn encode(&mut self, item: E, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let mut body = BytesMut::new();
+        item.encode(&mut body);
+        let mut packets = BytesMut::with_capacity(body.len() + 4);
+        loop {
+            let chunk = body.split_to(body.len().min(MAX_PAYLOAD_LEN));
+            Int3::from(chunk.len() as u32).encode(&mut packets);
+            Int1::from(self.seq_id).encode(&mut packets);
+            packets.extend_from_slice(&chunk);
+            self.seq_id = self.seq_id.wrapping_add(1);
+            if chunk.len() < MAX_PAYLOAD_LEN {
+                break;
+            }
+        }
+        if self.compress {
+            self.write_compressed(packets, dst)
+        } else {
+            dst.extend_from_slice(&packets);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::ComQuery;
+
+    #[test]
+    fn test_round_trip() {
+        let mut codec = PacketCodec::<Vec<u8>>::new();
+        let mut buf = BytesMut::new();
+        codec.encode(ComQuery::from("select 1"), &mut buf).unwrap();
+        codec.encode(ComQuery::from("select 2"), &mut buf).unwrap();
+        assert_eq!(codec.seq_id(), 2);
+        let mut codec = PacketCodec::<Vec<u8>>::new();
+        let mut partial = buf.split_to(6);
+        assert!(codec.decode(&mut partial).unwrap().is_none());
+        partial.extend_from_slice(&buf);
+        let first = codec.decode(&mut partial).unwrap().unwrap();
+        assert_eq!(first.seq_id.int(), 0);
+        assert_eq!(first.len.int(), 9);
+        assert_eq!(&first.payload[1..], b"select 1");
+        let second = codec.decode(&mut partial).unwrap().unwrap();
+        assert_eq!(second.seq_id.int(), 1);
+        assert_eq!(&second.payload[1..], b"select 2");
+        assert!(partial.is_empty());
+    }
+
+    #[test]
+    fn test_compressed() {
+        let mut codec = PacketCodec::<Vec<u8>>::new().with_compression(true);
+        let mut buf = BytesMut::new();
+        let long = "select ".to_string() + &"1,".repeat(100) + "1";
+        codec.encode(ComQuery::from("select 1"), &mut buf).unwrap();
+        codec.encode(ComQuery::from(&long), &mut buf).unwrap();
+        // short packet is not compressed
+        assert_eq!(&buf[4..7], &[0, 0, 0]);
+        let mut codec = PacketCodec::<Vec<u8>>::new().with_compression(true);
+        let mut partial = buf.split_to(buf.len() - 1);
+        let first = codec.decode(&mut partial).unwrap().unwrap();
+        assert_eq!(&first.payload[1..], b"select 1");
+        assert!(codec.decode(&mut partial).unwrap().is_none());
+        partial.extend_from_slice(&buf);
+        let second = codec.decode(&mut partial).unwrap().unwrap();
+        assert_eq!(second.seq_id.int(), 1);
+        assert_eq!(&second.payload[1..], long.as_bytes());
+    }
+
+    #[test]
+    fn test_out_of_order() {
+        let mut codec = PacketCodec::<Vec<u8>>::new();
+        let mut buf = BytesMut::from(&[1, 0, 0, 3, 0][..]);
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(CodecError::OutOfOrder { expect: 0, got: 3 })
+        ));
+    }
+}
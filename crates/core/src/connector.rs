@@ -1,19 +1,33 @@
-use crate::codec::{
-    get_var_bytes, get_var_str, Decode, DecodeError, DecodeResult, Encode, Int1, Int2, Int3, Int4,
-    VLenInt,
+use crate::{
+    binlog::GtidSet,
+    codec::{
+        get_var_bytes, get_var_str, put_var_bytes, put_var_str, Decode, DecodeError, DecodeResult,
+        Encode, Int1, Int2, Int3, Int4, Int8, VLenInt,
+    },
+    value::{ColumnFlags, ColumnType},
 };
 
 mod handshake_v10;
-use bytes::{BufMut, BytesMut};
+use bytes::{BufMut, Bytes, BytesMut};
 pub use handshake_v10::*;
 mod handshake_resp;
 pub use handshake_resp::*;
 mod auth;
 pub use auth::*;
+mod opts;
+pub use opts::*;
+mod machine;
+pub use machine::*;
+mod session_track;
+pub use session_track::*;
+mod stmt;
+pub use stmt::*;
+mod command;
+pub use command::*;
 use parse_tool::InputBuf;
 
 /// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_basic_packets.html#sect_protocol_basic_packets_packet)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Packet<P> {
     pub len: Int3,
     pub seq_id: Int1,
@@ -39,6 +53,13 @@ pub fn decode_packet<I: InputBuf, P: Decode<I>>(input: &mut I) -> DecodeResult<P
     })
 }
 
+/// payload length is calculated from encoded payload, `len` field is ignored
+impl<P: Encode> Encode for Packet<P> {
+    fn encode(&self, buf: &mut BytesMut) {
+        encode_packet(self.seq_id.int(), &self.payload, buf)
+    }
+}
+
 pub fn encode_packet<P: Encode>(seq_id: u8, payload: &P, buf: &mut BytesMut) {
     let start = buf.len();
     buf.extend_from_slice(&[0, 0, 0]);
@@ -81,14 +102,93 @@ pub fn native_password_auth(password: &[u8], auth_data: &[u8]) -> [u8; 20] {
     h1
 }
 
-#[derive(Debug, Clone)]
+/// scramble used by `caching_sha2_password` fast authentication
+///
+/// XOR(SHA256(password), SHA256(SHA256(SHA256(password)), nonce))
+pub fn caching_sha2_auth(password: &[u8], auth_data: &[u8]) -> [u8; 32] {
+    use sha2::{Digest, Sha256};
+    let mut h1: [u8; 32] = Sha256::digest(password).into();
+    let h2: [u8; 32] = Sha256::digest(h1).into();
+    let mut hasher = Sha256::new();
+    hasher.update(h2);
+    hasher.update(auth_data);
+    let multi: [u8; 32] = hasher.finalize().into();
+    for i in 0..32 {
+        h1[i] ^= multi[i];
+    }
+    h1
+}
+
+/// password encrypted by server public key in `caching_sha2_password` full authentication
+///
+/// RSA_OAEP(public_key, XOR(password + '\0', nonce)), `public_key` is PEM sent by server
+pub fn caching_sha2_rsa_auth(
+    password: &[u8],
+    auth_data: &[u8],
+    public_key: &[u8],
+) -> Result<Vec<u8>, rsa::Error> {
+    use rsa::{
+        pkcs1::DecodeRsaPublicKey, pkcs8::DecodePublicKey, rand_core::OsRng, Oaep, RsaPublicKey,
+    };
+    let pem = std::str::from_utf8(public_key).map_err(|_| rsa::Error::InvalidArguments)?;
+    let key = RsaPublicKey::from_public_key_pem(pem)
+        .map_err(|e| rsa::Error::Pkcs8(e.into()))
+        .or_else(|_| RsaPublicKey::from_pkcs1_pem(pem).map_err(rsa::Error::Pkcs1))?;
+    let mut data = password.to_vec();
+    data.push(0);
+    if !auth_data.is_empty() {
+        for (i, b) in data.iter_mut().enumerate() {
+            *b ^= auth_data[i % auth_data.len()];
+        }
+    }
+    key.encrypt(&mut OsRng, Oaep::new::<sha1::Sha1>(), &data)
+}
+
+bitflags::bitflags! {
+    /// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/mysql__com_8h.html)
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct ServerStatus: u16 {
+        /// Is raised when a multi-statement transaction has been started
+        const SERVER_STATUS_IN_TRANS = 1;
+        /// Server in auto_commit mode
+        const SERVER_STATUS_AUTOCOMMIT = 2;
+        /// Multi query - next query exists
+        const SERVER_MORE_RESULTS_EXISTS = 8;
+        const SERVER_QUERY_NO_GOOD_INDEX_USED = 16;
+        const SERVER_QUERY_NO_INDEX_USED = 32;
+        /// The server was able to fulfill the clients request and opened a read-only non-scrollable cursor for a query
+        const SERVER_STATUS_CURSOR_EXISTS = 64;
+        /// This flag is sent when a read-only cursor is exhausted, in reply to COM_STMT_FETCH command
+        const SERVER_STATUS_LAST_ROW_SENT = 128;
+        /// A database was dropped
+        const SERVER_STATUS_DB_DROPPED = 256;
+        const SERVER_STATUS_NO_BACKSLASH_ESCAPES = 512;
+        /// Sent to the client if after a prepared statement reprepare we discovered that the new statement returns a different number of result set columns
+        const SERVER_STATUS_METADATA_CHANGED = 1024;
+        const SERVER_QUERY_WAS_SLOW = 2048;
+        /// To mark ResultSet containing output parameter values
+        const SERVER_PS_OUT_PARAMS = 4096;
+        /// Set at the same time as SERVER_STATUS_IN_TRANS if the started multi-statement transaction is a read-only transaction
+        const SERVER_STATUS_IN_TRANS_READONLY = 8192;
+        /// This status flag, when on, implies that one of the state information has changed on the server because of the execution of the last statement
+        const SERVER_SESSION_STATE_CHANGED = 16384;
+    }
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_basic_ok_packet.html)
+///
+/// servers always send info as length encoded string, session state information
+/// only presents when `CLIENT_SESSION_TRACK` is negotiated and
+/// `SERVER_SESSION_STATE_CHANGED` is set.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct OkPacket {
     pub header: Int1,
     pub affected_rows: VLenInt,
     pub last_insert_id: VLenInt,
-    pub status_flags: Int2,
+    pub status_flags: ServerStatus,
     pub warnings: Int2,
     pub info: String,
+    pub session_state_changes: Vec<SessionStateChange>,
 }
 
 impl OkPacket {
@@ -99,16 +199,39 @@ impl OkPacket {
     pub fn is_eof(&self) -> bool {
         self.header.int() == 0xfe
     }
+
+    /// GTIDs of transactions committed by the statement, requires
+    /// `session_track_gtids` enabled on server
+    pub fn gtids(&self) -> Option<&str> {
+        self.session_state_changes
+            .iter()
+            .find_map(|change| match change {
+                SessionStateChange::Gtids(gtids) => Some(gtids.as_str()),
+                _ => None,
+            })
+    }
 }
 
-impl<I: InputBuf> Decode<I> for OkPacket {
-    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+impl Decode<BytesMut> for OkPacket {
+    fn decode(input: &mut BytesMut) -> Result<Self, DecodeError> {
         let header = Int1::decode(input)?;
         let affected_rows = VLenInt::decode(input)?;
         let last_insert_id = VLenInt::decode(input)?;
-        let status_flags = Int2::decode(input)?;
+        let status_flags = ServerStatus::from_bits_retain(Int2::decode(input)?.int());
         let warnings = Int2::decode(input)?;
-        let info = String::new();
+        let info = if input.left() > 0 {
+            get_var_str(input)?
+        } else {
+            String::new()
+        };
+        let session_state_changes = if status_flags
+            .contains(ServerStatus::SERVER_SESSION_STATE_CHANGED)
+            && input.left() > 0
+        {
+            decode_session_state(input)?
+        } else {
+            vec![]
+        };
         Ok(Self {
             header,
             affected_rows,
@@ -116,11 +239,32 @@ impl<I: InputBuf> Decode<I> for OkPacket {
             status_flags,
             warnings,
             info,
+            session_state_changes,
         })
     }
 }
 
-#[derive(Debug, Clone)]
+impl Encode for OkPacket {
+    fn encode(&self, buf: &mut BytesMut) {
+        self.header.encode(buf);
+        self.affected_rows.encode(buf);
+        self.last_insert_id.encode(buf);
+        buf.put_u16_le(self.status_flags.bits());
+        self.warnings.encode(buf);
+        let session_state = self
+            .status_flags
+            .contains(ServerStatus::SERVER_SESSION_STATE_CHANGED)
+            && !self.session_state_changes.is_empty();
+        if !self.info.is_empty() || session_state {
+            put_var_str(&self.info, buf);
+        }
+        if session_state {
+            encode_session_state(&self.session_state_changes, buf);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ErrPacket {
     pub header: Int1,
     pub code: Int2,
@@ -148,14 +292,63 @@ impl<I: InputBuf> Decode<I> for ErrPacket {
     }
 }
 
-#[derive(Debug, Clone)]
+impl Encode for ErrPacket {
+    fn encode(&self, buf: &mut BytesMut) {
+        self.header.encode(buf);
+        self.code.encode(buf);
+        self.sql_state_marker.encode(buf);
+        buf.extend_from_slice(self.sql_state.as_bytes());
+        buf.extend_from_slice(self.error_msg.as_bytes());
+    }
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_basic_eof_packet.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EofPacket {
+    pub header: Int1,
+    pub warnings: Int2,
+    pub status_flags: ServerStatus,
+}
+
+impl EofPacket {
+    /// whether payload is an EOF packet, rather than a row starts with 0xfe
+    pub fn is_eof(payload: &[u8]) -> bool {
+        payload.first() == Some(&0xfe) && payload.len() < 9
+    }
+}
+
+impl<I: InputBuf> Decode<I> for EofPacket {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        let header = Int1::decode(input)?;
+        if header.int() != 0xfe {
+            return Err(DecodeError::InvalidData);
+        }
+        let warnings = Int2::decode(input)?;
+        let status_flags = ServerStatus::from_bits_retain(Int2::decode(input)?.int());
+        Ok(Self {
+            header,
+            warnings,
+            status_flags,
+        })
+    }
+}
+
+impl Encode for EofPacket {
+    fn encode(&self, buf: &mut BytesMut) {
+        self.header.encode(buf);
+        self.warnings.encode(buf);
+        buf.put_u16_le(self.status_flags.bits());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum OkOrErr {
     Ok(OkPacket),
     Err(ErrPacket),
 }
 
-impl<I: InputBuf> Decode<I> for OkOrErr {
-    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+impl Decode<BytesMut> for OkOrErr {
+    fn decode(input: &mut BytesMut) -> Result<Self, DecodeError> {
         match input.slice()[0] {
             0xff => ErrPacket::decode(input).map(Self::Err),
             0xfe | 0x0 => OkPacket::decode(input).map(Self::Ok),
@@ -164,7 +357,16 @@ impl<I: InputBuf> Decode<I> for OkOrErr {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+impl Encode for OkOrErr {
+    fn encode(&self, buf: &mut BytesMut) {
+        match self {
+            Self::Ok(ok) => ok.encode(buf),
+            Self::Err(err) => err.encode(buf),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ComQuit;
 
 impl Encode for ComQuit {
@@ -173,8 +375,23 @@ impl Encode for ComQuit {
     }
 }
 
+impl<I: InputBuf> Decode<I> for ComQuit {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        check_command(input, 0x01)?;
+        Ok(Self)
+    }
+}
+
+/// consume command byte, return error if not matched
+pub fn check_command<I: InputBuf>(input: &mut I, command: u8) -> Result<(), DecodeError> {
+    if input.read_u8_le()? != command {
+        return Err(DecodeError::InvalidData);
+    }
+    Ok(())
+}
+
 /// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_query.html
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ComQuery {
     pub query: String,
 }
@@ -186,6 +403,14 @@ impl Encode for ComQuery {
     }
 }
 
+impl<I: InputBuf> Decode<I> for ComQuery {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        check_command(input, 0x03)?;
+        let query = String::from_utf8(input.read_to_end()).map_err(|_| DecodeError::InvalidUtf8)?;
+        Ok(Self { query })
+    }
+}
+
 impl<T: ToString> From<T> for ComQuery {
     fn from(value: T) -> Self {
         Self {
@@ -194,32 +419,48 @@ impl<T: ToString> From<T> for ComQuery {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TextResultSet {
     pub column_count: VLenInt,
     pub col_defs: Vec<ColDef>,
-    pub rows: Vec<TextResult>
+    pub rows: Vec<TextResult>,
 }
 
 /// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_query_response.html
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TextResult {
-    pub columns: Vec<Vec<u8>>,
+    /// `None` means NULL, values are sliced from packet without copying
+    pub columns: Vec<Option<Bytes>>,
 }
 
-impl<I: InputBuf> Decode<I> for TextResult {
-    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+impl Decode<BytesMut> for TextResult {
+    fn decode(input: &mut BytesMut) -> Result<Self, DecodeError> {
         let mut columns = vec![];
         while input.left() > 0 {
-            let col = get_var_bytes(input)?;
-            columns.push(col);
+            if input.slice()[0] == 0xfb {
+                input.jump_to(1)?;
+                columns.push(None);
+            } else {
+                columns.push(Some(get_var_bytes(input)?));
+            }
         }
         Ok(Self { columns })
     }
 }
 
+impl Encode for TextResult {
+    fn encode(&self, buf: &mut BytesMut) {
+        for col in self.columns.iter() {
+            match col {
+                None => buf.put_u8(0xfb),
+                Some(data) => put_var_bytes(data, buf),
+            }
+        }
+    }
+}
+
 /// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_query_response_text_resultset_column_definition.html
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ColDef {
     pub catalog: String,
     pub schema: String,
@@ -235,8 +476,18 @@ pub struct ColDef {
     pub decimals: Int1,
 }
 
-impl<I: InputBuf> Decode<I> for ColDef {
-    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+impl ColDef {
+    pub fn column_type(&self) -> Result<ColumnType, DecodeError> {
+        ColumnType::try_from(self.ty.int())
+    }
+
+    pub fn column_flags(&self) -> ColumnFlags {
+        ColumnFlags::from_bits_retain(self.flags.int())
+    }
+}
+
+impl Decode<BytesMut> for ColDef {
+    fn decode(input: &mut BytesMut) -> Result<Self, DecodeError> {
         let catalog = get_var_str(input)?;
         let schema = get_var_str(input)?;
         let table = get_var_str(input)?;
@@ -249,6 +500,10 @@ impl<I: InputBuf> Decode<I> for ColDef {
         let ty = Int1::decode(input)?;
         let flags = Int2::decode(input)?;
         let decimals = Int1::decode(input)?;
+        // filler
+        if input.left() >= 2 {
+            input.jump_to(2)?;
+        }
         Ok(Self {
             catalog,
             schema,
@@ -266,8 +521,33 @@ impl<I: InputBuf> Decode<I> for ColDef {
     }
 }
 
+impl Encode for ColDef {
+    fn encode(&self, buf: &mut BytesMut) {
+        put_var_str(&self.catalog, buf);
+        put_var_str(&self.schema, buf);
+        put_var_str(&self.table, buf);
+        put_var_str(&self.original_table, buf);
+        put_var_str(&self.name, buf);
+        put_var_str(&self.original_name, buf);
+        self.length_of_fixed_length_fields.encode(buf);
+        self.charset.encode(buf);
+        self.column_length.encode(buf);
+        self.ty.encode(buf);
+        self.flags.encode(buf);
+        self.decimals.encode(buf);
+        buf.extend_from_slice(&[0, 0]);
+    }
+}
+
+/// server returns EOF packet instead of blocking at the end of binlog
+pub const BINLOG_DUMP_NON_BLOCK: u16 = 0x01;
+/// COM_BINLOG_DUMP_GTID: position is used
+pub const BINLOG_THROUGH_POSITION: u16 = 0x02;
+/// COM_BINLOG_DUMP_GTID: GTID set is sent
+pub const BINLOG_THROUGH_GTID: u16 = 0x04;
+
 /// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_binlog_dump.html)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ComBinLogDump {
     pub pos: Int4,
     pub flags: Int2,
@@ -284,3 +564,281 @@ impl Encode for ComBinLogDump {
         buf.extend_from_slice(self.filename.as_bytes());
     }
 }
+
+impl<I: InputBuf> Decode<I> for ComBinLogDump {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        check_command(input, 0x12)?;
+        let pos = Int4::decode(input)?;
+        let flags = Int2::decode(input)?;
+        let server_id = Int4::decode(input)?;
+        let filename =
+            String::from_utf8(input.read_to_end()).map_err(|_| DecodeError::InvalidUtf8)?;
+        Ok(Self {
+            pos,
+            flags,
+            server_id,
+            filename,
+        })
+    }
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_binlog_dump_gtid.html)
+///
+/// GTID set is only sent when flags contain [`BINLOG_THROUGH_GTID`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComBinLogDumpGtid {
+    pub flags: Int2,
+    pub server_id: Int4,
+    pub filename: String,
+    pub pos: Int8,
+    pub gtid_set: GtidSet,
+}
+
+impl Encode for ComBinLogDumpGtid {
+    fn encode(&self, buf: &mut BytesMut) {
+        buf.put_u8(0x1e);
+        self.flags.encode(buf);
+        self.server_id.encode(buf);
+        Int4::from(self.filename.len() as u32).encode(buf);
+        buf.extend_from_slice(self.filename.as_bytes());
+        self.pos.encode(buf);
+        if self.flags.int() & BINLOG_THROUGH_GTID != 0 {
+            let mut data = BytesMut::new();
+            self.gtid_set.encode(&mut data);
+            Int4::from(data.len() as u32).encode(buf);
+            buf.extend_from_slice(&data);
+        }
+    }
+}
+
+impl<I: InputBuf> Decode<I> for ComBinLogDumpGtid {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        check_command(input, 0x1e)?;
+        let flags = Int2::decode(input)?;
+        let server_id = Int4::decode(input)?;
+        let len = Int4::decode(input)?.int() as usize;
+        let filename =
+            String::from_utf8(input.read_vec(len)?).map_err(|_| DecodeError::InvalidUtf8)?;
+        let pos = Int8::decode(input)?;
+        let gtid_set = if flags.int() & BINLOG_THROUGH_GTID != 0 {
+            let len = Int4::decode(input)?.int() as usize;
+            let mut data = BytesMut::from_iter(input.read_vec(len)?);
+            GtidSet::decode(&mut data)?
+        } else {
+            GtidSet::new()
+        };
+        Ok(Self {
+            flags,
+            server_id,
+            filename,
+            pos,
+            gtid_set,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+
+    use std::collections::HashMap;
+
+    use bytes::Buf;
+
+    use super::*;
+
+    fn round_trip<T: Encode + Decode<BytesMut> + PartialEq + Debug>(value: T) {
+        let mut buf = BytesMut::new();
+        value.encode(&mut buf);
+        assert_eq!(T::decode(&mut buf).unwrap(), value);
+        assert!(buf.is_empty(), "{value:?} left {} bytes", buf.len());
+    }
+
+    fn ok_packet() -> OkPacket {
+        OkPacket {
+            header: Int1::from(0),
+            affected_rows: VLenInt::new(70000),
+            last_insert_id: VLenInt::new(3),
+            status_flags: ServerStatus::SERVER_STATUS_AUTOCOMMIT
+                | ServerStatus::SERVER_SESSION_STATE_CHANGED,
+            warnings: Int2::from(1),
+            info: "Rows matched: 1".into(),
+            session_state_changes: vec![
+                SessionStateChange::Gtids("3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5".into()),
+                SessionStateChange::SystemVariable {
+                    name: "autocommit".into(),
+                    value: "ON".into(),
+                },
+                SessionStateChange::StateChange(true),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_handshake_round_trip() {
+        let raw = include_bytes!("../../../tests/data/handshake_v10.bin");
+        let mut buf = BytesMut::from(&raw[4..]);
+        let handshake = HandshakeV10::decode(&mut buf).unwrap();
+        let mut encoded = BytesMut::new();
+        handshake.encode(&mut encoded);
+        assert_eq!(&encoded[..], &raw[4..]);
+        round_trip(handshake);
+
+        let mut connect_attrs = HashMap::new();
+        connect_attrs.insert("_client_name".to_string(), "boxercrab".to_string());
+        round_trip(HandshakeResponse41 {
+            caps: ClientOpts::default().caps
+                | Capabilities::CLIENT_CONNECT_WITH_DB
+                | Capabilities::CLIENT_CONNECT_ATTRS,
+            max_packet_size: Int4::from(1 << 24),
+            charset: Int1::from(255),
+            ext_caps: Int4::from(0),
+            user_name: "root".into(),
+            auth_resp: BytesMut::from(&[1u8; 20][..]),
+            database: Some("test".into()),
+            plugin_name: Some("mysql_native_password".into()),
+            connect_attrs,
+            zstd_level: Int1::from(0),
+        });
+        round_trip(AuthSwitchReq {
+            plugin_name: "mysql_native_password".into(),
+            plugin_data: BytesMut::from(&[2u8; 20][..]),
+        });
+        round_trip(AuthSwitchResp {
+            data: BytesMut::from(&[3u8; 20][..]),
+        });
+        round_trip(AuthMoreData {
+            data: BytesMut::from(&[0x04][..]),
+        });
+    }
+
+    #[test]
+    fn test_response_round_trip() {
+        round_trip(ok_packet());
+        round_trip(OkPacket {
+            info: String::new(),
+            status_flags: ServerStatus::SERVER_STATUS_AUTOCOMMIT,
+            session_state_changes: vec![],
+            ..ok_packet()
+        });
+        let err = ErrPacket {
+            header: Int1::from(0xff),
+            code: Int2::from(1146),
+            sql_state_marker: Int1::from(b'#'),
+            sql_state: "42S02".into(),
+            error_msg: "Table 'test.t' doesn't exist".into(),
+        };
+        round_trip(err.clone());
+        round_trip(OkOrErr::Err(err));
+        round_trip(OkOrErr::Ok(ok_packet()));
+        round_trip(EofPacket {
+            header: Int1::from(0xfe),
+            warnings: Int2::from(0),
+            status_flags: ServerStatus::SERVER_MORE_RESULTS_EXISTS,
+        });
+        round_trip(TextResult {
+            columns: vec![
+                Some(Bytes::from_static(b"1")),
+                None,
+                Some(Bytes::from(vec![b'a'; 300])),
+            ],
+        });
+        round_trip(ColDef {
+            catalog: "def".into(),
+            schema: "test".into(),
+            table: "t".into(),
+            original_table: "t".into(),
+            name: "id".into(),
+            original_name: "id".into(),
+            length_of_fixed_length_fields: VLenInt::new(0x0c),
+            charset: Int2::from(63),
+            column_length: Int4::from(20),
+            ty: Int1::from(8),
+            flags: Int2::from(0x4203),
+            decimals: Int1::from(0),
+        });
+        round_trip(StmtPrepareOk {
+            status: Int1::from(0),
+            statement_id: Int4::from(1),
+            num_columns: Int2::from(2),
+            num_params: Int2::from(3),
+            warning_count: Int2::from(0),
+            metadata_follows: None,
+        });
+        round_trip(Statistics {
+            info: "Uptime: 10  Threads: 1".into(),
+        });
+    }
+
+    #[test]
+    fn test_command_round_trip() {
+        round_trip(ComQuit);
+        round_trip(ComQuery::from("select 1"));
+        round_trip(ComBinLogDump {
+            pos: Int4::from(4),
+            flags: Int2::from(0),
+            server_id: Int4::from(100),
+            filename: "mysql-bin.000001".into(),
+        });
+        round_trip(ComBinLogDumpGtid {
+            flags: Int2::from(BINLOG_THROUGH_GTID),
+            server_id: Int4::from(100),
+            filename: String::new(),
+            pos: Int8::from(4),
+            gtid_set: "e3e2a4ee-b6dc-11ea-8bcf-0242ac150002:1-3".parse().unwrap(),
+        });
+        round_trip(ComPing);
+        round_trip(ComInitDb {
+            schema: "test".into(),
+        });
+        round_trip(ComResetConnection);
+        round_trip(ComStatistics);
+        round_trip(ComSetOption {
+            option: SetOption::MultiStatementsOff,
+        });
+        round_trip(ComProcessKill {
+            connection_id: Int4::from(42),
+        });
+        round_trip(ComStmtPrepare::from("select ?"));
+        round_trip(ComStmtFetch {
+            statement_id: Int4::from(1),
+            num_rows: Int4::from(100),
+        });
+        round_trip(ComStmtReset {
+            statement_id: Int4::from(1),
+        });
+        round_trip(ComStmtClose {
+            statement_id: Int4::from(1),
+        });
+
+        let caps = ClientOpts::default().caps | Capabilities::CLIENT_CONNECT_ATTRS;
+        let change_user = ComChangeUser {
+            caps,
+            user: "root".into(),
+            auth_resp: BytesMut::from(&[1u8; 20][..]),
+            database: "test".into(),
+            charset: Int2::from(255),
+            plugin_name: "caching_sha2_password".into(),
+            connect_attrs: HashMap::from([("k".to_string(), "v".to_string())]),
+        };
+        let mut buf = BytesMut::new();
+        change_user.encode(&mut buf);
+        assert_eq!(
+            Command::decode_with_caps(&mut buf, caps).unwrap(),
+            Command::ChangeUser(change_user)
+        );
+        assert!(buf.is_empty());
+
+        let mut buf = BytesMut::new();
+        encode_packet(3, &ComQuery::from("select 1"), &mut buf);
+        let packet: Packet<ComQuery> = decode_packet(&mut buf).unwrap();
+        assert_eq!(packet.seq_id.int(), 3);
+        let mut encoded = BytesMut::new();
+        packet.encode(&mut encoded);
+        encoded.advance(4);
+        assert_eq!(
+            Command::decode_with_caps(&mut encoded, caps).unwrap(),
+            Command::Query(ComQuery::from("select 1"))
+        );
+    }
+}
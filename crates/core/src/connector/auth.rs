@@ -1,12 +1,12 @@
-use bytes::BytesMut;
+use bytes::{BufMut, BytesMut};
 use parse_tool::InputBuf;
 
-use crate::codec::{get_null_term_str, Decode, DecodeError, Encode};
+use crate::codec::{get_null_term_str, put_null_term_str, Decode, DecodeError, Encode};
 
 use super::{decode_header, Packet};
 
 /// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_connection_phase_packets_protocol_auth_switch_request.html)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct AuthSwitchReq {
     pub plugin_name: String,
     pub plugin_data: BytesMut,
@@ -16,15 +16,18 @@ impl AuthSwitchReq {
     pub const STATUS: u8 = 254;
 }
 
-impl<I: InputBuf> Decode<I> for AuthSwitchReq {
-    fn decode(input: &mut I) -> Result<AuthSwitchReq, DecodeError> {
+impl Decode<BytesMut> for AuthSwitchReq {
+    fn decode(input: &mut BytesMut) -> Result<AuthSwitchReq, DecodeError> {
         let tag = input.read_u8_le()?;
         if tag != 0xfe {
             return Err(DecodeError::InvalidData);
         }
         let plugin_name = get_null_term_str(input)?;
         let plugin_data = if input.left() > 0 {
-            BytesMut::from_iter(input.read_vec(input.left() - 1)?)
+            let data = BytesMut::from_iter(input.read_vec(input.left() - 1)?);
+            // trailing null
+            input.jump_to(1)?;
+            data
         } else {
             BytesMut::new()
         };
@@ -35,8 +38,17 @@ impl<I: InputBuf> Decode<I> for AuthSwitchReq {
     }
 }
 
+impl Encode for AuthSwitchReq {
+    fn encode(&self, buf: &mut BytesMut) {
+        buf.put_u8(Self::STATUS);
+        put_null_term_str(&self.plugin_name, buf);
+        buf.extend_from_slice(&self.plugin_data);
+        buf.put_u8(0);
+    }
+}
+
 /// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_connection_phase_packets_protocol_auth_switch_response.html
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct AuthSwitchResp {
     pub data: BytesMut,
 }
@@ -54,8 +66,43 @@ impl<I: InputBuf> Decode<I> for Packet<AuthSwitchResp> {
     }
 }
 
+impl<I: InputBuf> Decode<I> for AuthSwitchResp {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        let data = BytesMut::from_iter(input.read_to_end());
+        Ok(Self { data })
+    }
+}
+
 impl Encode for AuthSwitchResp {
     fn encode(&self, buf: &mut BytesMut) {
         buf.extend_from_slice(&self.data);
     }
 }
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_connection_phase_packets_protocol_auth_more_data.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthMoreData {
+    pub data: BytesMut,
+}
+
+impl AuthMoreData {
+    pub const STATUS: u8 = 0x01;
+}
+
+impl<I: InputBuf> Decode<I> for AuthMoreData {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        let tag = input.read_u8_le()?;
+        if tag != Self::STATUS {
+            return Err(DecodeError::InvalidData);
+        }
+        let data = BytesMut::from_iter(input.read_to_end());
+        Ok(Self { data })
+    }
+}
+
+impl Encode for AuthMoreData {
+    fn encode(&self, buf: &mut BytesMut) {
+        buf.put_u8(Self::STATUS);
+        buf.extend_from_slice(&self.data);
+    }
+}
@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+
+use bytes::{BufMut, BytesMut};
+use parse_tool::InputBuf;
+
+use crate::codec::{
+    get_bytes, get_null_term_bytes, get_null_term_str, get_var_str, put_null_term_bytes,
+    put_null_term_str, put_var_bytes, put_var_str, Decode, DecodeError, Encode, Int2, Int4,
+    VLenInt,
+};
+
+use super::{
+    check_command, Capabilities, ComBinLogDump, ComBinLogDumpGtid, ComQuery, ComQuit, ComStmtClose,
+    ComStmtFetch, ComStmtPrepare, ComStmtReset,
+};
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_ping.html)
+///
+/// response is OK packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComPing;
+
+impl Encode for ComPing {
+    fn encode(&self, buf: &mut BytesMut) {
+        buf.put_u8(0x0e);
+    }
+}
+
+impl<I: InputBuf> Decode<I> for ComPing {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        check_command(input, 0x0e)?;
+        Ok(Self)
+    }
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_init_db.html)
+///
+/// response is OK or ERR packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComInitDb {
+    pub schema: String,
+}
+
+impl Encode for ComInitDb {
+    fn encode(&self, buf: &mut BytesMut) {
+        buf.put_u8(0x02);
+        buf.extend_from_slice(self.schema.as_bytes());
+    }
+}
+
+impl<I: InputBuf> Decode<I> for ComInitDb {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        check_command(input, 0x02)?;
+        let schema =
+            String::from_utf8(input.read_to_end()).map_err(|_| DecodeError::InvalidUtf8)?;
+        Ok(Self { schema })
+    }
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_reset_connection.html)
+///
+/// response is OK packet, prepared statements and session state are dropped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComResetConnection;
+
+impl Encode for ComResetConnection {
+    fn encode(&self, buf: &mut BytesMut) {
+        buf.put_u8(0x1f);
+    }
+}
+
+impl<I: InputBuf> Decode<I> for ComResetConnection {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        check_command(input, 0x1f)?;
+        Ok(Self)
+    }
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_change_user.html)
+///
+/// response is OK, ERR, or auth switch request which continues authentication
+/// like connection phase
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComChangeUser {
+    /// capabilities of connection, decide which fields are sent
+    pub caps: Capabilities,
+    pub user: String,
+    pub auth_resp: BytesMut,
+    pub database: String,
+    pub charset: Int2,
+    pub plugin_name: String,
+    pub connect_attrs: HashMap<String, String>,
+}
+
+impl Encode for ComChangeUser {
+    fn encode(&self, buf: &mut BytesMut) {
+        buf.put_u8(0x11);
+        put_null_term_str(&self.user, buf);
+        if self.caps.contains(Capabilities::CLIENT_RESERVED2) {
+            buf.put_u8(self.auth_resp.len() as u8);
+            buf.extend_from_slice(&self.auth_resp);
+        } else {
+            put_null_term_bytes(&self.auth_resp, buf);
+        }
+        put_null_term_str(&self.database, buf);
+        self.charset.encode(buf);
+        if self.caps.contains(Capabilities::CLIENT_PLUGIN_AUTH) {
+            put_null_term_str(&self.plugin_name, buf);
+        }
+        if self.caps.contains(Capabilities::CLIENT_CONNECT_ATTRS) {
+            let mut attrs = BytesMut::new();
+            for (k, v) in self.connect_attrs.iter() {
+                put_var_str(k, &mut attrs);
+                put_var_str(v, &mut attrs);
+            }
+            put_var_bytes(&attrs, buf);
+        }
+    }
+}
+
+impl ComChangeUser {
+    /// decode with capabilities of connection, which are not sent in packet
+    pub fn decode_with_caps(input: &mut BytesMut, caps: Capabilities) -> Result<Self, DecodeError> {
+        check_command(input, 0x11)?;
+        let user = get_null_term_str(input)?;
+        let auth_resp = if caps.contains(Capabilities::CLIENT_RESERVED2) {
+            let len = input.read_u8_le()? as usize;
+            get_bytes(input, len)?
+        } else {
+            get_null_term_bytes(input)?
+        };
+        let database = get_null_term_str(input)?;
+        let charset = Int2::decode(input)?;
+        let plugin_name = if caps.contains(Capabilities::CLIENT_PLUGIN_AUTH) {
+            get_null_term_str(input)?
+        } else {
+            String::new()
+        };
+        let mut connect_attrs = HashMap::new();
+        if caps.contains(Capabilities::CLIENT_CONNECT_ATTRS) {
+            let len = VLenInt::decode(input)?.int() as usize;
+            let end = input
+                .left()
+                .checked_sub(len)
+                .ok_or(DecodeError::NoEnoughData)?;
+            while input.left() > end {
+                let key = get_var_str(input)?;
+                let val = get_var_str(input)?;
+                connect_attrs.insert(key, val);
+            }
+        }
+        Ok(Self {
+            caps,
+            user,
+            auth_resp: BytesMut::from(&auth_resp[..]),
+            database,
+            charset,
+            plugin_name,
+            connect_attrs,
+        })
+    }
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_statistics.html)
+///
+/// response is [`Statistics`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComStatistics;
+
+impl Encode for ComStatistics {
+    fn encode(&self, buf: &mut BytesMut) {
+        buf.put_u8(0x09);
+    }
+}
+
+impl<I: InputBuf> Decode<I> for ComStatistics {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        check_command(input, 0x09)?;
+        Ok(Self)
+    }
+}
+
+/// human readable server statistics, e.g. `Uptime: 10  Threads: 1 ...`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Statistics {
+    pub info: String,
+}
+
+impl<I: InputBuf> Decode<I> for Statistics {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        let info = String::from_utf8(input.read_to_end()).map_err(|_| DecodeError::InvalidUtf8)?;
+        Ok(Self { info })
+    }
+}
+
+impl Encode for Statistics {
+    fn encode(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(self.info.as_bytes());
+    }
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/mysql__com_8h.html)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
+pub enum SetOption {
+    MultiStatementsOn = 0,
+    MultiStatementsOff = 1,
+}
+
+impl TryFrom<u16> for SetOption {
+    type Error = DecodeError;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::MultiStatementsOn),
+            1 => Ok(Self::MultiStatementsOff),
+            _ => Err(DecodeError::InvalidData),
+        }
+    }
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_set_option.html)
+///
+/// response is EOF(OK if `CLIENT_DEPRECATE_EOF`) or ERR packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComSetOption {
+    pub option: SetOption,
+}
+
+impl Encode for ComSetOption {
+    fn encode(&self, buf: &mut BytesMut) {
+        buf.put_u8(0x1b);
+        Int2::from(self.option as u16).encode(buf);
+    }
+}
+
+impl<I: InputBuf> Decode<I> for ComSetOption {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        check_command(input, 0x1b)?;
+        let option = SetOption::try_from(Int2::decode(input)?.int())?;
+        Ok(Self { option })
+    }
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_process_kill.html)
+///
+/// response is OK or ERR packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComProcessKill {
+    pub connection_id: Int4,
+}
+
+impl Encode for ComProcessKill {
+    fn encode(&self, buf: &mut BytesMut) {
+        buf.put_u8(0x0c);
+        self.connection_id.encode(buf);
+    }
+}
+
+impl<I: InputBuf> Decode<I> for ComProcessKill {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        check_command(input, 0x0c)?;
+        let connection_id = Int4::decode(input)?;
+        Ok(Self { connection_id })
+    }
+}
+
+/// client command received by server, dispatched by command byte
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Quit(ComQuit),
+    InitDb(ComInitDb),
+    Query(ComQuery),
+    Statistics(ComStatistics),
+    ProcessKill(ComProcessKill),
+    Ping(ComPing),
+    ChangeUser(ComChangeUser),
+    BinlogDump(ComBinLogDump),
+    BinlogDumpGtid(ComBinLogDumpGtid),
+    StmtPrepare(ComStmtPrepare),
+    /// raw payload, decode it by [`ComStmtExecute::decode_with_params`] with parameter
+    /// count of prepared statement
+    ///
+    /// [`ComStmtExecute::decode_with_params`]: super::ComStmtExecute::decode_with_params
+    StmtExecute(BytesMut),
+    StmtClose(ComStmtClose),
+    StmtReset(ComStmtReset),
+    SetOption(ComSetOption),
+    StmtFetch(ComStmtFetch),
+    ResetConnection(ComResetConnection),
+    /// command not supported by this crate, raw payload including command byte
+    Unknown(BytesMut),
+}
+
+impl Command {
+    /// decode a command payload, capabilities of connection are required by COM_CHANGE_USER
+    pub fn decode_with_caps(input: &mut BytesMut, caps: Capabilities) -> Result<Self, DecodeError> {
+        let cmd = match input.first().copied() {
+            Some(0x01) => Self::Quit(ComQuit::decode(input)?),
+            Some(0x02) => Self::InitDb(ComInitDb::decode(input)?),
+            Some(0x03) => Self::Query(ComQuery::decode(input)?),
+            Some(0x09) => Self::Statistics(ComStatistics::decode(input)?),
+            Some(0x0c) => Self::ProcessKill(ComProcessKill::decode(input)?),
+            Some(0x0e) => Self::Ping(ComPing::decode(input)?),
+            Some(0x11) => Self::ChangeUser(ComChangeUser::decode_with_caps(input, caps)?),
+            Some(0x12) => Self::BinlogDump(ComBinLogDump::decode(input)?),
+            Some(0x1e) => Self::BinlogDumpGtid(ComBinLogDumpGtid::decode(input)?),
+            Some(0x16) => Self::StmtPrepare(ComStmtPrepare::decode(input)?),
+            Some(0x17) => Self::StmtExecute(input.split()),
+            Some(0x19) => Self::StmtClose(ComStmtClose::decode(input)?),
+            Some(0x1a) => Self::StmtReset(ComStmtReset::decode(input)?),
+            Some(0x1b) => Self::SetOption(ComSetOption::decode(input)?),
+            Some(0x1c) => Self::StmtFetch(ComStmtFetch::decode(input)?),
+            Some(0x1f) => Self::ResetConnection(ComResetConnection::decode(input)?),
+            Some(_) => Self::Unknown(input.split()),
+            None => return Err(DecodeError::NoEnoughData),
+        };
+        Ok(cmd)
+    }
+}
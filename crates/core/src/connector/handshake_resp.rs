@@ -4,41 +4,48 @@ use bytes::BytesMut;
 use parse_tool::InputBuf;
 
 use crate::codec::{
-    get_null_term_str, get_var_bytes, get_var_str, put_null_term_str, put_var_bytes, put_var_str,
-    Decode, DecodeError, Encode, Int1, Int4, VLenInt,
+    get_bytes, get_null_term_bytes, get_null_term_str, get_var_bytes, get_var_str,
+    put_null_term_bytes, put_null_term_str, put_var_bytes, put_var_str, Decode, DecodeError,
+    Encode, Int1, Int4, VLenInt,
 };
 
 use super::Capabilities;
 
 /// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_connection_phase_packets_protocol_handshake_response.html#sect_protocol_connection_phase_packets_protocol_handshake_response41)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct HandshakeResponse41 {
     pub caps: Capabilities,
     pub max_packet_size: Int4,
     pub charset: Int1,
+    /// MariaDB extended capabilities, last 4 bytes of filler
+    pub ext_caps: Int4,
     pub user_name: String,
     pub auth_resp: BytesMut,
     pub database: Option<String>,
     pub plugin_name: Option<String>,
     pub connect_attrs: HashMap<String, String>,
+    /// only sent when `CLIENT_ZSTD_COMPRESSION_ALGORITHM` is set
     pub zstd_level: Int1,
 }
 
-impl<I: InputBuf> Decode<I> for HandshakeResponse41 {
-    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+impl Decode<BytesMut> for HandshakeResponse41 {
+    fn decode(input: &mut BytesMut) -> Result<Self, DecodeError> {
         let caps = Int4::decode(input)?;
-        let caps = Capabilities::from_bits(caps.int()).unwrap();
+        let caps = Capabilities::from_bits_retain(caps.int());
         let max_packet_size = Int4::decode(input)?;
         let charset = Int1::decode(input)?;
-        input.read_vec(23)?;
+        input.read_vec(19)?;
+        let ext_caps = Int4::decode(input)?;
         let user_name = get_null_term_str(input)?;
         let auth_resp = if caps.contains(Capabilities::CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA) {
             get_var_bytes(input)?
-        } else {
+        } else if caps.contains(Capabilities::CLIENT_RESERVED2) {
             let len = Int1::decode(input)?.int() as usize;
-            input.read_vec(len)?
+            get_bytes(input, len)?
+        } else {
+            get_null_term_bytes(input)?
         };
-        let auth_resp = BytesMut::from_iter(auth_resp);
+        let auth_resp = BytesMut::from(&auth_resp[..]);
         let database = if caps.contains(Capabilities::CLIENT_CONNECT_WITH_DB) {
             Some(get_null_term_str(input)?)
         } else {
@@ -51,18 +58,27 @@ impl<I: InputBuf> Decode<I> for HandshakeResponse41 {
         };
         let mut connect_attrs: HashMap<String, String> = Default::default();
         if caps.contains(Capabilities::CLIENT_CONNECT_ATTRS) {
-            let count = Int1::decode(input)?.int();
-            for _ in 0..count {
+            let len = VLenInt::decode(input)?.int() as usize;
+            let end = input
+                .left()
+                .checked_sub(len)
+                .ok_or(DecodeError::NoEnoughData)?;
+            while input.left() > end {
                 let key = get_var_str(input)?;
                 let val = get_var_str(input)?;
                 connect_attrs.insert(key, val);
             }
         }
-        let zstd_level = Int1::decode(input)?;
+        let zstd_level = if caps.contains(Capabilities::CLIENT_ZSTD_COMPRESSION_ALGORITHM) {
+            Int1::decode(input)?
+        } else {
+            Int1::from(0)
+        };
         Ok(Self {
             caps,
             max_packet_size,
             charset,
+            ext_caps,
             user_name,
             auth_resp,
             database,
@@ -78,33 +94,41 @@ impl Encode for HandshakeResponse41 {
         Int4::from(self.caps.bits()).encode(buf);
         self.max_packet_size.encode(buf);
         self.charset.encode(buf);
-        buf.extend_from_slice(&vec![0].repeat(23));
+        buf.extend_from_slice(&[0; 19]);
+        self.ext_caps.encode(buf);
         put_null_term_str(&self.user_name, buf);
         if self
             .caps
             .contains(Capabilities::CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA)
         {
             put_var_bytes(&self.auth_resp, buf)
-        } else {
+        } else if self.caps.contains(Capabilities::CLIENT_RESERVED2) {
             let len = Int1::from(self.auth_resp.len() as u8);
             len.encode(buf);
             buf.extend_from_slice(&self.auth_resp);
+        } else {
+            put_null_term_bytes(&self.auth_resp, buf);
         }
         if self.caps.contains(Capabilities::CLIENT_CONNECT_WITH_DB) {
-            put_null_term_str(self.database.as_deref().unwrap_or("default"), buf);
+            put_null_term_str(self.database.as_deref().unwrap_or_default(), buf);
         }
         if self.caps.contains(Capabilities::CLIENT_PLUGIN_AUTH) {
             put_null_term_str(self.plugin_name.as_deref().unwrap_or_default(), buf);
         }
         if self.caps.contains(Capabilities::CLIENT_CONNECT_ATTRS) {
-            let len = VLenInt::new(self.connect_attrs.len() as u64);
-            len.encode(buf);
+            let mut attrs = BytesMut::new();
             for (k, v) in self.connect_attrs.iter() {
-                put_var_str(k, buf);
-                put_var_str(v, buf);
+                put_var_str(k, &mut attrs);
+                put_var_str(v, &mut attrs);
             }
+            put_var_bytes(&attrs, buf);
+        }
+        if self
+            .caps
+            .contains(Capabilities::CLIENT_ZSTD_COMPRESSION_ALGORITHM)
+        {
+            self.zstd_level.encode(buf);
         }
-        self.zstd_level.encode(buf);
     }
 }
 #[allow(unused_macros)]
@@ -1,10 +1,12 @@
-use bytes::BytesMut;
+use bytes::{BufMut, BytesMut};
 use parse_tool::InputBuf;
 
-use crate::codec::{get_null_term_str, Decode, DecodeError, Int1, Int2, Int4};
+use crate::codec::{
+    get_null_term_str, put_null_term_str, Decode, DecodeError, Encode, Int1, Int2, Int4,
+};
 
 /// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_connection_phase_packets_protocol_handshake_v10.html)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct HandshakeV10 {
     pub protocol_version: Int1,
     pub server_version: String,
@@ -13,12 +15,14 @@ pub struct HandshakeV10 {
     /// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_basic_character_set.html#a_protocol_character_set)
     pub charset: Int1,
     pub status: Int2,
+    /// MariaDB extended capabilities, sent in reserved bytes when `CLIENT_LONG_PASSWORD` is unset
+    pub ext_caps: Int4,
     pub auth_plugin_name: String,
     pub auth_plugin_data: BytesMut,
 }
 
-impl<I: InputBuf> Decode<I> for HandshakeV10 {
-    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+impl Decode<BytesMut> for HandshakeV10 {
+    fn decode(input: &mut BytesMut) -> Result<Self, DecodeError> {
         let protocol_version = Int1::decode(input)?;
         if protocol_version.int() != 10 {
             return Err(DecodeError::InvalidData);
@@ -35,11 +39,12 @@ impl<I: InputBuf> Decode<I> for HandshakeV10 {
         let mut caps = [0u8; 4];
         caps[..2].copy_from_slice(l_cap.bytes());
         caps[2..].copy_from_slice(h_cap.bytes());
-        let caps = Capabilities::from_bits(u32::from_le_bytes(caps)).unwrap();
+        let caps = Capabilities::from_bits_retain(u32::from_le_bytes(caps));
         let auth_data_len = Int1::decode(input)?.int();
-        input.read_vec(10)?;
-        if auth_data_len > 0 {
-            let len = 13.max(auth_data_len - 8) as usize;
+        input.read_vec(6)?;
+        let ext_caps = Int4::decode(input)?;
+        if caps.contains(Capabilities::CLIENT_RESERVED2) {
+            let len = 13.max(auth_data_len.saturating_sub(8)) as usize;
             auth_plugin_data.extend_from_slice(&input.read_vec(len)?);
         }
         let auth_plugin_name = if caps.contains(Capabilities::CLIENT_PLUGIN_AUTH) {
@@ -54,12 +59,48 @@ impl<I: InputBuf> Decode<I> for HandshakeV10 {
             caps,
             charset,
             status,
+            ext_caps,
             auth_plugin_name,
             auth_plugin_data,
         })
     }
 }
 
+impl Encode for HandshakeV10 {
+    fn encode(&self, buf: &mut BytesMut) {
+        self.protocol_version.encode(buf);
+        put_null_term_str(&self.server_version, buf);
+        self.thread_id.encode(buf);
+        let data = &self.auth_plugin_data;
+        let (part1, part2) = data.split_at(data.len().min(8));
+        buf.extend_from_slice(part1);
+        buf.extend_from_slice(&[0; 8][part1.len()..]);
+        // filler
+        buf.put_u8(0);
+        let caps = self.caps.bits().to_le_bytes();
+        buf.extend_from_slice(&caps[..2]);
+        self.charset.encode(buf);
+        self.status.encode(buf);
+        buf.extend_from_slice(&caps[2..]);
+        if self.caps.contains(Capabilities::CLIENT_PLUGIN_AUTH) {
+            buf.put_u8(data.len() as u8);
+        } else {
+            buf.put_u8(0);
+        }
+        buf.extend_from_slice(&[0; 6]);
+        self.ext_caps.encode(buf);
+        if self.caps.contains(Capabilities::CLIENT_RESERVED2) {
+            buf.extend_from_slice(part2);
+            if part2.len() < 13 {
+                buf.extend_from_slice(&[0; 13][part2.len()..]);
+            }
+        }
+        if self.caps.contains(Capabilities::CLIENT_PLUGIN_AUTH) {
+            put_null_term_str(&self.auth_plugin_name, buf);
+        }
+    }
+}
+
 bitflags::bitflags! {
     /// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/group__group__cs__capabilities__flags.html#ga07344a4eb8f5c74ea8875bb4e9852fb0)
     #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -0,0 +1,704 @@
+use bytes::{Buf, BytesMut};
+use thiserror::Error;
+
+use crate::codec::{Decode, DecodeError, Encode, Int1, Int2, Int3, Int4};
+
+use super::{
+    caching_sha2_auth, caching_sha2_rsa_auth, native_password_auth, AuthMoreData, AuthSwitchReq,
+    AuthSwitchResp, Capabilities, ClientOpts, ComBinLogDump, ComBinLogDumpGtid, ComChangeUser,
+    ComQuit, ErrPacket, HandshakeResponse41, HandshakeV10, OkPacket,
+};
+
+/// max payload length of a single packet, larger payload are split into several packets
+pub const MAX_PAYLOAD_LEN: usize = 0xffffff;
+
+#[derive(Debug, Error)]
+pub enum MachineError {
+    #[error("decode error: {0}")]
+    Decode(#[from] DecodeError),
+    #[error("server error {}: {}", .0.code, .0.error_msg)]
+    Server(ErrPacket),
+    #[error("packet out of order, expect seq id {expect}, got {got}")]
+    OutOfOrder { expect: u8, got: u8 },
+    #[error("unexpected packet 0x{header:02x} in {phase:?} phase")]
+    Unexpected { phase: Phase, header: u8 },
+    #[error("can not {action} in {phase:?} phase")]
+    InvalidPhase { phase: Phase, action: &'static str },
+    #[error("server does not support {0}")]
+    Unsupported(&'static str),
+    #[error("unsupported auth plugin {0}")]
+    UnsupportedAuthPlugin(String),
+    #[error("auth plugin {0} requires secure transport")]
+    InsecureAuth(String),
+    #[error("can not encrypt password with server public key: {0}")]
+    PublicKey(#[from] rsa::Error),
+}
+
+/// current phase of client connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// waiting for initial handshake packet
+    Handshake,
+    /// handshake response sent, waiting for auth result
+    Auth,
+    /// connection established, ready to send commands
+    Command,
+    /// COM_BINLOG_DUMP sent, receiving binlog events
+    Binlog,
+    /// COM_QUIT sent or connection failed
+    Closed,
+}
+
+/// events emitted by [`ClientMachine::poll_event`]
+#[derive(Debug, Clone)]
+pub enum MachineEvent {
+    /// initial handshake received, handshake response has been queued
+    Handshake(HandshakeV10),
+    /// authentication succeeded
+    Connected(OkPacket),
+    /// one response packet payload of current command
+    Response(BytesMut),
+    /// raw binlog event, including event header and checksum if enabled
+    Binlog(BytesMut),
+    /// server closed binlog stream, e.g. non-blocking dump reached end of binlog
+    BinlogEof,
+}
+
+/// sans-IO client connection state machine
+///
+/// caller feeds received bytes by [`ClientMachine::feed`], drives state by
+/// [`ClientMachine::poll_event`] and writes bytes taken from [`ClientMachine::take_output`]
+/// to transport, so the same logic can be used with any blocking or async IO.
+#[derive(Debug)]
+pub struct ClientMachine {
+    opts: ClientOpts,
+    /// options of COM_CHANGE_USER in progress, replace `opts` once server accepts them
+    changed_opts: Option<ClientOpts>,
+    phase: Phase,
+    seq_id: u8,
+    caps: Capabilities,
+    auth_plugin: String,
+    auth_data: BytesMut,
+    secure: bool,
+    recv_buf: BytesMut,
+    send_buf: BytesMut,
+}
+
+impl ClientMachine {
+    pub fn new(opts: ClientOpts) -> Self {
+        Self {
+            opts,
+            changed_opts: None,
+            phase: Phase::Handshake,
+            seq_id: 0,
+            caps: Capabilities::empty(),
+            auth_plugin: String::new(),
+            auth_data: BytesMut::new(),
+            secure: false,
+            recv_buf: BytesMut::new(),
+            send_buf: BytesMut::new(),
+        }
+    }
+
+    /// mark transport as secure(TLS or unix socket), which allows sending clear password
+    pub fn set_secure(&mut self, secure: bool) {
+        self.secure = secure;
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    /// capabilities used by this connection
+    pub fn caps(&self) -> Capabilities {
+        self.caps
+    }
+
+    pub fn opts(&self) -> &ClientOpts {
+        &self.opts
+    }
+
+    /// append bytes received from transport
+    pub fn feed(&mut self, data: &[u8]) {
+        self.recv_buf.extend_from_slice(data);
+    }
+
+    /// take bytes should be written to transport
+    pub fn take_output(&mut self) -> Option<BytesMut> {
+        if self.send_buf.is_empty() {
+            None
+        } else {
+            Some(self.send_buf.split())
+        }
+    }
+
+    /// process next complete packet in receive buffer
+    ///
+    /// return `Ok(None)` if more bytes are needed
+    pub fn poll_event(&mut self) -> Result<Option<MachineEvent>, MachineError> {
+        let payload = match self.next_packet() {
+            Ok(Some(payload)) => payload,
+            Ok(None) => return Ok(None),
+            Err(e) => {
+                self.phase = Phase::Closed;
+                return Err(e);
+            }
+        };
+        let ret = match self.phase {
+            Phase::Handshake => self.on_handshake(payload),
+            Phase::Auth => self.on_auth(payload),
+            Phase::Command => Ok(Some(MachineEvent::Response(payload))),
+            Phase::Binlog => self.on_binlog(payload),
+            Phase::Closed => Err(MachineError::Unexpected {
+                phase: self.phase,
+                header: payload.first().copied().unwrap_or_default(),
+            }),
+        };
+        if ret.is_err() && self.phase != Phase::Command {
+            self.phase = Phase::Closed;
+        }
+        ret
+    }
+
+    /// queue a command packet, sequence id is reset
+    pub fn command<P: Encode>(&mut self, cmd: &P) -> Result<(), MachineError> {
+        self.check_phase(Phase::Command, "send command")?;
+        self.seq_id = 0;
+        self.write_packet(cmd);
+        Ok(())
+    }
+
+    /// queue COM_BINLOG_DUMP and enter binlog phase
+    pub fn binlog_dump(&mut self, dump: &ComBinLogDump) -> Result<(), MachineError> {
+        self.command(dump)?;
+        self.phase = Phase::Binlog;
+        Ok(())
+    }
+
+    /// queue COM_BINLOG_DUMP_GTID and enter binlog phase
+    pub fn binlog_dump_gtid(&mut self, dump: &ComBinLogDumpGtid) -> Result<(), MachineError> {
+        self.command(dump)?;
+        self.phase = Phase::Binlog;
+        Ok(())
+    }
+
+    /// queue COM_CHANGE_USER and enter auth phase, [`MachineEvent::Connected`] is
+    /// emitted when server accepts new user
+    pub fn change_user(
+        &mut self,
+        user: &str,
+        password: &str,
+        database: Option<&str>,
+    ) -> Result<(), MachineError> {
+        self.check_phase(Phase::Command, "change user")?;
+        let mut opts = self.opts.clone();
+        opts.user = user.to_string();
+        opts.password = password.to_string();
+        opts.database = database.map(|db| db.to_string());
+        let auth_resp = self.auth_response(&opts.password, &self.auth_plugin, &self.auth_data)?;
+        let cmd = ComChangeUser {
+            caps: self.caps,
+            user: opts.user.clone(),
+            auth_resp,
+            database: opts.database.clone().unwrap_or_default(),
+            charset: Int2::from(opts.charset as u16),
+            plugin_name: self.auth_plugin.clone(),
+            connect_attrs: opts.connect_attrs.clone(),
+        };
+        self.command(&cmd)?;
+        self.changed_opts = Some(opts);
+        self.phase = Phase::Auth;
+        Ok(())
+    }
+
+    /// queue COM_QUIT and close connection
+    pub fn quit(&mut self) -> Result<(), MachineError> {
+        self.command(&ComQuit)?;
+        self.phase = Phase::Closed;
+        Ok(())
+    }
+
+    fn check_phase(&self, phase: Phase, action: &'static str) -> Result<(), MachineError> {
+        if self.phase != phase {
+            return Err(MachineError::InvalidPhase {
+                phase: self.phase,
+                action,
+            });
+        }
+        Ok(())
+    }
+
+    /// intersection of desired and server capabilities
+    fn negotiate(&self, server: Capabilities) -> Result<Capabilities, MachineError> {
+        if !server.contains(Capabilities::CLIENT_PROTOCOL_41) {
+            return Err(MachineError::Unsupported("CLIENT_PROTOCOL_41"));
+        }
+        let mut caps = self.opts.caps;
+        caps.set(
+            Capabilities::CLIENT_CONNECT_WITH_DB,
+            self.opts.database.is_some(),
+        );
+        caps.set(
+            Capabilities::CLIENT_CONNECT_ATTRS,
+            !self.opts.connect_attrs.is_empty(),
+        );
+        // TLS and compression are not handled by state machine
+        caps.remove(
+            Capabilities::CLIENT_SSL
+                | Capabilities::CLIENT_COMPRESS
+                | Capabilities::CLIENT_ZSTD_COMPRESSION_ALGORITHM,
+        );
+        Ok(caps & server)
+    }
+
+    /// password of user being authenticated
+    fn auth_password(&self) -> &str {
+        &self.changed_opts.as_ref().unwrap_or(&self.opts).password
+    }
+
+    fn auth_response(
+        &self,
+        password: &str,
+        plugin: &str,
+        data: &[u8],
+    ) -> Result<BytesMut, MachineError> {
+        if password.is_empty() {
+            return Ok(BytesMut::new());
+        }
+        let scramble = &data[..data.len().min(20)];
+        match plugin {
+            "mysql_native_password" => Ok(BytesMut::from_iter(native_password_auth(
+                password.as_bytes(),
+                scramble,
+            ))),
+            "caching_sha2_password" => Ok(BytesMut::from_iter(caching_sha2_auth(
+                password.as_bytes(),
+                scramble,
+            ))),
+            "mysql_clear_password" => self.clear_password(password, plugin),
+            _ => Err(MachineError::UnsupportedAuthPlugin(plugin.to_string())),
+        }
+    }
+
+    fn clear_password(&self, password: &str, plugin: &str) -> Result<BytesMut, MachineError> {
+        if !self.secure {
+            return Err(MachineError::InsecureAuth(plugin.to_string()));
+        }
+        let mut data = BytesMut::from(password.as_bytes());
+        data.extend_from_slice(&[0]);
+        Ok(data)
+    }
+
+    fn on_handshake(
+        &mut self,
+        mut payload: BytesMut,
+    ) -> Result<Option<MachineEvent>, MachineError> {
+        if payload.first() == Some(&0xff) {
+            return Err(MachineError::Server(ErrPacket::decode(&mut payload)?));
+        }
+        let handshake = HandshakeV10::decode(&mut payload)?;
+        self.caps = self.negotiate(handshake.caps)?;
+        self.auth_plugin = if handshake.auth_plugin_name.is_empty() {
+            "mysql_native_password".to_string()
+        } else {
+            handshake.auth_plugin_name.clone()
+        };
+        self.auth_data = handshake.auth_plugin_data.clone();
+        let auth_resp =
+            self.auth_response(&self.opts.password, &self.auth_plugin, &self.auth_data)?;
+        let resp = HandshakeResponse41 {
+            caps: self.caps,
+            max_packet_size: Int4::from(self.opts.max_packet_size),
+            charset: Int1::from(self.opts.charset),
+            ext_caps: Int4::from(0),
+            user_name: self.opts.user.clone(),
+            auth_resp,
+            database: self.opts.database.clone(),
+            plugin_name: Some(self.auth_plugin.clone()),
+            connect_attrs: self.opts.connect_attrs.clone(),
+            zstd_level: Int1::from(0),
+        };
+        self.write_packet(&resp);
+        self.phase = Phase::Auth;
+        Ok(Some(MachineEvent::Handshake(handshake)))
+    }
+
+    fn on_auth(&mut self, mut payload: BytesMut) -> Result<Option<MachineEvent>, MachineError> {
+        let header = payload.first().copied().unwrap_or_default();
+        match header {
+            0x00 => {
+                let ok = OkPacket::decode(&mut payload)?;
+                if let Some(opts) = self.changed_opts.take() {
+                    self.opts = opts;
+                }
+                self.phase = Phase::Command;
+                Ok(Some(MachineEvent::Connected(ok)))
+            }
+            0xff => {
+                self.changed_opts = None;
+                Err(MachineError::Server(ErrPacket::decode(&mut payload)?))
+            }
+            AuthSwitchReq::STATUS => {
+                let req = AuthSwitchReq::decode(&mut payload)?;
+                let data =
+                    self.auth_response(self.auth_password(), &req.plugin_name, &req.plugin_data)?;
+                self.auth_plugin = req.plugin_name;
+                self.auth_data = req.plugin_data;
+                self.write_packet(&AuthSwitchResp { data });
+                Ok(None)
+            }
+            AuthMoreData::STATUS if self.auth_plugin == "caching_sha2_password" => {
+                let more = AuthMoreData::decode(&mut payload)?;
+                match more.data.first() {
+                    // fast auth success, OK packet follows
+                    Some(0x03) => Ok(None),
+                    // full auth required, password is sent in clear over secure transport,
+                    // otherwise request server public key to encrypt it
+                    Some(0x04) => {
+                        let data = if self.secure {
+                            self.clear_password(self.auth_password(), &self.auth_plugin)?
+                        } else {
+                            BytesMut::from(&[0x02][..])
+                        };
+                        self.write_packet(&AuthSwitchResp { data });
+                        Ok(None)
+                    }
+                    // PEM of server public key
+                    Some(b'-') => {
+                        let scramble = &self.auth_data[..self.auth_data.len().min(20)];
+                        let data = caching_sha2_rsa_auth(
+                            self.auth_password().as_bytes(),
+                            scramble,
+                            &more.data,
+                        )?;
+                        self.write_packet(&AuthSwitchResp {
+                            data: BytesMut::from(&data[..]),
+                        });
+                        Ok(None)
+                    }
+                    _ => Err(MachineError::Unexpected {
+                        phase: self.phase,
+                        header,
+                    }),
+                }
+            }
+            _ => Err(MachineError::Unexpected {
+                phase: self.phase,
+                header,
+            }),
+        }
+    }
+
+    fn on_binlog(&mut self, mut payload: BytesMut) -> Result<Option<MachineEvent>, MachineError> {
+        match payload.first().copied().unwrap_or_default() {
+            0x00 => {
+                payload.advance(1);
+                Ok(Some(MachineEvent::Binlog(payload)))
+            }
+            0xfe if payload.len() < 9 => {
+                self.phase = Phase::Command;
+                Ok(Some(MachineEvent::BinlogEof))
+            }
+            0xff => {
+                self.phase = Phase::Command;
+                Err(MachineError::Server(ErrPacket::decode(&mut payload)?))
+            }
+            header => Err(MachineError::Unexpected {
+                phase: self.phase,
+                header,
+            }),
+        }
+    }
+
+    /// split a complete logical packet from receive buffer, checking sequence id
+    fn next_packet(&mut self) -> Result<Option<BytesMut>, MachineError> {
+        let mut total = 0;
+        loop {
+            let Some(header) = self.recv_buf.get(total..total + 4) else {
+                return Ok(None);
+            };
+            let len = u32::from_le_bytes([header[0], header[1], header[2], 0]) as usize;
+            total += 4 + len;
+            if self.recv_buf.len() < total {
+                return Ok(None);
+            }
+            if len < MAX_PAYLOAD_LEN {
+                break;
+            }
+        }
+        let mut chunks = self.recv_buf.split_to(total);
+        let mut payload = BytesMut::with_capacity(total);
+        while !chunks.is_empty() {
+            let len = u32::from_le_bytes([chunks[0], chunks[1], chunks[2], 0]) as usize;
+            let seq_id = chunks[3];
+            if seq_id != self.seq_id {
+                return Err(MachineError::OutOfOrder {
+                    expect: self.seq_id,
+                    got: seq_id,
+                });
+            }
+            self.seq_id = self.seq_id.wrapping_add(1);
+            chunks.advance(4);
+            payload.extend_from_slice(&chunks.split_to(len));
+        }
+        Ok(Some(payload))
+    }
+
+    /// encode payload into send buffer, split into several packets if needed
+    fn write_packet<P: Encode>(&mut self, payload: &P) {
+        let mut body = BytesMut::new();
+        payload.encode(&mut body);
+        loop {
+            let chunk = body.split_to(body.len().min(MAX_PAYLOAD_LEN));
+            Int3::from(chunk.len() as u32).encode(&mut self.send_buf);
+            Int1::from(self.seq_id).encode(&mut self.send_buf);
+            self.send_buf.extend_from_slice(&chunk);
+            self.seq_id = self.seq_id.wrapping_add(1);
+            if chunk.len() < MAX_PAYLOAD_LEN {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! hex {
+        ($data:literal) => {{
+            let buf = bytes::BytesMut::from_iter(
+                (0..$data.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&$data[i..i + 2], 16).unwrap()),
+            );
+            buf
+        }};
+    }
+
+    fn connected() -> ClientMachine {
+        let mut machine = ClientMachine::new(ClientOpts {
+            user: "auth".into(),
+            password: "1234".into(),
+            ..Default::default()
+        });
+        machine.feed(include_bytes!("../../../../tests/data/handshake_v10.bin"));
+        assert!(matches!(
+            machine.poll_event().unwrap(),
+            Some(MachineEvent::Handshake(_))
+        ));
+        let out = machine.take_output().unwrap();
+        assert_eq!(out[3], 1);
+        assert_eq!(machine.phase(), Phase::Auth);
+        // fast auth success then OK
+        machine.feed(&hex!("020000020103"));
+        assert!(machine.poll_event().unwrap().is_none());
+        machine.feed(&hex!("0700000300000002000000"));
+        assert!(matches!(
+            machine.poll_event().unwrap(),
+            Some(MachineEvent::Connected(_))
+        ));
+        assert_eq!(machine.phase(), Phase::Command);
+        machine
+    }
+
+    #[test]
+    fn test_handshake() {
+        connected();
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let mut machine = ClientMachine::new(ClientOpts {
+            database: Some("default".into()),
+            ..Default::default()
+        });
+        let mut data =
+            BytesMut::from(&include_bytes!("../../../../tests/data/handshake_v10.bin")[..]);
+        // server without CLIENT_DEPRECATE_EOF
+        data[31] &= !0x01;
+        machine.feed(&data);
+        machine.poll_event().unwrap();
+        let caps = machine.caps();
+        assert!(!caps.contains(Capabilities::CLIENT_DEPRECATE_EOF));
+        assert!(caps.contains(Capabilities::CLIENT_CONNECT_WITH_DB));
+        assert!(!caps.contains(Capabilities::CLIENT_CONNECT_ATTRS));
+        let mut out = machine.take_output().unwrap();
+        out.advance(4);
+        let resp = HandshakeResponse41::decode(&mut out).unwrap();
+        assert_eq!(resp.caps, caps);
+        assert_eq!(resp.database.as_deref(), Some("default"));
+        assert_eq!(out.len(), 0);
+
+        // server without CLIENT_PROTOCOL_41
+        let mut machine = ClientMachine::new(Default::default());
+        data[26] &= !0x02;
+        machine.feed(&data);
+        assert!(matches!(
+            machine.poll_event(),
+            Err(MachineError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_partial_packet() {
+        let mut machine = ClientMachine::new(Default::default());
+        let data = include_bytes!("../../../../tests/data/handshake_v10.bin");
+        machine.feed(&data[..10]);
+        assert!(machine.poll_event().unwrap().is_none());
+        assert!(machine.take_output().is_none());
+        machine.feed(&data[10..]);
+        assert!(machine.poll_event().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_auth_switch() {
+        let mut machine = ClientMachine::new(ClientOpts {
+            password: "1234".into(),
+            ..Default::default()
+        });
+        machine.feed(include_bytes!("../../../../tests/data/handshake_v10.bin"));
+        machine.poll_event().unwrap();
+        machine.take_output();
+        machine.feed(&hex!(
+            "2c000002fe6d7973716c5f6e61746976655f70617373776f7264000102030405060708090a0b0c0d0e0f10111213141500"
+        ));
+        assert!(machine.poll_event().unwrap().is_none());
+        let out = machine.take_output().unwrap();
+        assert_eq!(&out[..4], &[20, 0, 0, 3]);
+        assert_eq!(
+            &out[4..],
+            native_password_auth(b"1234", &hex!("0102030405060708090a0b0c0d0e0f1011121314"))
+        );
+    }
+
+    #[test]
+    fn test_caching_sha2_full_auth() {
+        use rsa::{pkcs8::DecodePrivateKey, Oaep, RsaPrivateKey};
+
+        let mut machine = ClientMachine::new(ClientOpts {
+            password: "1234".into(),
+            ..Default::default()
+        });
+        machine.feed(include_bytes!("../../../../tests/data/handshake_v10.bin"));
+        machine.poll_event().unwrap();
+        machine.take_output();
+        machine.feed(&hex!(
+            "2c000002fe63616368696e675f736861325f70617373776f7264000102030405060708090a0b0c0d0e0f101112131400"
+        ));
+        machine.poll_event().unwrap();
+        machine.take_output();
+
+        // full auth over insecure transport requests server public key
+        machine.feed(&hex!("020000040104"));
+        assert!(machine.poll_event().unwrap().is_none());
+        assert_eq!(&machine.take_output().unwrap()[..], &hex!("0100000502"));
+
+        let pem = include_bytes!("../../../../tests/data/rsa_public_key.pem");
+        let mut packet = BytesMut::new();
+        packet.extend_from_slice(&(pem.len() as u32 + 1).to_le_bytes()[..3]);
+        packet.extend_from_slice(&[6, AuthMoreData::STATUS]);
+        packet.extend_from_slice(pem);
+        machine.feed(&packet);
+        assert!(machine.poll_event().unwrap().is_none());
+        let out = machine.take_output().unwrap();
+        assert_eq!(&out[..4], &[0, 1, 0, 7]);
+        let key = RsaPrivateKey::from_pkcs8_pem(include_str!(
+            "../../../../tests/data/rsa_private_key.pem"
+        ))
+        .unwrap();
+        let data = key.decrypt(Oaep::new::<sha1::Sha1>(), &out[4..]).unwrap();
+        assert_eq!(data, [b'1' ^ 1, b'2' ^ 2, b'3' ^ 3, b'4' ^ 4, 5]);
+
+        machine.feed(&hex!("0700000800000002000000"));
+        assert!(matches!(
+            machine.poll_event().unwrap(),
+            Some(MachineEvent::Connected(_))
+        ));
+    }
+
+    #[test]
+    fn test_out_of_order() {
+        let mut machine = connected();
+        machine.feed(&hex!("0100000500"));
+        assert!(matches!(
+            machine.poll_event(),
+            Err(MachineError::OutOfOrder { expect: 4, got: 5 })
+        ));
+        assert_eq!(machine.phase(), Phase::Closed);
+    }
+
+    #[test]
+    fn test_binlog() {
+        let mut machine = connected();
+        let dump = ComBinLogDump {
+            pos: Int4::from(4),
+            flags: Int2::from(1),
+            server_id: Int4::from(100),
+            filename: "mysql_bin.000001".into(),
+        };
+        machine.binlog_dump(&dump).unwrap();
+        let out = machine.take_output().unwrap();
+        assert_eq!(out[3], 0);
+        assert_eq!(out[4], 0x12);
+        assert!(machine.command(&ComQuit).is_err());
+        machine.feed(&hex!("0400000100010203"));
+        machine.feed(&hex!("05000002fe00000200"));
+        match machine.poll_event().unwrap() {
+            Some(MachineEvent::Binlog(event)) => assert_eq!(&event[..], &[1, 2, 3]),
+            other => panic!("should be binlog event, got {other:?}"),
+        }
+        assert!(matches!(
+            machine.poll_event().unwrap(),
+            Some(MachineEvent::BinlogEof)
+        ));
+        assert_eq!(machine.phase(), Phase::Command);
+    }
+
+    #[test]
+    fn test_change_user() {
+        let mut machine = connected();
+        machine.change_user("other", "", Some("db")).unwrap();
+        assert_eq!(machine.phase(), Phase::Auth);
+        let out = machine.take_output().unwrap();
+        assert_eq!(&out[3..5], &[0, 0x11]);
+        assert_eq!(&out[5..12], b"other\0\0");
+        assert_eq!(&out[12..15], b"db\0");
+        machine.feed(&hex!("0700000100000002000000"));
+        assert!(matches!(
+            machine.poll_event().unwrap(),
+            Some(MachineEvent::Connected(_))
+        ));
+        assert_eq!(machine.opts().user, "other");
+        assert_eq!(machine.phase(), Phase::Command);
+    }
+
+    #[test]
+    fn test_change_user_failed() {
+        // auth response can't be computed, nothing is sent
+        let mut machine = connected();
+        machine.auth_plugin = "mysql_clear_password".into();
+        assert!(matches!(
+            machine.change_user("other", "secret", Some("db")),
+            Err(MachineError::InsecureAuth(_))
+        ));
+        assert!(machine.take_output().is_none());
+        assert_eq!(machine.phase(), Phase::Command);
+        assert_eq!(machine.opts().user, "auth");
+        assert_eq!(machine.opts().password, "1234");
+
+        // server rejects new user
+        let mut machine = connected();
+        machine.change_user("other", "", Some("db")).unwrap();
+        machine.take_output().unwrap();
+        machine.feed(&hex!("0f000001ff150423323830303064656e696564"));
+        assert!(matches!(
+            machine.poll_event(),
+            Err(MachineError::Server(err)) if err.code.int() == 1045
+        ));
+        assert_eq!(machine.phase(), Phase::Closed);
+        assert_eq!(machine.opts().user, "auth");
+        assert_eq!(machine.opts().password, "1234");
+        assert_eq!(machine.opts().database, None);
+    }
+}
@@ -0,0 +1,159 @@
+use bytes::{BufMut, BytesMut};
+
+use crate::codec::{
+    get_var_str, put_var_bytes, put_var_str, split_input, Decode, DecodeError, Encode, Int1,
+    VLenInt,
+};
+
+/// `enum_session_state_type` in [mysql_com.h](https://dev.mysql.com/doc/dev/mysql-server/latest/mysql__com_8h.html)
+pub const SESSION_TRACK_SYSTEM_VARIABLES: u8 = 0;
+pub const SESSION_TRACK_SCHEMA: u8 = 1;
+pub const SESSION_TRACK_STATE_CHANGE: u8 = 2;
+pub const SESSION_TRACK_GTIDS: u8 = 3;
+pub const SESSION_TRACK_TRANSACTION_CHARACTERISTICS: u8 = 4;
+pub const SESSION_TRACK_TRANSACTION_STATE: u8 = 5;
+
+/// one entry of session state information in OK packet
+///
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_basic_ok_packet.html#sect_protocol_basic_ok_packet_sessinfo)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionStateChange {
+    /// tracked system variable changed
+    SystemVariable { name: String, value: String },
+    /// current schema changed
+    Schema(String),
+    /// session state changed
+    StateChange(bool),
+    /// GTIDs of transactions committed by last statement
+    Gtids(String),
+    /// statements to restore transaction characteristics
+    TransactionCharacteristics(String),
+    /// transaction state, e.g. `T_______`
+    TransactionState(String),
+    /// type not known by this crate, data is kept as is
+    Unknown { ty: u8, data: Vec<u8> },
+}
+
+impl Decode<BytesMut> for SessionStateChange {
+    fn decode(input: &mut BytesMut) -> Result<Self, DecodeError> {
+        let ty = Int1::decode(input)?.int();
+        let len = VLenInt::decode(input)?.int() as usize;
+        let mut data = split_input(input, len)?;
+        let change = match ty {
+            SESSION_TRACK_SYSTEM_VARIABLES => Self::SystemVariable {
+                name: get_var_str(&mut data)?,
+                value: get_var_str(&mut data)?,
+            },
+            SESSION_TRACK_SCHEMA => Self::Schema(get_var_str(&mut data)?),
+            SESSION_TRACK_STATE_CHANGE => Self::StateChange(get_var_str(&mut data)? == "1"),
+            SESSION_TRACK_GTIDS => {
+                // encoding specification, only 0 is defined
+                Int1::decode(&mut data)?;
+                Self::Gtids(get_var_str(&mut data)?)
+            }
+            SESSION_TRACK_TRANSACTION_CHARACTERISTICS => {
+                Self::TransactionCharacteristics(get_var_str(&mut data)?)
+            }
+            SESSION_TRACK_TRANSACTION_STATE => Self::TransactionState(get_var_str(&mut data)?),
+            _ => Self::Unknown {
+                ty,
+                data: data.to_vec(),
+            },
+        };
+        Ok(change)
+    }
+}
+
+impl Encode for SessionStateChange {
+    fn encode(&self, buf: &mut BytesMut) {
+        let mut data = BytesMut::new();
+        let ty = match self {
+            Self::SystemVariable { name, value } => {
+                put_var_str(name, &mut data);
+                put_var_str(value, &mut data);
+                SESSION_TRACK_SYSTEM_VARIABLES
+            }
+            Self::Schema(schema) => {
+                put_var_str(schema, &mut data);
+                SESSION_TRACK_SCHEMA
+            }
+            Self::StateChange(changed) => {
+                put_var_str(if *changed { "1" } else { "0" }, &mut data);
+                SESSION_TRACK_STATE_CHANGE
+            }
+            Self::Gtids(gtids) => {
+                data.put_u8(0);
+                put_var_str(gtids, &mut data);
+                SESSION_TRACK_GTIDS
+            }
+            Self::TransactionCharacteristics(chars) => {
+                put_var_str(chars, &mut data);
+                SESSION_TRACK_TRANSACTION_CHARACTERISTICS
+            }
+            Self::TransactionState(state) => {
+                put_var_str(state, &mut data);
+                SESSION_TRACK_TRANSACTION_STATE
+            }
+            Self::Unknown { ty, data: raw } => {
+                data.extend_from_slice(raw);
+                *ty
+            }
+        };
+        buf.put_u8(ty);
+        put_var_bytes(&data, buf);
+    }
+}
+
+/// decode lenenc session state information block
+pub fn decode_session_state(input: &mut BytesMut) -> Result<Vec<SessionStateChange>, DecodeError> {
+    let len = VLenInt::decode(input)?.int() as usize;
+    let mut data = split_input(input, len)?;
+    let mut changes = vec![];
+    while !data.is_empty() {
+        changes.push(SessionStateChange::decode(&mut data)?);
+    }
+    Ok(changes)
+}
+
+/// encode session state information block with total length prefix
+pub fn encode_session_state(changes: &[SessionStateChange], buf: &mut BytesMut) {
+    let mut data = BytesMut::new();
+    for change in changes {
+        change.encode(&mut data);
+    }
+    put_var_bytes(&data, buf);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::{OkPacket, ServerStatus};
+
+    #[test]
+    fn test_ok_with_gtids() {
+        let gtids = "3e11fa47-71ca-11e1-9e33-c80aa9429562:23";
+        let mut entry = vec![
+            SESSION_TRACK_GTIDS,
+            gtids.len() as u8 + 2,
+            0,
+            gtids.len() as u8,
+        ];
+        entry.extend_from_slice(gtids.as_bytes());
+        entry.extend_from_slice(&[SESSION_TRACK_SCHEMA, 5, 4]);
+        entry.extend_from_slice(b"test");
+        let mut payload = BytesMut::from(&[0x00, 0x01, 0x00, 0x02, 0x40, 0x00, 0x00, 0x00][..]);
+        payload.extend_from_slice(&[entry.len() as u8]);
+        payload.extend_from_slice(&entry);
+        let ok = OkPacket::decode(&mut payload).unwrap();
+        assert!(ok.status_flags.contains(
+            ServerStatus::SERVER_STATUS_AUTOCOMMIT | ServerStatus::SERVER_SESSION_STATE_CHANGED
+        ));
+        assert_eq!(ok.affected_rows.int(), 1);
+        assert_eq!(ok.gtids(), Some(gtids));
+        assert_eq!(
+            ok.session_state_changes[1],
+            SessionStateChange::Schema("test".into())
+        );
+        assert!(payload.is_empty());
+    }
+}
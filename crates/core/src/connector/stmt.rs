@@ -0,0 +1,381 @@
+use bytes::{BufMut, BytesMut};
+use parse_tool::InputBuf;
+
+use crate::{
+    codec::{Decode, DecodeError, Encode, Int1, Int2, Int4},
+    value::{ColumnFlags, ColumnType, Value, BINARY_CHARSET},
+};
+
+use super::{check_command, ColDef};
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_stmt_prepare.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComStmtPrepare {
+    pub query: String,
+}
+
+impl Encode for ComStmtPrepare {
+    fn encode(&self, buf: &mut BytesMut) {
+        buf.put_u8(0x16);
+        buf.extend_from_slice(self.query.as_bytes());
+    }
+}
+
+impl<I: InputBuf> Decode<I> for ComStmtPrepare {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        check_command(input, 0x16)?;
+        let query = String::from_utf8(input.read_to_end()).map_err(|_| DecodeError::InvalidUtf8)?;
+        Ok(Self { query })
+    }
+}
+
+impl<T: ToString> From<T> for ComStmtPrepare {
+    fn from(value: T) -> Self {
+        Self {
+            query: value.to_string(),
+        }
+    }
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_stmt_prepare.html#sect_protocol_com_stmt_prepare_response_ok)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StmtPrepareOk {
+    pub status: Int1,
+    pub statement_id: Int4,
+    pub num_columns: Int2,
+    pub num_params: Int2,
+    pub warning_count: Int2,
+    /// only presents when `CLIENT_OPTIONAL_RESULTSET_METADATA` is set
+    pub metadata_follows: Option<Int1>,
+}
+
+impl<I: InputBuf> Decode<I> for StmtPrepareOk {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        let status = Int1::decode(input)?;
+        if status.int() != 0x00 {
+            return Err(DecodeError::InvalidData);
+        }
+        let statement_id = Int4::decode(input)?;
+        let num_columns = Int2::decode(input)?;
+        let num_params = Int2::decode(input)?;
+        // reserved
+        input.jump_to(1)?;
+        let warning_count = if input.left() >= 2 {
+            Int2::decode(input)?
+        } else {
+            Int2::from(0)
+        };
+        let metadata_follows = if input.left() > 0 {
+            Some(Int1::decode(input)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            status,
+            statement_id,
+            num_columns,
+            num_params,
+            warning_count,
+            metadata_follows,
+        })
+    }
+}
+
+impl Encode for StmtPrepareOk {
+    fn encode(&self, buf: &mut BytesMut) {
+        self.status.encode(buf);
+        self.statement_id.encode(buf);
+        self.num_columns.encode(buf);
+        self.num_params.encode(buf);
+        buf.put_u8(0);
+        self.warning_count.encode(buf);
+        if let Some(flag) = self.metadata_follows {
+            flag.encode(buf);
+        }
+    }
+}
+
+bitflags::bitflags! {
+    /// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/mysql__com_8h.html)
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+    pub struct CursorType: u8 {
+        const CURSOR_TYPE_NO_CURSOR = 0;
+        const CURSOR_TYPE_READ_ONLY = 1;
+        const CURSOR_TYPE_FOR_UPDATE = 2;
+        const CURSOR_TYPE_SCROLLABLE = 4;
+        /// On when the client will send the parameter count even for 0 parameters
+        const PARAMETER_COUNT_AVAILABLE = 8;
+    }
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_stmt_execute.html)
+///
+/// parameter types are always sent, `CLIENT_QUERY_ATTRIBUTES` is not supported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComStmtExecute {
+    pub statement_id: Int4,
+    pub flags: CursorType,
+    pub params: Vec<Value>,
+}
+
+impl Encode for ComStmtExecute {
+    fn encode(&self, buf: &mut BytesMut) {
+        buf.put_u8(0x17);
+        self.statement_id.encode(buf);
+        buf.put_u8(self.flags.bits());
+        // iteration count, always 1
+        Int4::from(1).encode(buf);
+        if self.params.is_empty() {
+            return;
+        }
+        let mut null_bitmap = vec![0u8; self.params.len().div_ceil(8)];
+        for (idx, param) in self.params.iter().enumerate() {
+            if param.is_null() {
+                null_bitmap[idx / 8] |= 1 << (idx % 8);
+            }
+        }
+        buf.extend_from_slice(&null_bitmap);
+        // new params bound flag
+        buf.put_u8(1);
+        for param in self.params.iter() {
+            let (ty, unsigned) = param.binary_type();
+            buf.put_u8(ty as u8);
+            buf.put_u8(if unsigned { 0x80 } else { 0 });
+        }
+        for param in self.params.iter() {
+            param.encode_binary(buf);
+        }
+    }
+}
+
+impl ComStmtExecute {
+    /// decode with parameter count of prepared statement, which is not sent in packet
+    ///
+    /// parameter types must be bound in packet, blob parameters are decoded as bytes
+    /// and other string parameters are decoded as utf-8 string.
+    pub fn decode_with_params(
+        input: &mut BytesMut,
+        num_params: usize,
+    ) -> Result<Self, DecodeError> {
+        check_command(input, 0x17)?;
+        let statement_id = Int4::decode(input)?;
+        let flags = CursorType::from_bits_retain(input.read_u8_le()?);
+        // iteration count
+        Int4::decode(input)?;
+        let mut params = Vec::with_capacity(num_params);
+        if num_params == 0 {
+            return Ok(Self {
+                statement_id,
+                flags,
+                params,
+            });
+        }
+        let null_bitmap = input.read_vec(num_params.div_ceil(8))?;
+        if input.read_u8_le()? != 1 {
+            // types of previous execution are unknown
+            return Err(DecodeError::InvalidData);
+        }
+        let mut types = Vec::with_capacity(num_params);
+        for _ in 0..num_params {
+            let ty = ColumnType::try_from(input.read_u8_le()?)?;
+            let flags = if input.read_u8_le()? & 0x80 != 0 {
+                ColumnFlags::UNSIGNED
+            } else {
+                ColumnFlags::empty()
+            };
+            types.push((ty, flags));
+        }
+        for (idx, (ty, flags)) in types.into_iter().enumerate() {
+            if null_bitmap[idx / 8] & (1 << (idx % 8)) != 0 {
+                params.push(Value::Null);
+                continue;
+            }
+            let charset = match ty {
+                ColumnType::TinyBlob
+                | ColumnType::MediumBlob
+                | ColumnType::LongBlob
+                | ColumnType::Blob => BINARY_CHARSET,
+                _ => 255,
+            };
+            params.push(Value::from_binary(ty, flags, charset, input)?);
+        }
+        Ok(Self {
+            statement_id,
+            flags,
+            params,
+        })
+    }
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_stmt_fetch.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComStmtFetch {
+    pub statement_id: Int4,
+    pub num_rows: Int4,
+}
+
+impl Encode for ComStmtFetch {
+    fn encode(&self, buf: &mut BytesMut) {
+        buf.put_u8(0x1c);
+        self.statement_id.encode(buf);
+        self.num_rows.encode(buf);
+    }
+}
+
+impl<I: InputBuf> Decode<I> for ComStmtFetch {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        check_command(input, 0x1c)?;
+        let statement_id = Int4::decode(input)?;
+        let num_rows = Int4::decode(input)?;
+        Ok(Self {
+            statement_id,
+            num_rows,
+        })
+    }
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_stmt_reset.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComStmtReset {
+    pub statement_id: Int4,
+}
+
+impl Encode for ComStmtReset {
+    fn encode(&self, buf: &mut BytesMut) {
+        buf.put_u8(0x1a);
+        self.statement_id.encode(buf);
+    }
+}
+
+impl<I: InputBuf> Decode<I> for ComStmtReset {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        check_command(input, 0x1a)?;
+        let statement_id = Int4::decode(input)?;
+        Ok(Self { statement_id })
+    }
+}
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_stmt_close.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComStmtClose {
+    pub statement_id: Int4,
+}
+
+impl Encode for ComStmtClose {
+    fn encode(&self, buf: &mut BytesMut) {
+        buf.put_u8(0x19);
+        self.statement_id.encode(buf);
+    }
+}
+
+impl<I: InputBuf> Decode<I> for ComStmtClose {
+    fn decode(input: &mut I) -> Result<Self, DecodeError> {
+        check_command(input, 0x19)?;
+        let statement_id = Int4::decode(input)?;
+        Ok(Self { statement_id })
+    }
+}
+
+/// decode binary protocol row, column definitions are required to decode values
+///
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_binary_resultset.html#sect_protocol_binary_resultset_row)
+pub fn decode_binary_row(
+    input: &mut BytesMut,
+    columns: &[ColDef],
+) -> Result<Vec<Value>, DecodeError> {
+    if Int1::decode(input)?.int() != 0x00 {
+        return Err(DecodeError::InvalidData);
+    }
+    // first 2 bits are reserved
+    let null_bitmap = input.read_vec((columns.len() + 7 + 2) / 8)?;
+    let mut values = Vec::with_capacity(columns.len());
+    for (idx, col) in columns.iter().enumerate() {
+        let bit = idx + 2;
+        if null_bitmap[bit / 8] & (1 << (bit % 8)) != 0 {
+            values.push(Value::Null);
+            continue;
+        }
+        values.push(Value::from_binary(
+            col.column_type()?,
+            col.column_flags(),
+            col.charset.int(),
+            input,
+        )?);
+    }
+    Ok(values)
+}
+
+/// encode binary protocol row, values must match column types
+pub fn encode_binary_row(values: &[Value], buf: &mut BytesMut) {
+    buf.put_u8(0x00);
+    let mut null_bitmap = vec![0u8; (values.len() + 7 + 2) / 8];
+    for (idx, value) in values.iter().enumerate() {
+        if value.is_null() {
+            let bit = idx + 2;
+            null_bitmap[bit / 8] |= 1 << (bit % 8);
+        }
+    }
+    buf.extend_from_slice(&null_bitmap);
+    for value in values {
+        value.encode_binary(buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_execute() {
+        let execute = ComStmtExecute {
+            statement_id: Int4::from(1),
+            flags: CursorType::CURSOR_TYPE_NO_CURSOR,
+            params: vec![Value::Int(3), Value::Null, Value::String("a".into())],
+        };
+        let mut buf = BytesMut::new();
+        execute.encode(&mut buf);
+        let mut expect = vec![0x17, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0b010, 1];
+        expect.extend_from_slice(&[8, 0, 6, 0, 253, 0]);
+        expect.extend_from_slice(&[3, 0, 0, 0, 0, 0, 0, 0, 1, b'a']);
+        assert_eq!(&buf[..], &expect[..]);
+        let decoded = ComStmtExecute::decode_with_params(&mut buf, 3).unwrap();
+        assert_eq!(decoded, execute);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_prepare_ok() {
+        let mut buf = BytesMut::from(&[0, 1, 0, 0, 0, 2, 0, 1, 0, 0, 0, 0][..]);
+        let ok = StmtPrepareOk::decode(&mut buf).unwrap();
+        assert_eq!(ok.statement_id.int(), 1);
+        assert_eq!(ok.num_columns.int(), 2);
+        assert_eq!(ok.num_params.int(), 1);
+        assert!(ok.metadata_follows.is_none());
+        let mut encoded = BytesMut::new();
+        ok.encode(&mut encoded);
+        assert_eq!(StmtPrepareOk::decode(&mut encoded).unwrap(), ok);
+    }
+
+    #[test]
+    fn test_binary_row() {
+        let mut buf = BytesMut::new();
+        let values = vec![Value::Null, Value::Int(-1), Value::String("foo".into())];
+        encode_binary_row(&values, &mut buf);
+        let col = |ty: u8| ColDef {
+            catalog: "def".into(),
+            schema: String::new(),
+            table: String::new(),
+            original_table: String::new(),
+            name: String::new(),
+            original_name: String::new(),
+            length_of_fixed_length_fields: crate::codec::VLenInt::new(0x0c),
+            charset: Int2::from(255),
+            column_length: Int4::from(0),
+            ty: Int1::from(ty),
+            flags: Int2::from(0),
+            decimals: Int1::from(0),
+        };
+        let columns = [col(8), col(8), col(253)];
+        assert_eq!(decode_binary_row(&mut buf, &columns).unwrap(), values);
+    }
+}
@@ -1,6 +1,15 @@
 pub mod codec;
 pub mod connector;
 pub mod binlog;
+pub mod client;
+pub mod value;
+pub mod server;
+pub mod backup;
+pub mod checkpoint;
+pub mod relay;
+pub mod proxy;
+#[cfg(test)]
+mod test_util;
 
 #[allow(unused_macros)]
 macro_rules! hex {
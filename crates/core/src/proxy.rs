@@ -0,0 +1,931 @@
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    io::{self, BufReader, BufWriter, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use bytes::BytesMut;
+use thiserror::Error;
+
+use crate::{
+    codec::{Decode, DecodeError, Int4, VLenInt},
+    connector::{
+        encode_packet, Capabilities, Command, EofPacket, ErrPacket, HandshakeResponse41,
+        HandshakeV10, OkPacket, ServerStatus, SessionStateChange, StmtPrepareOk, MAX_PAYLOAD_LEN,
+    },
+};
+
+/// length of SSL request sent by client before TLS handshake
+const SSL_REQUEST_LEN: usize = 32;
+
+const COM_FIELD_LIST: u8 = 0x04;
+
+const COM_STMT_SEND_LONG_DATA: u8 = 0x18;
+
+#[derive(Debug, Error)]
+pub enum ProxyError {
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    #[error("decode error: {0}")]
+    Decode(#[from] DecodeError),
+}
+
+/// events of proxied sessions
+#[derive(Debug, Clone)]
+pub enum ProxyEvent {
+    /// connection phase is done, `error` is set when authentication failed
+    Connected {
+        session: u64,
+        client: SocketAddr,
+        user: String,
+        database: Option<String>,
+        error: Option<ErrPacket>,
+    },
+    /// client switched to TLS, following traffic is relayed without decoding
+    Tls {
+        session: u64,
+        client: SocketAddr,
+    },
+    Command(CommandLog),
+    Disconnected {
+        session: u64,
+        duration: Duration,
+        commands: u64,
+    },
+}
+
+/// one command and its response
+#[derive(Debug, Clone)]
+pub struct CommandLog {
+    pub session: u64,
+    pub user: String,
+    pub database: Option<String>,
+    /// command name, like `COM_QUERY`
+    pub command: &'static str,
+    /// SQL of query or executed prepared statement
+    pub sql: Option<String>,
+    pub start: SystemTime,
+    pub latency: Duration,
+    /// rows of all result sets, or events of binlog dump
+    pub rows: u64,
+    pub affected_rows: u64,
+    pub error: Option<ErrPacket>,
+}
+
+impl ProxyEvent {
+    /// one line JSON object
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{");
+        match self {
+            ProxyEvent::Connected {
+                session,
+                client,
+                user,
+                database,
+                error,
+            } => {
+                let _ = write!(
+                    out,
+                    r#""event":"connect","session":{session},"client":"{client}","user":{},"database":{}"#,
+                    json_str(user),
+                    json_opt(database.as_deref())
+                );
+                write_error(&mut out, error.as_ref());
+            }
+            ProxyEvent::Tls { session, client } => {
+                let _ = write!(
+                    out,
+                    r#""event":"tls","session":{session},"client":"{client}""#
+                );
+            }
+            ProxyEvent::Command(log) => {
+                let start = log
+                    .start
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_micros();
+                let _ = write!(
+                    out,
+                    r#""event":"command","session":{},"user":{},"database":{},"command":"{}","sql":{},"start_us":{start},"latency_us":{},"rows":{},"affected_rows":{}"#,
+                    log.session,
+                    json_str(&log.user),
+                    json_opt(log.database.as_deref()),
+                    log.command,
+                    json_opt(log.sql.as_deref()),
+                    log.latency.as_micros(),
+                    log.rows,
+                    log.affected_rows,
+                );
+                write_error(&mut out, log.error.as_ref());
+            }
+            ProxyEvent::Disconnected {
+                session,
+                duration,
+                commands,
+            } => {
+                let _ = write!(
+                    out,
+                    r#""event":"disconnect","session":{session},"duration_us":{},"commands":{commands}"#,
+                    duration.as_micros()
+                );
+            }
+        }
+        out.push('}');
+        out
+    }
+}
+
+fn write_error(out: &mut String, error: Option<&ErrPacket>) {
+    if let Some(err) = error {
+        let _ = write!(
+            out,
+            r#","error":{{"code":{},"state":{},"message":{}}}"#,
+            err.code.int(),
+            json_str(&err.sql_state),
+            json_str(&err.error_msg)
+        );
+    }
+}
+
+fn json_opt(s: Option<&str>) -> String {
+    s.map(json_str).unwrap_or_else(|| "null".into())
+}
+
+fn json_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// receives events of proxied sessions, called from session threads
+pub trait ProxyLogger: Send + Sync + 'static {
+    fn log(&self, event: ProxyEvent);
+}
+
+impl<F: Fn(ProxyEvent) + Send + Sync + 'static> ProxyLogger for F {
+    fn log(&self, event: ProxyEvent) {
+        self(event)
+    }
+}
+
+/// options of [`Proxy`]
+#[derive(Debug, Clone)]
+pub struct ProxyOpts {
+    /// address of MySQL server
+    pub upstream: String,
+    /// relay TLS end to end when client asks for it, commands of TLS session are not
+    /// logged; TLS is not offered to clients when disabled
+    pub tls_passthrough: bool,
+}
+
+impl ProxyOpts {
+    pub fn new(upstream: impl Into<String>) -> Self {
+        Self {
+            upstream: upstream.into(),
+            tls_passthrough: true,
+        }
+    }
+}
+
+/// transparent proxy between MySQL clients and server, every session is decoded and
+/// reported to [`ProxyLogger`]
+///
+/// compression, query attributes and optional result set metadata are hidden from client
+/// so that traffic can be decoded
+#[derive(Debug)]
+pub struct Proxy<L> {
+    opts: ProxyOpts,
+    logger: L,
+    next_session: AtomicU64,
+}
+
+impl<L: ProxyLogger> Proxy<L> {
+    pub fn new(opts: ProxyOpts, logger: L) -> Self {
+        Self {
+            opts,
+            logger,
+            next_session: AtomicU64::new(1),
+        }
+    }
+
+    /// bind address and serve in background thread, return bound address
+    pub fn spawn(self, addr: impl ToSocketAddrs) -> io::Result<SocketAddr> {
+        let listener = TcpListener::bind(addr)?;
+        let local = listener.local_addr()?;
+        let this = Arc::new(self);
+        thread::spawn(move || this.run(listener));
+        Ok(local)
+    }
+
+    /// accept connections forever, each session is served in its own thread
+    pub fn run(self: Arc<Self>, listener: TcpListener) {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let this = self.clone();
+            thread::spawn(move || this.serve(stream));
+        }
+    }
+
+    /// proxy one client connection until either side closes it
+    pub fn serve(&self, client: TcpStream) -> Result<(), ProxyError> {
+        let session = self.next_session.fetch_add(1, Ordering::Relaxed);
+        let client_addr = client.peer_addr()?;
+        let server = TcpStream::connect(&self.opts.upstream)?;
+        client.set_nodelay(true)?;
+        server.set_nodelay(true)?;
+        let mut session = Session {
+            id: session,
+            client: Peer::new(client)?,
+            server: Peer::new(server)?,
+            caps: Capabilities::empty(),
+            user: String::new(),
+            database: None,
+            stmts: HashMap::new(),
+            commands: 0,
+        };
+        let start = Instant::now();
+        let result = self.proxy(&mut session, client_addr);
+        self.logger.log(ProxyEvent::Disconnected {
+            session: session.id,
+            duration: start.elapsed(),
+            commands: session.commands,
+        });
+        match result {
+            Err(ProxyError::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(()),
+            result => result,
+        }
+    }
+
+    fn proxy(&self, session: &mut Session, client_addr: SocketAddr) -> Result<(), ProxyError> {
+        let handshake = session.server.read()?;
+        if handshake.payload.first() == Some(&0xff) {
+            session.client.write(&handshake.raw)?;
+            return Ok(session.client.flush()?);
+        }
+        let mut hs = HandshakeV10::decode(&mut handshake.payload.clone())?;
+        hs.caps.remove(
+            Capabilities::CLIENT_COMPRESS
+                | Capabilities::CLIENT_ZSTD_COMPRESSION_ALGORITHM
+                | Capabilities::CLIENT_QUERY_ATTRIBUTES
+                | Capabilities::CLIENT_OPTIONAL_RESULTSET_METADATA,
+        );
+        if !self.opts.tls_passthrough {
+            hs.caps.remove(Capabilities::CLIENT_SSL);
+        }
+        let mut buf = BytesMut::new();
+        encode_packet(handshake.seq_id, &hs, &mut buf);
+        session.client.write(&buf)?;
+        session.client.flush()?;
+
+        let resp = session.forward_to_server()?;
+        session.server.flush()?;
+        let caps =
+            Capabilities::from_bits_retain(Int4::decode(&mut resp.clone()).map(|c| c.int())?);
+        if resp.len() == SSL_REQUEST_LEN && caps.contains(Capabilities::CLIENT_SSL) {
+            self.logger.log(ProxyEvent::Tls {
+                session: session.id,
+                client: client_addr,
+            });
+            return session.passthrough();
+        }
+        let resp = HandshakeResponse41::decode(&mut resp.clone())?;
+        session.caps = resp.caps;
+        session.user = resp.user_name;
+        session.database = resp.database;
+        let error = session.auth()?;
+        let failed = error.is_some();
+        self.logger.log(ProxyEvent::Connected {
+            session: session.id,
+            client: client_addr,
+            user: session.user.clone(),
+            database: session.database.clone(),
+            error,
+        });
+        if failed {
+            return Ok(());
+        }
+        while let Some(log) = session.command()? {
+            self.logger.log(ProxyEvent::Command(log));
+        }
+        Ok(())
+    }
+}
+
+/// logical packet and its raw frames
+struct RawPacket {
+    seq_id: u8,
+    raw: Vec<u8>,
+    payload: BytesMut,
+}
+
+struct Peer {
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+}
+
+impl Peer {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: BufWriter::new(stream),
+        })
+    }
+
+    fn read(&mut self) -> io::Result<RawPacket> {
+        let mut raw = vec![];
+        let mut payload = BytesMut::new();
+        loop {
+            let mut header = [0; 4];
+            self.reader.read_exact(&mut header)?;
+            let len = u32::from_le_bytes([header[0], header[1], header[2], 0]) as usize;
+            let start = payload.len();
+            payload.resize(start + len, 0);
+            self.reader.read_exact(&mut payload[start..])?;
+            raw.extend_from_slice(&header);
+            raw.extend_from_slice(&payload[start..]);
+            if len < MAX_PAYLOAD_LEN {
+                return Ok(RawPacket {
+                    seq_id: header[3],
+                    raw,
+                    payload,
+                });
+            }
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.writer.write_all(data)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+struct Session {
+    id: u64,
+    client: Peer,
+    server: Peer,
+    caps: Capabilities,
+    user: String,
+    database: Option<String>,
+    /// SQL of prepared statements
+    stmts: HashMap<u32, String>,
+    commands: u64,
+}
+
+impl Session {
+    fn forward_to_server(&mut self) -> io::Result<BytesMut> {
+        let packet = self.client.read()?;
+        self.server.write(&packet.raw)?;
+        Ok(packet.payload)
+    }
+
+    fn forward_to_client(&mut self) -> io::Result<BytesMut> {
+        let packet = self.server.read()?;
+        self.client.write(&packet.raw)?;
+        Ok(packet.payload)
+    }
+
+    fn deprecate_eof(&self) -> bool {
+        self.caps.contains(Capabilities::CLIENT_DEPRECATE_EOF)
+    }
+
+    /// whether packet ends column definitions or rows
+    fn is_end(&self, payload: &[u8]) -> bool {
+        if self.deprecate_eof() {
+            payload.first() == Some(&0xfe) && payload.len() < MAX_PAYLOAD_LEN
+        } else {
+            EofPacket::is_eof(payload)
+        }
+    }
+
+    /// relay auth exchange until server accepts or rejects
+    fn auth(&mut self) -> Result<Option<ErrPacket>, ProxyError> {
+        loop {
+            let mut payload = self.forward_to_client()?;
+            self.client.flush()?;
+            match payload.first() {
+                Some(0x00) => return Ok(None),
+                Some(0xff) => return Ok(Some(ErrPacket::decode(&mut payload)?)),
+                // caching_sha2_password fast auth success, OK packet follows
+                Some(0x01) if payload[..] == [0x01, 0x03] => {}
+                _ => {
+                    self.forward_to_server()?;
+                    self.server.flush()?;
+                }
+            }
+        }
+    }
+
+    /// relay next command and its response, `None` when client quits
+    fn command(&mut self) -> Result<Option<CommandLog>, ProxyError> {
+        let payload = match self.forward_to_server() {
+            Ok(payload) => payload,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        self.server.flush()?;
+        self.commands += 1;
+        let mut log = CommandLog {
+            session: self.id,
+            user: self.user.clone(),
+            database: self.database.clone(),
+            command: "COM_UNKNOWN",
+            sql: None,
+            start: SystemTime::now(),
+            latency: Duration::ZERO,
+            rows: 0,
+            affected_rows: 0,
+            error: None,
+        };
+        let instant = Instant::now();
+        let cmd = Command::decode_with_caps(&mut payload.clone(), self.caps)
+            .unwrap_or_else(|_| Command::Unknown(payload.clone()));
+        log.command = command_name(&cmd);
+        match cmd {
+            Command::Quit(_) => return Ok(None),
+            // commands without response
+            Command::StmtClose(close) => {
+                self.stmts.remove(&close.statement_id.int());
+            }
+            Command::Unknown(raw) if raw.first() == Some(&COM_STMT_SEND_LONG_DATA) => {
+                let id = raw
+                    .get(1..5)
+                    .and_then(|id| Int4::decode(&mut BytesMut::from(id)).ok());
+                log.sql = id.and_then(|id| self.stmts.get(&id.int()).cloned());
+            }
+            Command::Query(query) => {
+                log.sql = Some(query.query);
+                self.result_sets(&mut log)?;
+            }
+            Command::StmtPrepare(prepare) => {
+                if let Some(id) = self.prepare_response(&mut log)? {
+                    self.stmts.insert(id, prepare.query.clone());
+                }
+                log.sql = Some(prepare.query);
+            }
+            Command::StmtExecute(raw) => {
+                let id = raw
+                    .get(1..5)
+                    .and_then(|id| Int4::decode(&mut BytesMut::from(id)).ok());
+                log.sql = id.and_then(|id| self.stmts.get(&id.int()).cloned());
+                self.result_sets(&mut log)?;
+            }
+            Command::StmtFetch(fetch) => {
+                log.sql = self.stmts.get(&fetch.statement_id.int()).cloned();
+                self.rows(&mut log)?;
+            }
+            Command::BinlogDump(_) | Command::BinlogDumpGtid(_) => loop {
+                let mut payload = self.forward_to_client()?;
+                self.client.flush()?;
+                match payload.first() {
+                    Some(0xff) => {
+                        log.error = Some(ErrPacket::decode(&mut payload)?);
+                        break;
+                    }
+                    Some(0xfe) if payload.len() < 9 => break,
+                    _ => log.rows += 1,
+                }
+            },
+            Command::ChangeUser(change) => {
+                self.user = change.user.clone();
+                self.database = Some(change.database.clone()).filter(|db| !db.is_empty());
+                log.user = change.user;
+                log.database = self.database.clone();
+                log.error = self.auth()?;
+            }
+            // column definitions of table, not counted as rows
+            Command::Unknown(raw) if raw.first() == Some(&COM_FIELD_LIST) => loop {
+                let mut payload = self.forward_to_client()?;
+                if payload.first() == Some(&0xff) {
+                    log.error = Some(ErrPacket::decode(&mut payload)?);
+                    break;
+                }
+                if self.is_end(&payload) {
+                    break;
+                }
+            },
+            cmd => {
+                let mut payload = self.forward_to_client()?;
+                match payload.first() {
+                    Some(0xff) => log.error = Some(ErrPacket::decode(&mut payload)?),
+                    Some(0x00) if !matches!(cmd, Command::Statistics(_)) => {
+                        let ok = OkPacket::decode(&mut payload)?;
+                        self.track_schema(&ok);
+                        log.affected_rows += ok.affected_rows.int();
+                    }
+                    _ => {}
+                }
+                if let Command::InitDb(init) = cmd {
+                    if log.error.is_none() {
+                        self.database = Some(init.schema);
+                    }
+                }
+            }
+        }
+        self.client.flush()?;
+        log.latency = instant.elapsed();
+        Ok(Some(log))
+    }
+
+    fn track_schema(&mut self, ok: &OkPacket) {
+        for change in ok.session_state_changes.iter() {
+            if let SessionStateChange::Schema(schema) = change {
+                self.database = Some(schema.clone());
+            }
+        }
+    }
+
+    /// relay response of COM_QUERY or COM_STMT_EXECUTE, including multiple result sets
+    fn result_sets(&mut self, log: &mut CommandLog) -> Result<(), ProxyError> {
+        loop {
+            let mut payload = self.forward_to_client()?;
+            let status = match payload.first() {
+                Some(0x00) => {
+                    let ok = OkPacket::decode(&mut payload)?;
+                    self.track_schema(&ok);
+                    log.affected_rows += ok.affected_rows.int();
+                    ok.status_flags
+                }
+                Some(0xff) => {
+                    log.error = Some(ErrPacket::decode(&mut payload)?);
+                    return Ok(());
+                }
+                // LOCAL INFILE request, file content ends with an empty packet
+                Some(0xfb) => {
+                    self.client.flush()?;
+                    while !self.forward_to_server()?.is_empty() {}
+                    self.server.flush()?;
+                    continue;
+                }
+                _ => {
+                    let columns = VLenInt::decode(&mut payload)?.int();
+                    for _ in 0..columns {
+                        self.forward_to_client()?;
+                    }
+                    if !self.deprecate_eof() {
+                        let mut eof = self.forward_to_client()?;
+                        let eof = EofPacket::decode(&mut eof)?;
+                        // rows are fetched by COM_STMT_FETCH
+                        if eof
+                            .status_flags
+                            .contains(ServerStatus::SERVER_STATUS_CURSOR_EXISTS)
+                        {
+                            return Ok(());
+                        }
+                    }
+                    match self.rows(log)? {
+                        Some(status) => status,
+                        None => return Ok(()),
+                    }
+                }
+            };
+            if !status.contains(ServerStatus::SERVER_MORE_RESULTS_EXISTS) {
+                return Ok(());
+            }
+        }
+    }
+
+    /// relay rows until end of result set, return status of terminating packet or `None`
+    /// if server sent an error
+    fn rows(&mut self, log: &mut CommandLog) -> Result<Option<ServerStatus>, ProxyError> {
+        loop {
+            let mut payload = self.forward_to_client()?;
+            if self.is_end(&payload) {
+                let status = if self.deprecate_eof() {
+                    OkPacket::decode(&mut payload)?.status_flags
+                } else {
+                    EofPacket::decode(&mut payload)?.status_flags
+                };
+                return Ok(Some(status));
+            }
+            if payload.first() == Some(&0xff) {
+                log.error = Some(ErrPacket::decode(&mut payload)?);
+                return Ok(None);
+            }
+            log.rows += 1;
+        }
+    }
+
+    /// relay response of COM_STMT_PREPARE, return statement id
+    fn prepare_response(&mut self, log: &mut CommandLog) -> Result<Option<u32>, ProxyError> {
+        let mut payload = self.forward_to_client()?;
+        if payload.first() == Some(&0xff) {
+            log.error = Some(ErrPacket::decode(&mut payload)?);
+            return Ok(None);
+        }
+        let ok = StmtPrepareOk::decode(&mut payload)?;
+        for count in [ok.num_params.int(), ok.num_columns.int()] {
+            for _ in 0..count {
+                self.forward_to_client()?;
+            }
+            if count > 0 && !self.deprecate_eof() {
+                self.forward_to_client()?;
+            }
+        }
+        Ok(Some(ok.statement_id.int()))
+    }
+
+    /// relay raw bytes in both directions, used after TLS is negotiated
+    fn passthrough(&mut self) -> Result<(), ProxyError> {
+        // bytes already buffered belong to TLS handshake
+        let pending = self.client.reader.buffer().to_vec();
+        self.server.write(&pending)?;
+        self.server.flush()?;
+        let pending = self.server.reader.buffer().to_vec();
+        self.client.write(&pending)?;
+        self.client.flush()?;
+        let client = self.client.writer.get_ref();
+        let server = self.server.writer.get_ref();
+        let (mut client_reader, client_writer) = (client.try_clone()?, client.try_clone()?);
+        let (mut server_reader, server_writer) = (server.try_clone()?, server.try_clone()?);
+        let upload = thread::spawn(move || pump(&mut client_reader, server_writer));
+        let download = pump(&mut server_reader, client_writer);
+        let upload = upload.join().unwrap_or(Ok(()));
+        download?;
+        Ok(upload?)
+    }
+}
+
+fn pump(reader: &mut TcpStream, mut writer: TcpStream) -> io::Result<()> {
+    let result = io::copy(reader, &mut writer);
+    let _ = writer.shutdown(Shutdown::Write);
+    result.map(|_| ())
+}
+
+fn command_name(cmd: &Command) -> &'static str {
+    match cmd {
+        Command::Quit(_) => "COM_QUIT",
+        Command::InitDb(_) => "COM_INIT_DB",
+        Command::Query(_) => "COM_QUERY",
+        Command::Statistics(_) => "COM_STATISTICS",
+        Command::ProcessKill(_) => "COM_PROCESS_KILL",
+        Command::Ping(_) => "COM_PING",
+        Command::ChangeUser(_) => "COM_CHANGE_USER",
+        Command::BinlogDump(_) => "COM_BINLOG_DUMP",
+        Command::BinlogDumpGtid(_) => "COM_BINLOG_DUMP_GTID",
+        Command::StmtPrepare(_) => "COM_STMT_PREPARE",
+        Command::StmtExecute(_) => "COM_STMT_EXECUTE",
+        Command::StmtClose(_) => "COM_STMT_CLOSE",
+        Command::StmtReset(_) => "COM_STMT_RESET",
+        Command::SetOption(_) => "COM_SET_OPTION",
+        Command::StmtFetch(_) => "COM_STMT_FETCH",
+        Command::ResetConnection(_) => "COM_RESET_CONNECTION",
+        Command::Unknown(raw) if raw.first() == Some(&COM_FIELD_LIST) => "COM_FIELD_LIST",
+        Command::Unknown(raw) if raw.first() == Some(&COM_STMT_SEND_LONG_DATA) => {
+            "COM_STMT_SEND_LONG_DATA"
+        }
+        Command::Unknown(_) => "COM_UNKNOWN",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{mpsc, Mutex};
+
+    use super::*;
+    use crate::{
+        client::{Client, ClientError, TcpConnector},
+        codec::{Encode, Int1, Int2},
+        connector::{ClientOpts, ComBinLogDump, BINLOG_DUMP_NON_BLOCK},
+        server::{col_def, FakeSource, FakeSourceOpts, ServerConn},
+        test_util::temp_dir,
+        value::ColumnType,
+    };
+
+    struct RawCommand(&'static [u8]);
+
+    impl Encode for RawCommand {
+        fn encode(&self, buf: &mut BytesMut) {
+            buf.extend_from_slice(self.0);
+        }
+    }
+
+    /// upstream answering every command by `respond` after handshake, receiver gets a
+    /// message once proxy closes the connection
+    fn scripted_upstream(
+        respond: impl Fn(&mut ServerConn<TcpStream>, BytesMut) + Send + 'static,
+    ) -> (SocketAddr, mpsc::Receiver<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, closed) = mpsc::channel();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = ServerConn::new(stream);
+            let source = FakeSource::new(FakeSourceOpts::new("."));
+            source.handshake(&mut conn).unwrap();
+            while let Ok(payload) = conn.read_packet() {
+                respond(&mut conn, payload);
+            }
+            tx.send(()).unwrap();
+        });
+        (addr, closed)
+    }
+
+    fn logger() -> (impl ProxyLogger, Arc<Mutex<Vec<ProxyEvent>>>) {
+        let events = Arc::new(Mutex::new(vec![]));
+        let logger = {
+            let events = events.clone();
+            move |event: ProxyEvent| events.lock().unwrap().push(event)
+        };
+        (logger, events)
+    }
+
+    fn wait_disconnected(events: &Mutex<Vec<ProxyEvent>>) {
+        let start = Instant::now();
+        while !matches!(
+            events.lock().unwrap().last(),
+            Some(ProxyEvent::Disconnected { .. })
+        ) {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn connect(addr: SocketAddr) -> Client<TcpStream> {
+        let opts = ClientOpts {
+            user: "repl".into(),
+            ..FakeSource::client_opts(addr)
+        };
+        Client::connect_with(&TcpConnector, opts).unwrap()
+    }
+
+    #[test]
+    fn test_proxy() {
+        let dir = temp_dir("proxy");
+        let file = include_bytes!("../../../tests/events/02_query/log.bin");
+        std::fs::write(dir.join("mysql-bin.000001"), file).unwrap();
+        let upstream = FakeSource::new(FakeSourceOpts::new(&dir))
+            .spawn("127.0.0.1:0")
+            .unwrap();
+
+        let (logger, events) = logger();
+        let proxy = Proxy::new(ProxyOpts::new(upstream.to_string()), logger);
+        let addr = proxy.spawn("127.0.0.1:0").unwrap();
+        let mut client = connect(addr);
+        let rows = client
+            .query("SHOW MASTER STATUS")
+            .unwrap()
+            .collect_rows()
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        let err = client.query("DROP TABLE t").unwrap_err();
+        assert!(matches!(err, ClientError::Server(e) if e.code.int() == 1235));
+        client.ping().unwrap();
+        let dump = ComBinLogDump {
+            pos: Int4::from(4),
+            flags: Int2::from(BINLOG_DUMP_NON_BLOCK),
+            server_id: Int4::from(100),
+            filename: "mysql-bin.000001".into(),
+        };
+        client.binlog_dump(&dump).unwrap();
+        while client.recv_binlog().unwrap().is_some() {}
+        client.quit().unwrap();
+
+        wait_disconnected(&events);
+        let events = events.lock().unwrap();
+        assert!(
+            matches!(&events[0], ProxyEvent::Connected { user, error: None, .. } if user == "repl")
+        );
+        let logs: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                ProxyEvent::Command(log) => Some(log),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(logs.len(), 4);
+        assert_eq!(logs[0].command, "COM_QUERY");
+        assert_eq!(logs[0].sql.as_deref(), Some("SHOW MASTER STATUS"));
+        assert_eq!(logs[0].rows, 1);
+        assert_eq!(logs[1].error.as_ref().map(|e| e.code.int()), Some(1235));
+        assert_eq!(logs[2].command, "COM_PING");
+        assert_eq!(logs[3].command, "COM_BINLOG_DUMP");
+        assert_eq!(logs[3].rows, 8);
+        assert!(events[2].to_json().contains(r#""error":{"code":1235"#));
+        assert!(matches!(
+            events.last(),
+            Some(ProxyEvent::Disconnected { commands: 5, .. })
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_proxy_responses() {
+        let (upstream, closed) = scripted_upstream(|conn, payload| match payload[0] {
+            // OK then a result set
+            0x03 => {
+                let ok = OkPacket {
+                    header: Int1::from(0),
+                    affected_rows: VLenInt::new(2),
+                    last_insert_id: VLenInt::new(0),
+                    status_flags: ServerStatus::SERVER_STATUS_AUTOCOMMIT
+                        | ServerStatus::SERVER_MORE_RESULTS_EXISTS,
+                    warnings: Int2::from(0),
+                    info: String::new(),
+                    session_state_changes: vec![],
+                };
+                conn.write_packet(&ok).unwrap();
+                let rows = [vec![Some("1".into())], vec![Some("2".into())]];
+                conn.write_result_set(&[("id", ColumnType::LongLong)], &rows)
+                    .unwrap();
+            }
+            COM_FIELD_LIST => {
+                conn.write_packet(&col_def("id", ColumnType::LongLong))
+                    .unwrap();
+                conn.write_packet(&col_def("name", ColumnType::VarString))
+                    .unwrap();
+                conn.write_eof().unwrap();
+            }
+            // COM_STMT_PREPARE
+            0x16 => conn
+                .write_err(1146, "42S02", "Table 'test.t' doesn't exist")
+                .unwrap(),
+            // COM_PING
+            0x0e => conn.write_ok(0).unwrap(),
+            _ => {}
+        });
+        let (logger, events) = logger();
+        let proxy = Proxy::new(ProxyOpts::new(upstream.to_string()), logger);
+        let addr = proxy.spawn("127.0.0.1:0").unwrap();
+        let mut client = connect(addr);
+        client
+            .stream()
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+
+        let mut result = client
+            .query("UPDATE t SET a = 1; SELECT id FROM t")
+            .unwrap();
+        assert_eq!(result.affected_rows(), 2);
+        assert!(result.next_result_set().unwrap());
+        assert_eq!(result.collect_rows().unwrap().len(), 2);
+        drop(result);
+        client.command(&RawCommand(b"\x04t\x00")).unwrap();
+        for _ in 0..3 {
+            client.read_response().unwrap();
+        }
+        let err = client.prepare("SELECT * FROM t").unwrap_err();
+        assert!(matches!(err, ClientError::Server(e) if e.code.int() == 1146));
+        // statement id(4) + param id(2) + data, server sends no response
+        client
+            .command(&RawCommand(b"\x18\x01\x00\x00\x00\x00\x00data"))
+            .unwrap();
+        client.ping().unwrap();
+        // disconnect without COM_QUIT
+        drop(client);
+        closed.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        wait_disconnected(&events);
+        let events = events.lock().unwrap();
+        let logs: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                ProxyEvent::Command(log) => Some(log),
+                _ => None,
+            })
+            .collect();
+        let commands: Vec<_> = logs.iter().map(|log| log.command).collect();
+        assert_eq!(
+            commands,
+            [
+                "COM_QUERY",
+                "COM_FIELD_LIST",
+                "COM_STMT_PREPARE",
+                "COM_STMT_SEND_LONG_DATA",
+                "COM_PING"
+            ]
+        );
+        assert_eq!((logs[0].affected_rows, logs[0].rows), (2, 2));
+        assert_eq!(logs[1].rows, 0);
+        assert_eq!(logs[2].error.as_ref().map(|e| e.code.int()), Some(1146));
+        assert!(matches!(
+            events.last(),
+            Some(ProxyEvent::Disconnected { commands: 5, .. })
+        ));
+    }
+}
@@ -0,0 +1,345 @@
+use std::{
+    fs, io,
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use thiserror::Error;
+
+use crate::{
+    backup::{Backup, BackupError, BackupOpts},
+    client::{Client, ClientError, TcpConnector},
+    connector::ClientOpts,
+    server::{FakeSource, FakeSourceOpts},
+};
+
+/// variables of upstream reported to downstream replicas
+const UPSTREAM_VARIABLES: [&str; 3] = ["binlog_checksum", "binlog_format", "gtid_mode"];
+
+#[derive(Debug, Error)]
+pub enum RelayError {
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Client(#[from] ClientError),
+    #[error(transparent)]
+    Backup(#[from] BackupError),
+}
+
+impl RelayError {
+    /// connection lost or io error, which is retried by replication thread
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Io(_) | Self::Client(ClientError::Io(_)) => true,
+            Self::Backup(e) => e.is_transient(),
+            _ => false,
+        }
+    }
+}
+
+/// options of [`Relay`]
+#[derive(Debug, Clone)]
+pub struct RelayOpts {
+    pub upstream: ClientOpts,
+    /// downstream side, binlog files are written into `binlog_dir` and `server_id`
+    /// is used to register on upstream
+    pub source: FakeSourceOpts,
+    /// first binlog file to fetch when local directory is empty, first file listed by
+    /// `SHOW BINARY LOGS` of upstream by default
+    pub start_file: Option<String>,
+    /// wait before reconnecting after upstream connection is lost
+    pub reconnect_interval: Duration,
+}
+
+impl RelayOpts {
+    pub fn new(upstream: ClientOpts, binlog_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            upstream,
+            source: FakeSourceOpts::new(binlog_dir),
+            start_file: None,
+            reconnect_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+/// replicates binlog files from one upstream connection into local directory, and serves
+/// them to any number of downstream replicas by [`FakeSource`], downstream catches up
+/// from disk then tails the files as they grow
+#[derive(Debug)]
+pub struct Relay {
+    opts: RelayOpts,
+}
+
+impl Relay {
+    pub fn new(opts: RelayOpts) -> Self {
+        Self { opts }
+    }
+
+    pub fn opts(&self) -> &RelayOpts {
+        &self.opts
+    }
+
+    /// connect upstream and serve downstream at `addr`, replication runs in background
+    /// thread and reconnects `reconnect_interval` after upstream ends the stream
+    ///
+    /// transient errors are passed to `on_error` and retried, other errors stop the
+    /// replication thread and are returned by [`RelayHandle::join`].
+    pub fn spawn(
+        mut self,
+        addr: impl ToSocketAddrs,
+        mut on_error: impl FnMut(&RelayError) + Send + 'static,
+    ) -> Result<RelayHandle, RelayError> {
+        fs::create_dir_all(&self.opts.source.binlog_dir)?;
+        let mut client = self.connect()?;
+        let names = UPSTREAM_VARIABLES.map(|name| format!("@@global.{name}"));
+        let row = client
+            .query(&format!("SELECT {}", names.join(", ")))?
+            .next();
+        if let Some(row) = row.transpose()? {
+            for (name, value) in UPSTREAM_VARIABLES.iter().zip(row.into_values()) {
+                if let Some(value) = value.as_str() {
+                    let name = name.to_string();
+                    self.opts.source.variables.insert(name, value.to_string());
+                }
+            }
+        }
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let stopped = Arc::new(AtomicBool::new(false));
+        let source = Arc::new(FakeSource::new(self.opts.source.clone()));
+        let serving = stopped.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if serving.load(Ordering::Acquire) {
+                    break;
+                }
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let _ = stream.set_nodelay(true);
+                let source = source.clone();
+                thread::spawn(move || source.serve(stream));
+            }
+        });
+        let upstream = Arc::new(Mutex::new(None));
+        let (replicating, current) = (stopped.clone(), upstream.clone());
+        let thread = thread::spawn(move || {
+            let mut client = Ok(client);
+            loop {
+                let result = client.and_then(|client| {
+                    *current.lock().unwrap() = Some(client.stream().try_clone()?);
+                    if replicating.load(Ordering::Acquire) {
+                        return Ok(());
+                    }
+                    self.replicate_with(client)
+                });
+                if replicating.load(Ordering::Acquire) {
+                    return Ok(());
+                }
+                if let Err(e) = result {
+                    if !e.is_transient() {
+                        return Err(e);
+                    }
+                    on_error(&e);
+                }
+                thread::park_timeout(self.opts.reconnect_interval);
+                if replicating.load(Ordering::Acquire) {
+                    return Ok(());
+                }
+                client = self.connect();
+            }
+        });
+        Ok(RelayHandle {
+            addr,
+            stopped,
+            upstream,
+            thread,
+        })
+    }
+
+    /// connect upstream and write received events into local binlog files, return when
+    /// upstream ends the stream
+    pub fn replicate(&self) -> Result<(), RelayError> {
+        let client = self.connect()?;
+        self.replicate_with(client)
+    }
+
+    fn connect(&self) -> Result<Client<TcpStream>, RelayError> {
+        Ok(Client::connect_with(
+            &TcpConnector,
+            self.opts.upstream.clone(),
+        )?)
+    }
+
+    fn replicate_with(&self, client: Client<TcpStream>) -> Result<(), RelayError> {
+        let opts = BackupOpts {
+            upstream: self.opts.upstream.clone(),
+            dir: self.opts.source.binlog_dir.clone(),
+            server_id: self.opts.source.server_id,
+            start_file: self.opts.start_file.clone(),
+            stop_never: true,
+            reconnect_interval: self.opts.reconnect_interval,
+        };
+        Ok(Backup::new(opts).run_with(client)?)
+    }
+}
+
+/// handle of a spawned [`Relay`], dropping it leaves the relay running
+#[derive(Debug)]
+pub struct RelayHandle {
+    addr: SocketAddr,
+    stopped: Arc<AtomicBool>,
+    /// current upstream connection, shut down to interrupt a blocking dump
+    upstream: Arc<Mutex<Option<TcpStream>>>,
+    thread: JoinHandle<Result<(), RelayError>>,
+}
+
+impl RelayHandle {
+    /// address downstream replicas connect to
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// whether replication thread has stopped by shutdown or an error
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// stop replication and accepting downstream connections, connected downstream
+    /// replicas are served until they disconnect
+    pub fn shutdown(self) -> Result<(), RelayError> {
+        self.stopped.store(true, Ordering::Release);
+        if let Some(stream) = self.upstream.lock().unwrap().take() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        self.thread.thread().unpark();
+        // wake up listener blocked in accept
+        let _ = TcpStream::connect(self.addr);
+        self.join()
+    }
+
+    /// wait for replication thread, which only stops by shutdown or non transient error
+    pub fn join(self) -> Result<(), RelayError> {
+        self.thread.join().expect("relay thread panicked")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::Path,
+        time::{Duration, Instant},
+    };
+
+    use super::*;
+    use crate::{
+        binlog::binlog_files,
+        codec::{Int2, Int4},
+        connector::{ComBinLogDump, BINLOG_DUMP_NON_BLOCK},
+        test_util::temp_dir,
+    };
+
+    fn wait_synced(upstream: &Path, relay: &Path) {
+        let start = Instant::now();
+        let synced = || {
+            let relay = binlog_files(relay).unwrap();
+            binlog_files(upstream)
+                .unwrap()
+                .iter()
+                .all(|file| relay.contains(file))
+        };
+        while !synced() {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "relay is not synced"
+            );
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    fn connect(addr: SocketAddr) -> Client<TcpStream> {
+        let opts = FakeSource::client_opts(addr);
+        Client::connect_with(&TcpConnector, opts).unwrap()
+    }
+
+    #[test]
+    fn test_relay() {
+        let upstream_dir = temp_dir("relay-upstream");
+        let relay_dir = temp_dir("relay-local");
+        let file1 = include_bytes!("../../../tests/events/02_query/log.bin");
+        let file2 = include_bytes!("../../../tests/events/04_rotate/log.bin");
+        fs::write(upstream_dir.join("mysql_bin.000001"), file1).unwrap();
+
+        let mut source = FakeSourceOpts::new(&upstream_dir);
+        source.poll_interval = Duration::from_millis(10);
+        let upstream = FakeSource::new(source).spawn("127.0.0.1:0").unwrap();
+        let upstream = FakeSource::client_opts(upstream);
+        let mut opts = RelayOpts::new(upstream, &relay_dir);
+        opts.source.server_id = 2;
+        opts.source.poll_interval = Duration::from_millis(10);
+        let relay = Relay::new(opts)
+            .spawn("127.0.0.1:0", |e| panic!("relay error: {e}"))
+            .unwrap();
+        let addr = relay.addr();
+        wait_synced(&upstream_dir, &relay_dir);
+        assert_eq!(fs::read(relay_dir.join("mysql_bin.000001")).unwrap(), file1);
+
+        // catch up from disk
+        let mut client = connect(addr);
+        let dump = ComBinLogDump {
+            pos: Int4::from(4),
+            flags: Int2::from(BINLOG_DUMP_NON_BLOCK),
+            server_id: Int4::from(100),
+            filename: "mysql_bin.000001".into(),
+        };
+        client.binlog_dump(&dump).unwrap();
+        let mut types = vec![];
+        while let Some(event) = client.recv_binlog().unwrap() {
+            types.push(event[4]);
+        }
+        assert_eq!(types, [4, 15, 35, 33, 2, 33, 2, 4]);
+
+        // live tailing
+        let dump = ComBinLogDump {
+            flags: Int2::from(0),
+            ..dump
+        };
+        client.binlog_dump(&dump).unwrap();
+        client
+            .stream()
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        for _ in 0..types.len() {
+            client.recv_binlog().unwrap().unwrap();
+        }
+        fs::write(upstream_dir.join("mysql_bin.000002"), file2).unwrap();
+        let mut types = vec![];
+        for _ in 0..3 {
+            types.push(client.recv_binlog().unwrap().unwrap()[4]);
+        }
+        assert_eq!(types, [15, 35, 4]);
+        wait_synced(&upstream_dir, &relay_dir);
+        assert_eq!(fs::read(relay_dir.join("mysql_bin.000002")).unwrap(), file2);
+
+        // blocking dump of upstream is interrupted
+        assert!(!relay.is_finished());
+        relay.shutdown().unwrap();
+        let start = Instant::now();
+        while TcpStream::connect(addr).is_ok() {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "relay still listens"
+            );
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        fs::remove_dir_all(upstream_dir).unwrap();
+        fs::remove_dir_all(relay_dir).unwrap();
+    }
+}
@@ -0,0 +1,1058 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    hash::{BuildHasher, Hasher},
+    io::{self, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, ToSocketAddrs},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bytes::{BufMut, Bytes, BytesMut};
+use thiserror::Error;
+
+use crate::{
+    binlog::{
+        binlog_files, format_desc_checksum, BinlogFileReader, Event, EventHeader, EventHeaderFlag,
+        EventType, GtidSet, ReaderError, RotateEvent, Sid, TransactionTracker, BINLOG_MAGIC,
+        EVENT_HEADER_LEN,
+    },
+    codec::{Decode, DecodeError, Encode, Int1, Int2, Int4, Int8, VLenInt},
+    connector::{
+        encode_packet, native_password_auth, AuthSwitchReq, AuthSwitchResp, Capabilities, ColDef,
+        Command, EofPacket, ErrPacket, HandshakeResponse41, HandshakeV10, OkPacket, ServerStatus,
+        TextResult, BINLOG_DUMP_NON_BLOCK, BINLOG_THROUGH_GTID, MAX_PAYLOAD_LEN,
+    },
+    value::{ColumnFlags, ColumnType, BINARY_CHARSET},
+};
+
+const NATIVE_PASSWORD: &str = "mysql_native_password";
+
+/// utf8mb4_0900_ai_ci
+const DEFAULT_CHARSET: u16 = 255;
+
+const COM_REGISTER_SLAVE: u8 = 0x15;
+
+#[derive(Debug, Error)]
+pub enum ServerError {
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    #[error("decode error: {0}")]
+    Decode(#[from] DecodeError),
+    #[error("access denied for user {0}")]
+    AccessDenied(String),
+    #[error("invalid binlog file {0}")]
+    InvalidBinlog(PathBuf),
+    #[error(transparent)]
+    Reader(#[from] ReaderError),
+}
+
+/// blocking packet IO of server side connection
+#[derive(Debug)]
+pub struct ServerConn<S> {
+    stream: S,
+    seq_id: u8,
+    caps: Capabilities,
+}
+
+impl<S: Read + Write> ServerConn<S> {
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            seq_id: 0,
+            caps: Capabilities::empty(),
+        }
+    }
+
+    /// capabilities negotiated with client
+    pub fn caps(&self) -> Capabilities {
+        self.caps
+    }
+
+    pub fn set_caps(&mut self, caps: Capabilities) {
+        self.caps = caps;
+    }
+
+    pub fn stream(&self) -> &S {
+        &self.stream
+    }
+
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// read a complete logical packet, sequence id of response follows the received one
+    pub fn read_packet(&mut self) -> Result<BytesMut, ServerError> {
+        let mut payload = BytesMut::new();
+        loop {
+            let mut header = [0; 4];
+            self.stream.read_exact(&mut header)?;
+            let len = u32::from_le_bytes([header[0], header[1], header[2], 0]) as usize;
+            self.seq_id = header[3].wrapping_add(1);
+            let start = payload.len();
+            payload.resize(start + len, 0);
+            self.stream.read_exact(&mut payload[start..])?;
+            if len < MAX_PAYLOAD_LEN {
+                return Ok(payload);
+            }
+        }
+    }
+
+    /// write payload, split into multiple packets if it's too large
+    pub fn write_packet<P: Encode>(&mut self, payload: &P) -> Result<(), ServerError> {
+        let mut data = BytesMut::new();
+        payload.encode(&mut data);
+        let mut buf = BytesMut::new();
+        let mut last_len = 0;
+        for chunk in data.chunks(MAX_PAYLOAD_LEN) {
+            encode_packet(self.seq_id, &RawPayload(chunk), &mut buf);
+            self.seq_id = self.seq_id.wrapping_add(1);
+            last_len = chunk.len();
+        }
+        if last_len == MAX_PAYLOAD_LEN || data.is_empty() {
+            encode_packet(self.seq_id, &RawPayload(&[]), &mut buf);
+            self.seq_id = self.seq_id.wrapping_add(1);
+        }
+        self.stream.write_all(&buf)?;
+        self.stream.flush()?;
+        Ok(())
+    }
+
+    pub fn write_ok(&mut self, affected_rows: u64) -> Result<(), ServerError> {
+        self.write_packet(&ok_packet(0x00, affected_rows))
+    }
+
+    pub fn write_err(&mut self, code: u16, state: &str, msg: &str) -> Result<(), ServerError> {
+        let err = ErrPacket {
+            header: Int1::from(0xff),
+            code: Int2::from(code),
+            sql_state_marker: Int1::from(b'#'),
+            sql_state: state.to_string(),
+            error_msg: msg.to_string(),
+        };
+        self.write_packet(&err)
+    }
+
+    /// end of column definitions or rows, an OK packet if `CLIENT_DEPRECATE_EOF` is set
+    pub fn write_eof(&mut self) -> Result<(), ServerError> {
+        if self.caps.contains(Capabilities::CLIENT_DEPRECATE_EOF) {
+            self.write_packet(&ok_packet(0xfe, 0))
+        } else {
+            self.write_packet(&EofPacket {
+                header: Int1::from(0xfe),
+                warnings: Int2::from(0),
+                status_flags: ServerStatus::SERVER_STATUS_AUTOCOMMIT,
+            })
+        }
+    }
+
+    /// write a complete text protocol result set
+    pub fn write_result_set(
+        &mut self,
+        columns: &[(&str, ColumnType)],
+        rows: &[Vec<Option<String>>],
+    ) -> Result<(), ServerError> {
+        self.write_packet(&VLenInt::new(columns.len() as u64))?;
+        for (name, ty) in columns {
+            self.write_packet(&col_def(name, *ty))?;
+        }
+        if !self.caps.contains(Capabilities::CLIENT_DEPRECATE_EOF) {
+            self.write_eof()?;
+        }
+        for row in rows {
+            let row = TextResult {
+                columns: row
+                    .iter()
+                    .map(|v| v.as_ref().map(|v| Bytes::copy_from_slice(v.as_bytes())))
+                    .collect(),
+            };
+            self.write_packet(&row)?;
+        }
+        self.write_eof()
+    }
+}
+
+struct RawPayload<'a>(&'a [u8]);
+
+impl Encode for RawPayload<'_> {
+    fn encode(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(self.0);
+    }
+}
+
+/// binlog event in replication stream, prefixed with OK byte
+struct EventPayload<'a>(&'a [u8]);
+
+impl Encode for EventPayload<'_> {
+    fn encode(&self, buf: &mut BytesMut) {
+        buf.put_u8(0x00);
+        buf.extend_from_slice(self.0);
+    }
+}
+
+fn ok_packet(header: u8, affected_rows: u64) -> OkPacket {
+    OkPacket {
+        header: Int1::from(header),
+        affected_rows: VLenInt::new(affected_rows),
+        last_insert_id: VLenInt::new(0),
+        status_flags: ServerStatus::SERVER_STATUS_AUTOCOMMIT,
+        warnings: Int2::from(0),
+        info: String::new(),
+        session_state_changes: vec![],
+    }
+}
+
+pub(crate) fn col_def(name: &str, ty: ColumnType) -> ColDef {
+    let numeric = matches!(ty, ColumnType::LongLong);
+    let (charset, flags) = if numeric {
+        (BINARY_CHARSET, ColumnFlags::NOT_NULL | ColumnFlags::NUM)
+    } else {
+        (DEFAULT_CHARSET, ColumnFlags::empty())
+    };
+    ColDef {
+        catalog: "def".into(),
+        schema: String::new(),
+        table: String::new(),
+        original_table: String::new(),
+        name: name.into(),
+        original_name: String::new(),
+        length_of_fixed_length_fields: VLenInt::new(0x0c),
+        charset: Int2::from(charset),
+        column_length: Int4::from(if numeric { 20 } else { 1024 }),
+        ty: Int1::from(ty as u8),
+        flags: Int2::from(flags.bits()),
+        decimals: Int1::from(if numeric { 0 } else { 0x1f }),
+    }
+}
+
+/// options of [`FakeSource`]
+#[derive(Debug, Clone)]
+pub struct FakeSourceOpts {
+    /// directory of binlog files, files with numeric extension like `mysql-bin.000001`
+    /// are served in name order
+    pub binlog_dir: PathBuf,
+    pub server_id: u32,
+    pub server_version: String,
+    /// accept any user when `None`
+    pub user: Option<String>,
+    pub password: String,
+    /// answers of `SELECT @@var` and `SHOW VARIABLES`
+    pub variables: HashMap<String, String>,
+    /// interval to check new events when dumping without `BINLOG_DUMP_NON_BLOCK`
+    pub poll_interval: Duration,
+}
+
+impl FakeSourceOpts {
+    pub fn new(binlog_dir: impl Into<PathBuf>) -> Self {
+        let variables = [
+            ("version_comment", "boxercrab fake source"),
+            ("server_uuid", "a1b2c3d4-0000-0000-0000-000000000001"),
+            ("binlog_checksum", "CRC32"),
+            ("binlog_format", "ROW"),
+            ("binlog_row_image", "FULL"),
+            ("gtid_mode", "ON"),
+            ("enforce_gtid_consistency", "ON"),
+            ("log_bin", "1"),
+            ("max_allowed_packet", "67108864"),
+            ("character_set_server", "utf8mb4"),
+            ("collation_server", "utf8mb4_0900_ai_ci"),
+            ("time_zone", "SYSTEM"),
+            ("system_time_zone", "UTC"),
+            ("lower_case_table_names", "0"),
+            ("sql_mode", ""),
+            ("transaction_isolation", "REPEATABLE-READ"),
+            ("session_track_gtids", "OFF"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        Self {
+            binlog_dir: binlog_dir.into(),
+            server_id: 1,
+            server_version: "8.0.34-boxercrab".into(),
+            user: None,
+            password: String::new(),
+            variables,
+            poll_interval: Duration::from_millis(100),
+        }
+    }
+
+    /// value of a system variable, `server_id` and `version` are taken from options
+    pub fn variable(&self, name: &str) -> Option<String> {
+        let name = name.to_ascii_lowercase();
+        match name.as_str() {
+            "server_id" => Some(self.server_id.to_string()),
+            "version" => Some(self.server_version.clone()),
+            _ => self.variables.get(&name).cloned(),
+        }
+    }
+}
+
+/// in-process server which acts as a MySQL source, it answers the queries a replica
+/// issues before replication and streams events of local binlog files
+#[derive(Debug)]
+pub struct FakeSource {
+    opts: FakeSourceOpts,
+    next_thread_id: AtomicU32,
+}
+
+impl FakeSource {
+    pub fn new(opts: FakeSourceOpts) -> Self {
+        Self {
+            opts,
+            next_thread_id: AtomicU32::new(1),
+        }
+    }
+
+    pub fn opts(&self) -> &FakeSourceOpts {
+        &self.opts
+    }
+
+    /// bind address and serve in background thread, return bound address
+    pub fn spawn(self, addr: impl ToSocketAddrs) -> io::Result<SocketAddr> {
+        let listener = TcpListener::bind(addr)?;
+        let local = listener.local_addr()?;
+        let this = Arc::new(self);
+        thread::spawn(move || this.run(listener));
+        Ok(local)
+    }
+
+    /// accept connections forever, each connection is served in its own thread
+    pub fn run(self: Arc<Self>, listener: TcpListener) {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let _ = stream.set_nodelay(true);
+            let this = self.clone();
+            thread::spawn(move || this.serve(stream));
+        }
+    }
+
+    /// serve one connection until client quits or disconnects
+    pub fn serve<S: Read + Write>(&self, stream: S) -> Result<(), ServerError> {
+        let mut conn = ServerConn::new(stream);
+        self.handshake(&mut conn)?;
+        loop {
+            let mut payload = match conn.read_packet() {
+                Ok(payload) => payload,
+                Err(ServerError::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    return Ok(())
+                }
+                Err(e) => return Err(e),
+            };
+            match Command::decode_with_caps(&mut payload, conn.caps())? {
+                Command::Quit(_) => return Ok(()),
+                Command::Query(query) => self.on_query(&mut conn, &query.query)?,
+                Command::Ping(_) | Command::InitDb(_) | Command::ResetConnection(_) => {
+                    conn.write_ok(0)?
+                }
+                Command::BinlogDump(dump) => self.dump(
+                    &mut conn,
+                    &dump.filename,
+                    dump.pos.int() as u64,
+                    dump.flags.int(),
+                    None,
+                )?,
+                Command::BinlogDumpGtid(dump) => {
+                    let flags = dump.flags.int();
+                    let gtid_set = (flags & BINLOG_THROUGH_GTID != 0).then_some(dump.gtid_set);
+                    self.dump(&mut conn, &dump.filename, dump.pos.int(), flags, gtid_set)?
+                }
+                Command::Unknown(payload) if payload.first() == Some(&COM_REGISTER_SLAVE) => {
+                    conn.write_ok(0)?
+                }
+                _ => conn.write_err(1047, "08S01", "Unknown command")?,
+            }
+        }
+    }
+
+    /// connection phase, ERR is sent and error is returned when authentication fails
+    pub(crate) fn handshake<S: Read + Write>(
+        &self,
+        conn: &mut ServerConn<S>,
+    ) -> Result<(), ServerError> {
+        let thread_id = self.next_thread_id.fetch_add(1, Ordering::Relaxed);
+        let scramble = scramble(thread_id);
+        let caps = Capabilities::CLIENT_LONG_PASSWORD
+            | Capabilities::CLIENT_LONG_FLAG
+            | Capabilities::CLIENT_CONNECT_WITH_DB
+            | Capabilities::CLIENT_PROTOCOL_41
+            | Capabilities::CLIENT_TRANSACTIONS
+            | Capabilities::CLIENT_RESERVED2
+            | Capabilities::CLIENT_MULTI_STATEMENTS
+            | Capabilities::CLIENT_MULTI_RESULTS
+            | Capabilities::CLIENT_PLUGIN_AUTH
+            | Capabilities::CLIENT_CONNECT_ATTRS
+            | Capabilities::CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA
+            | Capabilities::CLIENT_DEPRECATE_EOF;
+        let mut auth_plugin_data = BytesMut::from(&scramble[..]);
+        auth_plugin_data.put_u8(0);
+        let handshake = HandshakeV10 {
+            protocol_version: Int1::from(10),
+            server_version: self.opts.server_version.clone(),
+            thread_id: Int4::from(thread_id),
+            caps,
+            charset: Int1::from(DEFAULT_CHARSET as u8),
+            status: Int2::from(ServerStatus::SERVER_STATUS_AUTOCOMMIT.bits()),
+            ext_caps: Int4::from(0),
+            auth_plugin_name: NATIVE_PASSWORD.into(),
+            auth_plugin_data,
+        };
+        conn.write_packet(&handshake)?;
+
+        let resp = HandshakeResponse41::decode(&mut conn.read_packet()?)?;
+        conn.set_caps(resp.caps & caps);
+        let mut auth_resp = resp.auth_resp;
+        if resp
+            .plugin_name
+            .as_deref()
+            .is_some_and(|plugin| plugin != NATIVE_PASSWORD)
+        {
+            let req = AuthSwitchReq {
+                plugin_name: NATIVE_PASSWORD.into(),
+                plugin_data: BytesMut::from(&scramble[..]),
+            };
+            conn.write_packet(&req)?;
+            auth_resp = AuthSwitchResp::decode(&mut conn.read_packet()?)?.data;
+        }
+        if !self.check_auth(&resp.user_name, &auth_resp, &scramble) {
+            let msg = format!("Access denied for user '{}'", resp.user_name);
+            conn.write_err(1045, "28000", &msg)?;
+            return Err(ServerError::AccessDenied(resp.user_name));
+        }
+        conn.write_ok(0)
+    }
+
+    fn check_auth(&self, user: &str, auth_resp: &[u8], scramble: &[u8]) -> bool {
+        if self.opts.user.as_deref().is_some_and(|u| u != user) {
+            return false;
+        }
+        if self.opts.password.is_empty() {
+            auth_resp.is_empty()
+        } else {
+            auth_resp == native_password_auth(self.opts.password.as_bytes(), scramble)
+        }
+    }
+
+    fn on_query<S: Read + Write>(
+        &self,
+        conn: &mut ServerConn<S>,
+        query: &str,
+    ) -> Result<(), ServerError> {
+        let sql = query.trim().trim_end_matches(';').trim_end();
+        let lower = sql.to_ascii_lowercase();
+        if lower.starts_with("set ") {
+            return conn.write_ok(0);
+        }
+        match lower.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["show", "master" | "binary", "status"] | ["show", "binary", "log", "status"] => {
+                let files = self.binlog_files()?;
+                let (file, pos) = files.last().cloned().unwrap_or_default();
+                let gtids = self.executed_gtids(&files)?;
+                let columns = [
+                    ("File", ColumnType::VarString),
+                    ("Position", ColumnType::LongLong),
+                    ("Binlog_Do_DB", ColumnType::VarString),
+                    ("Binlog_Ignore_DB", ColumnType::VarString),
+                    ("Executed_Gtid_Set", ColumnType::VarString),
+                ];
+                let row = vec![
+                    Some(file),
+                    Some(pos.to_string()),
+                    Some(String::new()),
+                    Some(String::new()),
+                    Some(gtids.to_string()),
+                ];
+                return conn.write_result_set(&columns, &[row]);
+            }
+            ["show", "binary" | "master", "logs"] => {
+                let columns = [
+                    ("Log_name", ColumnType::VarString),
+                    ("File_size", ColumnType::LongLong),
+                ];
+                let rows: Vec<_> = self
+                    .binlog_files()?
+                    .into_iter()
+                    .map(|(name, size)| vec![Some(name), Some(size.to_string())])
+                    .collect();
+                return conn.write_result_set(&columns, &rows);
+            }
+            ["show", .., "variables", "like", _] => {
+                let pattern = sql.rsplit(char::is_whitespace).next().unwrap_or_default();
+                let pattern = pattern.trim_matches(|c| c == '\'' || c == '"');
+                let mut names: Vec<_> = self
+                    .opts
+                    .variables
+                    .keys()
+                    .map(String::as_str)
+                    .chain(["server_id", "version"])
+                    .filter(|name| like(pattern, name))
+                    .collect();
+                names.sort_unstable();
+                let rows: Vec<_> = names
+                    .into_iter()
+                    .map(|name| vec![Some(name.to_string()), self.opts.variable(name)])
+                    .collect();
+                let columns = [
+                    ("Variable_name", ColumnType::VarString),
+                    ("Value", ColumnType::VarString),
+                ];
+                return conn.write_result_set(&columns, &rows);
+            }
+            _ => {}
+        }
+        if lower.starts_with("select ") {
+            if let Some((columns, row)) = self.select(&sql[7..]) {
+                let columns: Vec<_> = columns.iter().map(|(n, t)| (n.as_str(), *t)).collect();
+                return conn.write_result_set(&columns, &[row]);
+            }
+        }
+        let msg = format!("This version of fake source doesn't yet support '{sql}'");
+        conn.write_err(1235, "42000", &msg)
+    }
+
+    /// evaluate select list of system variables, functions and literals
+    #[allow(clippy::type_complexity)]
+    fn select(&self, list: &str) -> Option<(Vec<(String, ColumnType)>, Vec<Option<String>>)> {
+        let mut columns = vec![];
+        let mut row = vec![];
+        for item in list.split(',') {
+            let item = item.trim();
+            let (expr, alias) = match item.to_ascii_lowercase().find(" as ") {
+                Some(idx) => (item[..idx].trim(), Some(item[idx + 4..].trim())),
+                None => (item, None),
+            };
+            let lower = expr.to_ascii_lowercase();
+            let value = if let Some(name) = lower.strip_prefix("@@") {
+                let name = name
+                    .strip_prefix("global.")
+                    .or_else(|| name.strip_prefix("session."))
+                    .unwrap_or(name);
+                self.opts.variable(name)
+            } else {
+                match lower.as_str() {
+                    "version()" => Some(self.opts.server_version.clone()),
+                    "database()" | "null" => None,
+                    "unix_timestamp()" => Some(
+                        SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .unwrap_or_default()
+                            .as_secs()
+                            .to_string(),
+                    ),
+                    _ if lower.parse::<i64>().is_ok() => Some(lower.clone()),
+                    _ if expr.len() >= 2 && (expr.starts_with('\'') && expr.ends_with('\'')) => {
+                        Some(expr[1..expr.len() - 1].to_string())
+                    }
+                    _ => return None,
+                }
+            };
+            let name = alias.unwrap_or(expr).trim_matches('`').to_string();
+            let ty = match &value {
+                Some(v) if v.parse::<i64>().is_ok() => ColumnType::LongLong,
+                _ => ColumnType::VarString,
+            };
+            columns.push((name, ty));
+            row.push(value);
+        }
+        Some((columns, row))
+    }
+
+    /// binlog files and their size in name order
+    pub fn binlog_files(&self) -> Result<Vec<(String, u64)>, ServerError> {
+        Ok(binlog_files(&self.opts.binlog_dir)?)
+    }
+
+    /// previous GTIDs of first binlog file and GTIDs of all GTID events
+    fn executed_gtids(&self, files: &[(String, u64)]) -> Result<GtidSet, ServerError> {
+        let mut gtids = GtidSet::new();
+        for (idx, (name, _)) in files.iter().enumerate() {
+            let mut reader = open_binlog(&self.opts.binlog_dir.join(name))?;
+            loop {
+                let event = match reader.next_raw() {
+                    Ok(Some(event)) => event,
+                    // end of file or event being written
+                    Ok(None) | Err(ReaderError::Incomplete(_)) => break,
+                    Err(e) => return Err(e.into()),
+                };
+                if idx == 0 && event[4] == EventType::PreviousGtids as u8 {
+                    // trailing checksum is left unread
+                    let mut body = BytesMut::from(&event[EVENT_HEADER_LEN..]);
+                    gtids.union(&GtidSet::decode(&mut body)?);
+                } else if let Some((sid, gno)) = gtid_of(&event) {
+                    gtids.add(sid, gno)?;
+                }
+            }
+        }
+        Ok(gtids)
+    }
+
+    fn dump<S: Read + Write>(
+        &self,
+        conn: &mut ServerConn<S>,
+        filename: &str,
+        pos: u64,
+        flags: u16,
+        gtid_set: Option<GtidSet>,
+    ) -> Result<(), ServerError> {
+        let files = self.binlog_files()?;
+        let idx = if filename.is_empty() {
+            (!files.is_empty()).then_some(0)
+        } else {
+            files.iter().position(|(name, _)| name == filename)
+        };
+        let Some(idx) = idx else {
+            let msg = "Could not find first log file name in binary log index file";
+            return conn.write_err(1236, "HY000", msg);
+        };
+        let mut name = files[idx].0.clone();
+        let mut offset = if filename.is_empty() {
+            BINLOG_MAGIC.len() as u64
+        } else {
+            pos.max(BINLOG_MAGIC.len() as u64)
+        };
+        let mut rotated = false;
+        let mut skipping = false;
+        let mut tracker = TransactionTracker::new();
+        let non_block = flags & BINLOG_DUMP_NON_BLOCK != 0;
+        loop {
+            let path = self.opts.binlog_dir.join(&name);
+            // file is just created, format description event is not written yet
+            let Some(fde) = format_desc(&path)? else {
+                if non_block {
+                    return conn.write_eof();
+                }
+                thread::sleep(self.opts.poll_interval);
+                continue;
+            };
+            let checksum = format_desc_checksum(&fde) == Some(1);
+            if !rotated {
+                let rotate = rotate_event(self.opts.server_id, &name, offset, checksum);
+                conn.write_packet(&EventPayload(&rotate))?;
+                if offset > BINLOG_MAGIC.len() as u64 {
+                    conn.write_packet(&EventPayload(&fde))?;
+                }
+                rotated = true;
+            }
+            // file is kept open, only events appended since last poll are read
+            let mut reader = open_binlog(&path)?;
+            if let Err(e) = reader.skip_to(offset) {
+                return match e {
+                    ReaderError::InvalidPosition(_) => {
+                        conn.write_err(1236, "HY000", &e.to_string())
+                    }
+                    e => Err(e.into()),
+                };
+            }
+            loop {
+                let event = match reader.next_raw() {
+                    Ok(Some(event)) => event,
+                    // end of file or event being written
+                    Ok(None) | Err(ReaderError::Incomplete(_)) => {
+                        // a file never grows once next file is created
+                        let files = self.binlog_files()?;
+                        let next = files
+                            .iter()
+                            .position(|(n, _)| *n == name)
+                            .and_then(|idx| files.get(idx + 1));
+                        if let Some((next, _)) = next {
+                            if fs::metadata(&path)?.len() == reader.offset() {
+                                name = next.clone();
+                                offset = BINLOG_MAGIC.len() as u64;
+                                break;
+                            }
+                        }
+                        if non_block {
+                            return conn.write_eof();
+                        }
+                        thread::sleep(self.opts.poll_interval);
+                        continue;
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                if let Some(gtid_set) = &gtid_set {
+                    let ty = EventType::try_from(event[4]).ok();
+                    match ty {
+                        // events of file itself are never skipped
+                        Some(
+                            EventType::FormatDescription
+                            | EventType::Rotate
+                            | EventType::PreviousGtids,
+                        ) => {}
+                        _ => {
+                            if ty == Some(EventType::Gtid) {
+                                skipping = gtid_of(&event)
+                                    .is_some_and(|(sid, gno)| gtid_set.contains(&sid, gno));
+                            } else if ty == Some(EventType::AnonymousGtid) {
+                                skipping = false;
+                            }
+                            let skipped = skipping;
+                            if is_transaction_event(ty) {
+                                let event = Event::decode_with(&mut event.clone(), checksum)?;
+                                if tracker.update(&event.payload) {
+                                    skipping = false;
+                                }
+                            }
+                            if skipped {
+                                continue;
+                            }
+                        }
+                    }
+                }
+                // rotate event in file announces next file itself
+                rotated = event[4] == EventType::Rotate as u8;
+                conn.write_packet(&EventPayload(&event))?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+impl FakeSource {
+    /// options connecting to fake source listening on `addr`
+    pub(crate) fn client_opts(addr: SocketAddr) -> crate::connector::ClientOpts {
+        crate::connector::ClientOpts {
+            host: addr.ip().to_string(),
+            port: addr.port(),
+            ..Default::default()
+        }
+    }
+}
+
+fn open_binlog(path: &Path) -> Result<BinlogFileReader<BufReader<File>>, ServerError> {
+    BinlogFileReader::open(path).map_err(|e| match e {
+        ReaderError::InvalidMagic => ServerError::InvalidBinlog(path.to_path_buf()),
+        e => e.into(),
+    })
+}
+
+/// format description event at the beginning of file, `None` if it's not written yet
+fn format_desc(path: &Path) -> Result<Option<BytesMut>, ServerError> {
+    match open_binlog(path)?.next_raw() {
+        Ok(Some(event)) if event[4] == EventType::FormatDescription as u8 => Ok(Some(event)),
+        Ok(Some(_)) => Err(ServerError::InvalidBinlog(path.to_path_buf())),
+        Ok(None) | Err(ReaderError::Incomplete(_)) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// events deciding transaction boundaries
+fn is_transaction_event(ty: Option<EventType>) -> bool {
+    matches!(
+        ty,
+        Some(
+            EventType::Gtid
+                | EventType::AnonymousGtid
+                | EventType::Query
+                | EventType::Xid
+                | EventType::XaPrepare
+        )
+    )
+}
+
+/// sid and gno of a GTID event
+fn gtid_of(event: &[u8]) -> Option<(Sid, u64)> {
+    if event.get(4) != Some(&(EventType::Gtid as u8)) {
+        return None;
+    }
+    // commit flag(1) + sid(16) + gno(8)
+    let body = event.get(EVENT_HEADER_LEN + 1..EVENT_HEADER_LEN + 25)?;
+    let sid: Sid = body[..16].try_into().ok()?;
+    let gno = u64::from_le_bytes(body[16..].try_into().ok()?);
+    Some((sid, gno))
+}
+
+/// artificial rotate event sent at the beginning of replication stream
+fn rotate_event(server_id: u32, name: &str, pos: u64, checksum: bool) -> BytesMut {
+    let event = Event {
+        header: EventHeader {
+            timestamp: Int4::from(0),
+            event_type: Int1::from(EventType::Rotate as u8),
+            server_id: Int4::from(server_id),
+            event_size: Int4::from(0),
+            log_pos: Int4::from(0),
+            flags: EventHeaderFlag::LOG_EVENT_ARTIFICIAL_F,
+        },
+        payload: RotateEvent {
+            pos: Int8::from(pos),
+            log: name.to_string(),
+        },
+    };
+    let mut buf = BytesMut::new();
+    event.encode_with(checksum, &mut buf);
+    buf
+}
+
+/// printable random scramble
+fn scramble(seed: u32) -> [u8; 20] {
+    let state = std::collections::hash_map::RandomState::new();
+    let mut out = [0; 20];
+    for (i, chunk) in out.chunks_mut(8).enumerate() {
+        let mut hasher = state.build_hasher();
+        hasher.write_u32(seed);
+        hasher.write_usize(i);
+        for (b, v) in chunk.iter_mut().zip(hasher.finish().to_le_bytes()) {
+            *b = 0x21 + v % 94;
+        }
+    }
+    out
+}
+
+/// SQL `LIKE` with `%` and `_`, case insensitive
+fn like(pattern: &str, s: &str) -> bool {
+    fn matches(p: &[u8], s: &[u8]) -> bool {
+        match p.split_first() {
+            None => s.is_empty(),
+            Some((b'%', rest)) => (0..=s.len()).any(|i| matches(rest, &s[i..])),
+            Some((b'_', rest)) => !s.is_empty() && matches(rest, &s[1..]),
+            Some((c, rest)) => {
+                s.first().is_some_and(|b| b.eq_ignore_ascii_case(c)) && matches(rest, &s[1..])
+            }
+        }
+    }
+    matches(pattern.as_bytes(), s.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        binlog::{next_event, parse_sid, BinlogWriter, BinlogWriterOpts},
+        client::{Client, ClientError, TcpConnector},
+        connector::{ClientOpts, ComBinLogDump, ComBinLogDumpGtid, MachineError},
+        test_util::temp_dir,
+    };
+
+    fn binlog_dir(name: &str) -> PathBuf {
+        let dir = temp_dir(&format!("fake-{name}"));
+        let file1 = include_bytes!("../../../tests/events/02_query/log.bin");
+        let file2 = include_bytes!("../../../tests/events/04_rotate/log.bin");
+        fs::write(dir.join("mysql-bin.000001"), file1).unwrap();
+        fs::write(dir.join("mysql-bin.000002"), file2).unwrap();
+        dir
+    }
+
+    fn count_events(path: &Path) -> usize {
+        let data = fs::read(path).unwrap();
+        let mut offset = BINLOG_MAGIC.len();
+        let mut count = 0;
+        while let Some(event) = next_event(&data, offset) {
+            offset += event.len();
+            count += 1;
+        }
+        count
+    }
+
+    fn connect(
+        addr: SocketAddr,
+        password: &str,
+    ) -> Result<Client<std::net::TcpStream>, ClientError> {
+        let opts = ClientOpts {
+            user: "repl".into(),
+            password: password.into(),
+            ..FakeSource::client_opts(addr)
+        };
+        Client::connect_with(&TcpConnector, opts)
+    }
+
+    #[test]
+    fn test_fake_source() {
+        let dir = binlog_dir("dump");
+        let mut opts = FakeSourceOpts::new(&dir);
+        opts.password = "secret".into();
+        let addr = FakeSource::new(opts).spawn("127.0.0.1:0").unwrap();
+
+        let err = connect(addr, "wrong").unwrap_err();
+        assert!(
+            matches!(err, ClientError::Machine(MachineError::Server(e)) if e.code.int() == 1045)
+        );
+
+        let mut client = connect(addr, "secret").unwrap();
+        client
+            .query_drop("SET @master_binlog_checksum = 'CRC32'")
+            .unwrap();
+        let row = client
+            .query("SELECT @@global.binlog_checksum, @@server_id AS id")
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(row.get(0).and_then(|v| v.as_str()), Some("CRC32"));
+        assert_eq!(row.get_by_name("id").and_then(|v| v.as_u64()), Some(1));
+
+        let row = client
+            .query("SHOW MASTER STATUS")
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let size = fs::metadata(dir.join("mysql-bin.000002")).unwrap().len();
+        assert_eq!(
+            row.get(0).and_then(|v| v.as_str()),
+            Some("mysql-bin.000002")
+        );
+        assert_eq!(row.get(1).and_then(|v| v.as_u64()), Some(size));
+        assert_eq!(
+            row.get(4).and_then(|v| v.as_str()),
+            Some("e3e2a4ee-b6dc-11ea-8bcf-0242ac150002:1-2")
+        );
+
+        let dump = ComBinLogDump {
+            pos: Int4::from(4),
+            flags: Int2::from(BINLOG_DUMP_NON_BLOCK),
+            server_id: Int4::from(100),
+            filename: "mysql-bin.000001".into(),
+        };
+        client.binlog_dump(&dump).unwrap();
+        let first = client.recv_binlog().unwrap().unwrap();
+        assert_eq!(first[4], EventType::Rotate as u8);
+        let mut count = 1;
+        while client.recv_binlog().unwrap().is_some() {
+            count += 1;
+        }
+        let file1 = count_events(&dir.join("mysql-bin.000001"));
+        let file2 = count_events(&dir.join("mysql-bin.000002"));
+        // artificial rotate of first file, second file is announced by rotate event
+        // in first file
+        assert_eq!(count, 1 + file1 + file2);
+
+        let mut gtid_set = GtidSet::new();
+        gtid_set
+            .add(
+                parse_sid("e3e2a4ee-b6dc-11ea-8bcf-0242ac150002").unwrap(),
+                1,
+            )
+            .unwrap();
+        let dump = ComBinLogDumpGtid {
+            flags: Int2::from(BINLOG_DUMP_NON_BLOCK | BINLOG_THROUGH_GTID),
+            server_id: Int4::from(100),
+            filename: String::new(),
+            pos: Int8::from(4),
+            gtid_set,
+        };
+        client.binlog_dump_gtid(&dump).unwrap();
+        let mut gtids = vec![];
+        while let Some(event) = client.recv_binlog().unwrap() {
+            gtids.extend(gtid_of(&event).map(|(_, gno)| gno));
+        }
+        assert_eq!(gtids, [2]);
+        client.quit().unwrap();
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_executed_gtids() {
+        let dir = binlog_dir("gtids");
+        let source = FakeSource::new(FakeSourceOpts::new(&dir));
+        let path = dir.join("mysql-bin.000002");
+        let mut data = fs::read(&path).unwrap();
+        // event being written at the end of last file
+        data.extend_from_slice(&[0; 10]);
+        fs::write(&path, &data).unwrap();
+        let files = source.binlog_files().unwrap();
+        assert_eq!(
+            source.executed_gtids(&files).unwrap().to_string(),
+            "e3e2a4ee-b6dc-11ea-8bcf-0242ac150002:1-2"
+        );
+
+        // event size smaller than header
+        data[BINLOG_MAGIC.len() + 9..BINLOG_MAGIC.len() + 13].copy_from_slice(&[5, 0, 0, 0]);
+        fs::write(&path, &data).unwrap();
+        assert!(matches!(
+            source.executed_gtids(&files),
+            Err(ServerError::Reader(ReaderError::Decode { .. }))
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_dump_gtid_skip() {
+        let dir = temp_dir("fake-skip");
+        let mut opts = BinlogWriterOpts::new(&dir, "mysql-bin");
+        opts.previous_gtids = "a1b2c3d4-0000-0000-0000-000000000001:1-100"
+            .parse()
+            .unwrap();
+        let mut writer = BinlogWriter::open(opts).unwrap();
+        for event in crate::binlog::tests::statement_events() {
+            writer.append(&event).unwrap();
+        }
+        writer.rotate().unwrap();
+        let gtid_set = writer.gtids().clone();
+        writer.close().unwrap();
+        let addr = FakeSource::new(FakeSourceOpts::new(&dir))
+            .spawn("127.0.0.1:0")
+            .unwrap();
+        let mut client = connect(addr, "").unwrap();
+
+        let row = client
+            .query("SHOW MASTER STATUS")
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let gtids: GtidSet = row
+            .get(4)
+            .and_then(|v| v.as_str())
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(gtids, gtid_set);
+
+        // every transaction is skipped, events of files are still sent
+        let dump = ComBinLogDumpGtid {
+            flags: Int2::from(BINLOG_DUMP_NON_BLOCK | BINLOG_THROUGH_GTID),
+            server_id: Int4::from(100),
+            filename: String::new(),
+            pos: Int8::from(4),
+            gtid_set,
+        };
+        client.binlog_dump_gtid(&dump).unwrap();
+        let mut types = vec![];
+        while let Some(event) = client.recv_binlog().unwrap() {
+            types.push(event[4]);
+        }
+        assert_eq!(types, [4, 15, 35, 4, 15, 35, 3]);
+        client.quit().unwrap();
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_dump_tail() {
+        let dir = temp_dir("fake-tail");
+        let data = include_bytes!("../../../tests/events/02_query/log.bin");
+        let path = dir.join("mysql-bin.000001");
+        // last event is half written
+        fs::write(&path, &data[..data.len() - 20]).unwrap();
+        let mut opts = FakeSourceOpts::new(&dir);
+        opts.poll_interval = Duration::from_millis(10);
+        let addr = FakeSource::new(opts).spawn("127.0.0.1:0").unwrap();
+        let mut client = connect(addr, "").unwrap();
+        client
+            .stream()
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let dump = ComBinLogDump {
+            pos: Int4::from(4),
+            flags: Int2::from(0),
+            server_id: Int4::from(100),
+            filename: "mysql-bin.000001".into(),
+        };
+        client.binlog_dump(&dump).unwrap();
+        let count = count_events(&path);
+        for _ in 0..1 + count {
+            client.recv_binlog().unwrap().unwrap();
+        }
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&data[data.len() - 20..]).unwrap();
+        let last = client.recv_binlog().unwrap().unwrap();
+        assert_eq!(last[..], data[data.len() - last.len()..]);
+        assert_eq!(count_events(&path), count + 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,9 @@
+use std::{fs, path::PathBuf};
+
+/// empty directory unique to test process
+pub(crate) fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("boxercrab-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
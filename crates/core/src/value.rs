@@ -0,0 +1,1089 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use parse_tool::InputBuf;
+
+use crate::codec::{get_var_bytes, get_var_str, put_var_bytes, Decode, DecodeError, Int4, Int8};
+
+/// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/field__types_8h.html)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum ColumnType {
+    Decimal = 0,
+    Tiny = 1,
+    Short = 2,
+    Long = 3,
+    Float = 4,
+    Double = 5,
+    Null = 6,
+    Timestamp = 7,
+    LongLong = 8,
+    Int24 = 9,
+    Date = 10,
+    Time = 11,
+    DateTime = 12,
+    Year = 13,
+    NewDate = 14,
+    VarChar = 15,
+    Bit = 16,
+    Timestamp2 = 17,
+    DateTime2 = 18,
+    Time2 = 19,
+    TypedArray = 20,
+    Vector = 242,
+    Invalid = 243,
+    Bool = 244,
+    Json = 245,
+    NewDecimal = 246,
+    Enum = 247,
+    Set = 248,
+    TinyBlob = 249,
+    MediumBlob = 250,
+    LongBlob = 251,
+    Blob = 252,
+    VarString = 253,
+    String = 254,
+    Geometry = 255,
+}
+
+impl TryFrom<u8> for ColumnType {
+    type Error = DecodeError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        let ty = match value {
+            0 => Self::Decimal,
+            1 => Self::Tiny,
+            2 => Self::Short,
+            3 => Self::Long,
+            4 => Self::Float,
+            5 => Self::Double,
+            6 => Self::Null,
+            7 => Self::Timestamp,
+            8 => Self::LongLong,
+            9 => Self::Int24,
+            10 => Self::Date,
+            11 => Self::Time,
+            12 => Self::DateTime,
+            13 => Self::Year,
+            14 => Self::NewDate,
+            15 => Self::VarChar,
+            16 => Self::Bit,
+            17 => Self::Timestamp2,
+            18 => Self::DateTime2,
+            19 => Self::Time2,
+            20 => Self::TypedArray,
+            242 => Self::Vector,
+            243 => Self::Invalid,
+            244 => Self::Bool,
+            245 => Self::Json,
+            246 => Self::NewDecimal,
+            247 => Self::Enum,
+            248 => Self::Set,
+            249 => Self::TinyBlob,
+            250 => Self::MediumBlob,
+            251 => Self::LongBlob,
+            252 => Self::Blob,
+            253 => Self::VarString,
+            254 => Self::String,
+            255 => Self::Geometry,
+            _ => return Err(DecodeError::InvalidData),
+        };
+        Ok(ty)
+    }
+}
+
+bitflags::bitflags! {
+    /// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/group__group__cs__column__definition__flags.html)
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct ColumnFlags: u16 {
+        /// Field can't be NULL
+        const NOT_NULL = 1;
+        /// Field is part of a primary key
+        const PRI_KEY = 2;
+        /// Field is part of a unique key
+        const UNIQUE_KEY = 4;
+        /// Field is part of a key
+        const MULTIPLE_KEY = 8;
+        /// Field is a blob
+        const BLOB = 16;
+        /// Field is unsigned
+        const UNSIGNED = 32;
+        /// Field is zerofill
+        const ZEROFILL = 64;
+        /// Field is binary
+        const BINARY = 128;
+        /// field is an enum
+        const ENUM = 256;
+        /// field is a autoincrement field
+        const AUTO_INCREMENT = 512;
+        /// Field is a timestamp
+        const TIMESTAMP = 1024;
+        /// field is a set
+        const SET = 2048;
+        /// Field doesn't have default value
+        const NO_DEFAULT_VALUE = 4096;
+        /// Field is set to NOW on UPDATE
+        const ON_UPDATE_NOW = 8192;
+        /// Field is num (for clients)
+        const NUM = 32768;
+    }
+}
+
+/// collation id of `binary` charset
+pub const BINARY_CHARSET: u16 = 63;
+
+/// typed column value shared by text protocol, binary protocol and binlog rows
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Int(i64),
+    UInt(u64),
+    Float(f32),
+    Double(f64),
+    /// decimal in string form, e.g. `-12.340`
+    Decimal(String),
+    String(String),
+    /// binary string, blob, bit, geometry or string can not be decoded as utf-8
+    Bytes(Bytes),
+    Date {
+        year: u16,
+        month: u8,
+        day: u8,
+    },
+    DateTime {
+        year: u16,
+        month: u8,
+        day: u8,
+        hour: u8,
+        minute: u8,
+        second: u8,
+        micro_second: u32,
+    },
+    Time {
+        negative: bool,
+        days: u32,
+        hours: u8,
+        minutes: u8,
+        seconds: u8,
+        micro_seconds: u32,
+    },
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Value::Int(v) => Some(v),
+            Value::UInt(v) => i64::try_from(v).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Value::Int(v) => u64::try_from(v).ok(),
+            Value::UInt(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) | Value::Decimal(s) => Some(s),
+            Value::Bytes(b) => std::str::from_utf8(b).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::String(s) | Value::Decimal(s) => Some(s.as_bytes()),
+            Value::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// convert text protocol value according to column type, flags and charset
+    pub fn from_text(
+        ty: ColumnType,
+        flags: ColumnFlags,
+        charset: u16,
+        data: Bytes,
+    ) -> Result<Self, DecodeError> {
+        let text = || std::str::from_utf8(&data).map_err(|_| DecodeError::InvalidUtf8);
+        let value = match ty {
+            ColumnType::Null => Value::Null,
+            ColumnType::Tiny
+            | ColumnType::Short
+            | ColumnType::Long
+            | ColumnType::Int24
+            | ColumnType::LongLong
+            | ColumnType::Year
+            | ColumnType::Bool => {
+                if flags.contains(ColumnFlags::UNSIGNED) {
+                    Value::UInt(parse_num(text()?)?)
+                } else {
+                    Value::Int(parse_num(text()?)?)
+                }
+            }
+            ColumnType::Float => Value::Float(parse_num(text()?)?),
+            ColumnType::Double => Value::Double(parse_num(text()?)?),
+            ColumnType::Decimal | ColumnType::NewDecimal => Value::Decimal(text()?.to_string()),
+            ColumnType::Date | ColumnType::NewDate => parse_date_time(text()?, false)?,
+            ColumnType::DateTime
+            | ColumnType::DateTime2
+            | ColumnType::Timestamp
+            | ColumnType::Timestamp2 => parse_date_time(text()?, true)?,
+            ColumnType::Time | ColumnType::Time2 => parse_time(text()?)?,
+            ColumnType::Bit | ColumnType::Geometry | ColumnType::Vector => Value::Bytes(data),
+            _ => string_or_bytes(charset, data),
+        };
+        Ok(value)
+    }
+
+    /// decode binary protocol value according to column type, flags and charset
+    ///
+    /// NULL is not encoded in value part but null bitmap, so it's never returned
+    /// unless column type is `MYSQL_TYPE_NULL`.
+    ///
+    /// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_binary_resultset.html#sect_protocol_binary_resultset_row_value)
+    pub fn from_binary(
+        ty: ColumnType,
+        flags: ColumnFlags,
+        charset: u16,
+        input: &mut BytesMut,
+    ) -> Result<Self, DecodeError> {
+        let unsigned = flags.contains(ColumnFlags::UNSIGNED);
+        let value = match ty {
+            ColumnType::Null => Value::Null,
+            ColumnType::Tiny | ColumnType::Bool => {
+                let v = input.read_u8_le()?;
+                if unsigned {
+                    Value::UInt(v as u64)
+                } else {
+                    Value::Int(v as i8 as i64)
+                }
+            }
+            ColumnType::Short | ColumnType::Year => {
+                let v = input.read_u16_le()?;
+                if unsigned {
+                    Value::UInt(v as u64)
+                } else {
+                    Value::Int(v as i16 as i64)
+                }
+            }
+            ColumnType::Long | ColumnType::Int24 => {
+                let v = Int4::decode(input)?.int();
+                if unsigned {
+                    Value::UInt(v as u64)
+                } else {
+                    Value::Int(v as i32 as i64)
+                }
+            }
+            ColumnType::LongLong => {
+                let v = Int8::decode(input)?.int();
+                if unsigned {
+                    Value::UInt(v)
+                } else {
+                    Value::Int(v as i64)
+                }
+            }
+            ColumnType::Float => Value::Float(f32::from_le_bytes(input.read_array()?)),
+            ColumnType::Double => Value::Double(f64::from_le_bytes(input.read_array()?)),
+            ColumnType::Date
+            | ColumnType::NewDate
+            | ColumnType::DateTime
+            | ColumnType::DateTime2
+            | ColumnType::Timestamp
+            | ColumnType::Timestamp2 => {
+                let len = input.read_u8_le()?;
+                let mut data = [0u8; 11];
+                let raw = input.read_vec(len as usize)?;
+                data.get_mut(..raw.len())
+                    .ok_or(DecodeError::InvalidData)?
+                    .copy_from_slice(&raw);
+                let year = u16::from_le_bytes([data[0], data[1]]);
+                if matches!(ty, ColumnType::Date | ColumnType::NewDate) {
+                    Value::Date {
+                        year,
+                        month: data[2],
+                        day: data[3],
+                    }
+                } else {
+                    Value::DateTime {
+                        year,
+                        month: data[2],
+                        day: data[3],
+                        hour: data[4],
+                        minute: data[5],
+                        second: data[6],
+                        micro_second: u32::from_le_bytes([data[7], data[8], data[9], data[10]]),
+                    }
+                }
+            }
+            ColumnType::Time | ColumnType::Time2 => {
+                let len = input.read_u8_le()?;
+                let mut data = [0u8; 12];
+                let raw = input.read_vec(len as usize)?;
+                data.get_mut(..raw.len())
+                    .ok_or(DecodeError::InvalidData)?
+                    .copy_from_slice(&raw);
+                Value::Time {
+                    negative: data[0] == 1,
+                    days: u32::from_le_bytes([data[1], data[2], data[3], data[4]]),
+                    hours: data[5],
+                    minutes: data[6],
+                    seconds: data[7],
+                    micro_seconds: u32::from_le_bytes([data[8], data[9], data[10], data[11]]),
+                }
+            }
+            ColumnType::Decimal | ColumnType::NewDecimal => Value::Decimal(get_var_str(input)?),
+            ColumnType::Bit | ColumnType::Geometry | ColumnType::Vector => {
+                Value::Bytes(get_var_bytes(input)?)
+            }
+            _ => string_or_bytes(charset, get_var_bytes(input)?),
+        };
+        Ok(value)
+    }
+
+    /// column type and unsigned flag used when binding value as statement parameter
+    pub fn binary_type(&self) -> (ColumnType, bool) {
+        match self {
+            Value::Null => (ColumnType::Null, false),
+            Value::Int(_) => (ColumnType::LongLong, false),
+            Value::UInt(_) => (ColumnType::LongLong, true),
+            Value::Float(_) => (ColumnType::Float, false),
+            Value::Double(_) => (ColumnType::Double, false),
+            Value::Decimal(_) => (ColumnType::NewDecimal, false),
+            Value::String(_) => (ColumnType::VarString, false),
+            Value::Bytes(_) => (ColumnType::Blob, false),
+            Value::Date { .. } => (ColumnType::Date, false),
+            Value::DateTime { .. } => (ColumnType::DateTime, false),
+            Value::Time { .. } => (ColumnType::Time, false),
+        }
+    }
+
+    /// encode as binary protocol value, NULL writes nothing
+    pub fn encode_binary(&self, buf: &mut BytesMut) {
+        match self {
+            Value::Null => {}
+            Value::Int(v) => buf.put_i64_le(*v),
+            Value::UInt(v) => buf.put_u64_le(*v),
+            Value::Float(v) => buf.put_f32_le(*v),
+            Value::Double(v) => buf.put_f64_le(*v),
+            Value::Decimal(v) | Value::String(v) => put_var_bytes(v, buf),
+            Value::Bytes(v) => put_var_bytes(v, buf),
+            Value::Date { year, month, day } => {
+                buf.put_u8(4);
+                buf.put_u16_le(*year);
+                buf.put_u8(*month);
+                buf.put_u8(*day);
+            }
+            Value::DateTime {
+                year,
+                month,
+                day,
+                hour,
+                minute,
+                second,
+                micro_second,
+            } => {
+                buf.put_u8(if *micro_second == 0 { 7 } else { 11 });
+                buf.put_u16_le(*year);
+                buf.extend_from_slice(&[*month, *day, *hour, *minute, *second]);
+                if *micro_second != 0 {
+                    buf.put_u32_le(*micro_second);
+                }
+            }
+            Value::Time {
+                negative,
+                days,
+                hours,
+                minutes,
+                seconds,
+                micro_seconds,
+            } => {
+                buf.put_u8(if *micro_seconds == 0 { 8 } else { 12 });
+                buf.put_u8(*negative as u8);
+                buf.put_u32_le(*days);
+                buf.extend_from_slice(&[*hours, *minutes, *seconds]);
+                if *micro_seconds != 0 {
+                    buf.put_u32_le(*micro_seconds);
+                }
+            }
+        }
+    }
+}
+
+/// value of binlog rows borrowing strings and bytes from row data, converted to [`Value`]
+/// on demand
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueRef<'a> {
+    String(&'a str),
+    /// binary string, blob, bit, JSON, geometry or string can not be decoded as utf-8
+    Bytes(&'a [u8]),
+    /// numbers, decimal, date and time, never string or bytes
+    Owned(Value),
+}
+
+impl<'a> ValueRef<'a> {
+    /// decode binlog row value at the beginning of `data` according to column type and
+    /// its metadata in table map event, returns value and its length
+    ///
+    /// signedness of integers comes from table map optional metadata, strings are decoded
+    /// as utf-8 when possible since charset is unknown, JSON is kept as binary.
+    ///
+    /// [doc](https://dev.mysql.com/doc/dev/mysql-server/latest/classmysql_1_1binlog_1_1event_1_1Table__map__event.html)
+    pub fn from_binlog(
+        ty: ColumnType,
+        meta: u16,
+        unsigned: bool,
+        data: &'a [u8],
+    ) -> Result<(Self, usize), DecodeError> {
+        let len = binlog_value_len(ty, meta, data)?;
+        let data = &data[..len];
+        let int = |data: &[u8]| {
+            let mut buf = [0; 8];
+            buf[..data.len()].copy_from_slice(data);
+            let v = u64::from_le_bytes(buf);
+            if unsigned {
+                Value::UInt(v)
+            } else {
+                // sign extend
+                let shift = 64 - data.len() * 8;
+                Value::Int(((v << shift) as i64) >> shift)
+            }
+        };
+        let value = match ty {
+            ColumnType::Null => Value::Null,
+            ColumnType::Tiny
+            | ColumnType::Short
+            | ColumnType::Int24
+            | ColumnType::Long
+            | ColumnType::LongLong => int(data),
+            ColumnType::Float => Value::Float(f32::from_le_bytes(data.try_into().unwrap())),
+            ColumnType::Double => Value::Double(f64::from_le_bytes(data.try_into().unwrap())),
+            ColumnType::Year => match data[0] {
+                0 => Value::Int(0),
+                year => Value::Int(year as i64 + 1900),
+            },
+            ColumnType::NewDecimal => Value::Decimal(decode_decimal(meta, data)),
+            ColumnType::Date | ColumnType::NewDate => {
+                let v = u32::from_le_bytes([data[0], data[1], data[2], 0]);
+                Value::Date {
+                    year: (v >> 9) as u16,
+                    month: (v >> 5 & 0xf) as u8,
+                    day: (v & 0x1f) as u8,
+                }
+            }
+            ColumnType::Time => {
+                let v = u32::from_le_bytes([data[0], data[1], data[2], 0]);
+                let v = ((v << 8) as i32) >> 8;
+                let hours = v.unsigned_abs() / 10000;
+                Value::Time {
+                    negative: v < 0,
+                    days: hours / 24,
+                    hours: (hours % 24) as u8,
+                    minutes: (v.unsigned_abs() / 100 % 100) as u8,
+                    seconds: (v.unsigned_abs() % 100) as u8,
+                    micro_seconds: 0,
+                }
+            }
+            ColumnType::Time2 => {
+                let packed = time_packed(meta, data);
+                let negative = packed < 0;
+                let packed = packed.unsigned_abs();
+                let hms = packed >> 24;
+                let hours = (hms >> 12) as u32 % (1 << 10);
+                Value::Time {
+                    negative,
+                    days: hours / 24,
+                    hours: (hours % 24) as u8,
+                    minutes: (hms >> 6 & 0x3f) as u8,
+                    seconds: (hms & 0x3f) as u8,
+                    micro_seconds: (packed % (1 << 24)) as u32,
+                }
+            }
+            ColumnType::Timestamp => {
+                date_time_from_unix(u32::from_le_bytes(data.try_into().unwrap()), 0)
+            }
+            ColumnType::Timestamp2 => {
+                let seconds = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+                date_time_from_unix(seconds, frac_micro(meta, &data[4..]))
+            }
+            ColumnType::DateTime => {
+                let v = u64::from_le_bytes(data.try_into().unwrap());
+                let (date, time) = (v / 1000000, v % 1000000);
+                Value::DateTime {
+                    year: (date / 10000) as u16,
+                    month: (date / 100 % 100) as u8,
+                    day: (date % 100) as u8,
+                    hour: (time / 10000) as u8,
+                    minute: (time / 100 % 100) as u8,
+                    second: (time % 100) as u8,
+                    micro_second: 0,
+                }
+            }
+            ColumnType::DateTime2 => {
+                let v = u64::from_be_bytes([0, 0, 0, data[0], data[1], data[2], data[3], data[4]]);
+                // sign bit is always set since negative datetime is not allowed
+                let v = v & !(1 << 39);
+                let (ymd, hms) = (v >> 17, v & 0x1ffff);
+                let ym = ymd >> 5;
+                Value::DateTime {
+                    year: (ym / 13) as u16,
+                    month: (ym % 13) as u8,
+                    day: (ymd & 0x1f) as u8,
+                    hour: (hms >> 12) as u8,
+                    minute: (hms >> 6 & 0x3f) as u8,
+                    second: (hms & 0x3f) as u8,
+                    micro_second: frac_micro(meta, &data[5..]),
+                }
+            }
+            ColumnType::VarChar | ColumnType::VarString => {
+                return Ok((str_or_bytes(&data[if meta < 256 { 1 } else { 2 }..]), len));
+            }
+            ColumnType::String => match string_meta(meta) {
+                (ColumnType::Enum | ColumnType::Set, _) => int_unsigned(data),
+                (_, max_len) => {
+                    return Ok((
+                        str_or_bytes(&data[if max_len < 256 { 1 } else { 2 }..]),
+                        len,
+                    ));
+                }
+            },
+            ColumnType::Enum | ColumnType::Set => int_unsigned(data),
+            ColumnType::Bit => return Ok((ValueRef::Bytes(data), len)),
+            ColumnType::Blob
+            | ColumnType::TinyBlob
+            | ColumnType::MediumBlob
+            | ColumnType::LongBlob => return Ok((str_or_bytes(&data[meta as usize..]), len)),
+            ColumnType::Json | ColumnType::Geometry | ColumnType::Vector => {
+                return Ok((ValueRef::Bytes(&data[meta as usize..]), len));
+            }
+            _ => return Err(DecodeError::InvalidData),
+        };
+        Ok((ValueRef::Owned(value), len))
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, ValueRef::Owned(Value::Null))
+    }
+
+    pub fn as_str(&self) -> Option<&'a str> {
+        match *self {
+            ValueRef::String(s) => Some(s),
+            ValueRef::Bytes(b) => std::str::from_utf8(b).ok(),
+            ValueRef::Owned(_) => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match *self {
+            ValueRef::String(s) => Some(s.as_bytes()),
+            ValueRef::Bytes(b) => Some(b),
+            ValueRef::Owned(_) => None,
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        self.clone().into()
+    }
+}
+
+impl From<ValueRef<'_>> for Value {
+    fn from(value: ValueRef<'_>) -> Self {
+        match value {
+            ValueRef::String(s) => Value::String(s.to_string()),
+            ValueRef::Bytes(b) => Value::Bytes(Bytes::copy_from_slice(b)),
+            ValueRef::Owned(value) => value,
+        }
+    }
+}
+
+fn str_or_bytes(data: &[u8]) -> ValueRef<'_> {
+    match std::str::from_utf8(data) {
+        Ok(s) => ValueRef::String(s),
+        Err(_) => ValueRef::Bytes(data),
+    }
+}
+
+/// length of binlog row value at the beginning of `data`, used to skip a value without
+/// decoding it
+pub fn binlog_value_len(ty: ColumnType, meta: u16, data: &[u8]) -> Result<usize, DecodeError> {
+    // length prefix of `n` bytes followed by data
+    let prefixed = |n: usize| {
+        let prefix = data.get(..n).ok_or(DecodeError::NoEnoughData)?;
+        let mut buf = [0; 8];
+        buf[..n].copy_from_slice(prefix);
+        Ok::<_, DecodeError>(n + u64::from_le_bytes(buf) as usize)
+    };
+    let len = match ty {
+        ColumnType::Null => 0,
+        ColumnType::Tiny | ColumnType::Year => 1,
+        ColumnType::Short => 2,
+        ColumnType::Int24 | ColumnType::Date | ColumnType::NewDate | ColumnType::Time => 3,
+        ColumnType::Long | ColumnType::Float | ColumnType::Timestamp => 4,
+        ColumnType::LongLong | ColumnType::Double | ColumnType::DateTime => 8,
+        ColumnType::NewDecimal => decimal_len(meta),
+        ColumnType::Time2 => 3 + frac_len(meta),
+        ColumnType::Timestamp2 => 4 + frac_len(meta),
+        ColumnType::DateTime2 => 5 + frac_len(meta),
+        ColumnType::VarChar | ColumnType::VarString => prefixed(if meta < 256 { 1 } else { 2 })?,
+        ColumnType::String => match string_meta(meta) {
+            (ColumnType::Enum | ColumnType::Set, len) => len as usize,
+            (_, max_len) => prefixed(if max_len < 256 { 1 } else { 2 })?,
+        },
+        ColumnType::Enum | ColumnType::Set => (meta & 0xff) as usize,
+        ColumnType::Bit => (meta >> 8) as usize + ((meta & 0xff) as usize).div_ceil(8),
+        ColumnType::Blob
+        | ColumnType::TinyBlob
+        | ColumnType::MediumBlob
+        | ColumnType::LongBlob
+        | ColumnType::Json
+        | ColumnType::Geometry
+        | ColumnType::Vector => match meta {
+            1..=4 => prefixed(meta as usize)?,
+            _ => return Err(DecodeError::InvalidData),
+        },
+        _ => return Err(DecodeError::InvalidData),
+    };
+    if data.len() < len {
+        return Err(DecodeError::NoEnoughData);
+    }
+    Ok(len)
+}
+
+/// real type and max length of `MYSQL_TYPE_STRING` column, enum and set are stored as
+/// string in table map
+fn string_meta(meta: u16) -> (ColumnType, u16) {
+    let (real_type, len) = ((meta >> 8) as u8, meta & 0xff);
+    if real_type & 0x30 != 0x30 {
+        // max length over 255 takes 2 bits of real type
+        let len = len | (((real_type & 0x30) ^ 0x30) as u16) << 4;
+        (
+            ColumnType::try_from(real_type | 0x30).unwrap_or(ColumnType::String),
+            len,
+        )
+    } else {
+        (
+            ColumnType::try_from(real_type).unwrap_or(ColumnType::String),
+            len,
+        )
+    }
+}
+
+fn int_unsigned(data: &[u8]) -> Value {
+    let mut buf = [0; 8];
+    buf[..data.len()].copy_from_slice(data);
+    Value::UInt(u64::from_le_bytes(buf))
+}
+
+/// bytes of fractional seconds with precision `fsp`
+fn frac_len(fsp: u16) -> usize {
+    (fsp as usize).div_ceil(2)
+}
+
+/// fractional seconds stored in big endian, scaled to micro seconds
+fn frac_micro(fsp: u16, data: &[u8]) -> u32 {
+    let frac = data
+        .iter()
+        .take(frac_len(fsp))
+        .fold(0u32, |v, b| v << 8 | *b as u32);
+    match frac_len(fsp) {
+        1 => frac * 10000,
+        2 => frac * 100,
+        _ => frac,
+    }
+}
+
+/// packed time as `my_time_packed_from_binary`, hms in high bits and micro seconds in
+/// low 24 bits
+fn time_packed(fsp: u16, data: &[u8]) -> i64 {
+    let int = (u32::from_be_bytes([0, data[0], data[1], data[2]]) as i64) - 0x800000;
+    let (frac, scale, bits) = match frac_len(fsp) {
+        0 => return int << 24,
+        1 => (data[3] as i64, 10000, 8),
+        2 => (u16::from_be_bytes([data[3], data[4]]) as i64, 100, 16),
+        _ => {
+            let v =
+                u64::from_be_bytes([0, 0, data[0], data[1], data[2], data[3], data[4], data[5]]);
+            return v as i64 - 0x800000000000;
+        }
+    };
+    // negative time stores fraction as complement
+    let (int, frac) = if int < 0 && frac != 0 {
+        (int + 1, frac - (1 << bits))
+    } else {
+        (int, frac)
+    };
+    (int << 24) + frac * scale
+}
+
+/// UTC date time of unix timestamp
+fn date_time_from_unix(seconds: u32, micro_second: u32) -> Value {
+    // civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let days = (seconds / 86400) as i64 + 719468;
+    let era = days / 146097;
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    let time = seconds % 86400;
+    Value::DateTime {
+        year: year as u16,
+        month: month as u8,
+        day: day as u8,
+        hour: (time / 3600) as u8,
+        minute: (time / 60 % 60) as u8,
+        second: (time % 60) as u8,
+        micro_second,
+    }
+}
+
+/// bytes of leftover decimal digits, 9 digits are stored in 4 bytes
+const DIG_TO_BYTES: [usize; 10] = [0, 1, 1, 2, 2, 3, 3, 4, 4, 4];
+
+/// length of binary decimal, precision in high byte and scale in low byte of `meta`
+fn decimal_len(meta: u16) -> usize {
+    let (precision, scale) = ((meta >> 8) as usize, (meta & 0xff) as usize);
+    let intg = precision.saturating_sub(scale);
+    intg / 9 * 4 + DIG_TO_BYTES[intg % 9] + scale / 9 * 4 + DIG_TO_BYTES[scale % 9]
+}
+
+/// decode binary decimal of `decimal2bin` into string like `-12.340`
+fn decode_decimal(meta: u16, data: &[u8]) -> String {
+    let (precision, scale) = ((meta >> 8) as usize, (meta & 0xff) as usize);
+    let intg = precision.saturating_sub(scale);
+    if data.is_empty() {
+        return "0".to_string();
+    }
+    let negative = data[0] & 0x80 == 0;
+    let mut data = data.to_vec();
+    data[0] ^= 0x80;
+    if negative {
+        data.iter_mut().for_each(|b| *b = !*b);
+    }
+    let mut input = &data[..];
+    let mut take = |len: usize| {
+        let (head, rest) = input.split_at(len);
+        input = rest;
+        head.iter().fold(0u32, |v, b| v << 8 | *b as u32)
+    };
+    let mut int = String::new();
+    if intg % 9 > 0 {
+        int.push_str(&take(DIG_TO_BYTES[intg % 9]).to_string());
+    }
+    for _ in 0..intg / 9 {
+        int.push_str(&format!("{:09}", take(4)));
+    }
+    let int = int.trim_start_matches('0');
+    let mut s = String::new();
+    if negative {
+        s.push('-');
+    }
+    s.push_str(if int.is_empty() { "0" } else { int });
+    if scale > 0 {
+        s.push('.');
+        for _ in 0..scale / 9 {
+            s.push_str(&format!("{:09}", take(4)));
+        }
+        if scale % 9 > 0 {
+            let digits = scale % 9;
+            s.push_str(&format!("{:0digits$}", take(DIG_TO_BYTES[digits])));
+        }
+    }
+    s
+}
+
+fn string_or_bytes(charset: u16, data: Bytes) -> Value {
+    if charset == BINARY_CHARSET {
+        return Value::Bytes(data);
+    }
+    match std::str::from_utf8(&data) {
+        Ok(s) => Value::String(s.to_string()),
+        Err(_) => Value::Bytes(data),
+    }
+}
+
+fn parse_num<T: std::str::FromStr>(s: &str) -> Result<T, DecodeError> {
+    s.parse().map_err(|_| DecodeError::InvalidData)
+}
+
+/// parse fraction part `.123` into micro seconds
+fn parse_micro(frac: Option<&str>) -> Result<u32, DecodeError> {
+    match frac {
+        None => Ok(0),
+        Some(frac) if frac.len() <= 6 => {
+            Ok(parse_num::<u32>(frac)? * 10u32.pow(6 - frac.len() as u32))
+        }
+        Some(_) => Err(DecodeError::InvalidData),
+    }
+}
+
+/// `YYYY-MM-DD` or `YYYY-MM-DD hh:mm:ss[.ffffff]`
+fn parse_date_time(s: &str, with_time: bool) -> Result<Value, DecodeError> {
+    let (date, time) = match s.split_once(' ') {
+        Some((date, time)) => (date, Some(time)),
+        None => (s, None),
+    };
+    let mut parts = date.splitn(3, '-');
+    let mut next = || parts.next().ok_or(DecodeError::InvalidData);
+    let (year, month, day) = (
+        parse_num(next()?)?,
+        parse_num(next()?)?,
+        parse_num(next()?)?,
+    );
+    if !with_time {
+        return Ok(Value::Date { year, month, day });
+    }
+    let (hour, minute, second, micro_second) = match time {
+        Some(time) => {
+            let (time, frac) = match time.split_once('.') {
+                Some((time, frac)) => (time, Some(frac)),
+                None => (time, None),
+            };
+            let mut parts = time.splitn(3, ':');
+            let mut next = || parts.next().ok_or(DecodeError::InvalidData);
+            (
+                parse_num(next()?)?,
+                parse_num(next()?)?,
+                parse_num(next()?)?,
+                parse_micro(frac)?,
+            )
+        }
+        None => (0, 0, 0, 0),
+    };
+    Ok(Value::DateTime {
+        year,
+        month,
+        day,
+        hour,
+        minute,
+        second,
+        micro_second,
+    })
+}
+
+/// `[-]hhh:mm:ss[.ffffff]`, hours may exceed 24
+fn parse_time(s: &str) -> Result<Value, DecodeError> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, s),
+    };
+    let (time, frac) = match s.split_once('.') {
+        Some((time, frac)) => (time, Some(frac)),
+        None => (s, None),
+    };
+    let mut parts = time.splitn(3, ':');
+    let mut next = || parts.next().ok_or(DecodeError::InvalidData);
+    let total_hours: u32 = parse_num(next()?)?;
+    let minutes = parse_num(next()?)?;
+    let seconds = parse_num(next()?)?;
+    Ok(Value::Time {
+        negative,
+        days: total_hours / 24,
+        hours: (total_hours % 24) as u8,
+        minutes,
+        seconds,
+        micro_seconds: parse_micro(frac)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_text() {
+        let text = |ty, flags, data: &str| {
+            Value::from_text(ty, flags, 255, Bytes::copy_from_slice(data.as_bytes()))
+        };
+        assert_eq!(
+            text(
+                ColumnType::LongLong,
+                ColumnFlags::UNSIGNED,
+                "18446744073709551615"
+            )
+            .unwrap(),
+            Value::UInt(u64::MAX)
+        );
+        assert_eq!(
+            text(ColumnType::Tiny, ColumnFlags::empty(), "-1").unwrap(),
+            Value::Int(-1)
+        );
+        assert_eq!(
+            text(
+                ColumnType::DateTime,
+                ColumnFlags::empty(),
+                "2023-01-02 03:04:05.12"
+            )
+            .unwrap(),
+            Value::DateTime {
+                year: 2023,
+                month: 1,
+                day: 2,
+                hour: 3,
+                minute: 4,
+                second: 5,
+                micro_second: 120000
+            }
+        );
+        assert_eq!(
+            text(ColumnType::Time, ColumnFlags::empty(), "-838:59:59").unwrap(),
+            Value::Time {
+                negative: true,
+                days: 34,
+                hours: 22,
+                minutes: 59,
+                seconds: 59,
+                micro_seconds: 0
+            }
+        );
+        assert_eq!(
+            Value::from_text(
+                ColumnType::Blob,
+                ColumnFlags::BINARY,
+                BINARY_CHARSET,
+                Bytes::from_static(b"\xff")
+            )
+            .unwrap(),
+            Value::Bytes(Bytes::from_static(&[0xff]))
+        );
+        assert!(text(ColumnType::Long, ColumnFlags::empty(), "abc").is_err());
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        let values = [
+            Value::Int(-2),
+            Value::UInt(u64::MAX),
+            Value::Double(1.5),
+            Value::String("foo".into()),
+            Value::Date {
+                year: 2023,
+                month: 4,
+                day: 5,
+            },
+            Value::DateTime {
+                year: 2023,
+                month: 4,
+                day: 5,
+                hour: 6,
+                minute: 7,
+                second: 8,
+                micro_second: 9,
+            },
+            Value::Time {
+                negative: true,
+                days: 1,
+                hours: 2,
+                minutes: 3,
+                seconds: 4,
+                micro_seconds: 0,
+            },
+        ];
+        for value in values {
+            let mut buf = BytesMut::new();
+            value.encode_binary(&mut buf);
+            let (ty, unsigned) = value.binary_type();
+            let flags = if unsigned {
+                ColumnFlags::UNSIGNED
+            } else {
+                ColumnFlags::empty()
+            };
+            let decoded = Value::from_binary(ty, flags, 255, &mut buf).unwrap();
+            assert_eq!(decoded, value);
+            assert!(buf.is_empty());
+        }
+        let mut buf = BytesMut::from(&[0xfe][..]);
+        assert_eq!(
+            Value::from_binary(ColumnType::Tiny, ColumnFlags::empty(), 63, &mut buf).unwrap(),
+            Value::Int(-2)
+        );
+    }
+
+    #[test]
+    fn test_from_binlog() {
+        let binlog = |ty, meta, data: &[u8]| {
+            let (value, len) = ValueRef::from_binlog(ty, meta, false, data).unwrap();
+            assert_eq!(len, data.len());
+            value.to_value()
+        };
+        assert_eq!(
+            binlog(ColumnType::Int24, 0, &[0xff, 0xff, 0xff]),
+            Value::Int(-1)
+        );
+        assert_eq!(
+            ValueRef::from_binlog(ColumnType::Tiny, 0, true, &[0xff]).unwrap(),
+            (ValueRef::Owned(Value::UInt(255)), 1)
+        );
+        assert_eq!(
+            binlog(
+                ColumnType::NewDecimal,
+                10 << 8 | 4,
+                &[0x7f, 0xfb, 0x2d, 0xe9, 0xd1]
+            ),
+            Value::Decimal("-1234.5678".into())
+        );
+        assert_eq!(
+            binlog(
+                ColumnType::DateTime2,
+                4,
+                &[0x99, 0xaf, 0xca, 0x61, 0xc8, 0x04, 0xd2]
+            ),
+            Value::DateTime {
+                year: 2023,
+                month: 4,
+                day: 5,
+                hour: 6,
+                minute: 7,
+                second: 8,
+                micro_second: 123400
+            }
+        );
+        assert_eq!(
+            binlog(ColumnType::Timestamp2, 0, &[0x64, 0x2d, 0x0f, 0x18]),
+            binlog(ColumnType::Timestamp, 0, &[0x18, 0x0f, 0x2d, 0x64]),
+        );
+        assert_eq!(
+            binlog(ColumnType::Timestamp, 0, &[0x18, 0x0f, 0x2d, 0x64]),
+            Value::DateTime {
+                year: 2023,
+                month: 4,
+                day: 5,
+                hour: 6,
+                minute: 3,
+                second: 4,
+                micro_second: 0
+            }
+        );
+        assert_eq!(
+            binlog(ColumnType::Time2, 0, &[0x7f, 0xff, 0xff]),
+            Value::Time {
+                negative: true,
+                days: 0,
+                hours: 0,
+                minutes: 0,
+                seconds: 1,
+                micro_seconds: 0
+            }
+        );
+        // varchar(100) of 4 bytes charset takes 2 bytes length
+        assert_eq!(
+            binlog(ColumnType::VarChar, 400, &[3, 0, b'a', b'b', b'c']),
+            Value::String("abc".into())
+        );
+        // char(10) as string type with real type in high byte
+        assert_eq!(
+            binlog(ColumnType::String, 0xfe28, &[2, b'x', b'y']),
+            Value::String("xy".into())
+        );
+        assert_eq!(binlog(ColumnType::String, 0xf701, &[2]), Value::UInt(2));
+        assert!(ValueRef::from_binlog(ColumnType::Blob, 2, false, &[3, 0, b'a']).is_err());
+    }
+}
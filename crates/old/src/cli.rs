@@ -74,6 +74,7 @@ fn init_log(debug: bool) -> Handle {
     log4rs::init_config(config).unwrap()
 }
 
+/// deprecated, whole file is loaded into memory, use `bcrab events` which reads one event at a time
 fn read_input(path: &str) -> std::io::Result<(usize, Vec<u8>)> {
     let mut f = File::open(path)?;
     let mut buf = vec![];
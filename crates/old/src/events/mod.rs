@@ -416,6 +416,9 @@ impl Event {
         }
     }
 
+    /// parse all events of a binlog file loaded in memory
+    ///
+    /// deprecated, use `BinlogFileReader` of `boxercrab` to read events one at a time
     pub fn from_bytes<'a>(input: &'a [u8]) -> IResult<&'a [u8], Vec<Event>> {
         let (i, _) = check_start(input)?;
         many1(Self::parse)(i)